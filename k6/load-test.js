@@ -31,6 +31,7 @@ function randomName() {
 
 let profile;
 let cookie;
+let csrfToken;
 let program;
 
 export default function () {
@@ -44,12 +45,14 @@ export default function () {
     });
 
     cookie = auth.cookies["JWT"][0].value;
+    csrfToken = auth.cookies["XSRF-TOKEN"][0].value;
 
     const sets = 8;
 
     const headers = {
       "content-type": "application/json",
-      Cookie: "JWT=" + encodeURIComponent(cookie),
+      Cookie: "JWT=" + encodeURIComponent(cookie) + "; XSRF-TOKEN=" + encodeURIComponent(csrfToken),
+      "X-XSRF-TOKEN": csrfToken,
     };
 
     // create a profile
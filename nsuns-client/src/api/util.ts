@@ -59,12 +59,34 @@ export const json =
 
 type Fetcher = (...args: FetchParams) => Promise<Response>;
 
+const CSRF_COOKIE = "XSRF-TOKEN";
+
+const CSRF_HEADER = "X-XSRF-TOKEN";
+
+const getCookie = (name: string): string | undefined =>
+  document.cookie
+    .split("; ")
+    .find((cookie) => cookie.startsWith(`${name}=`))
+    ?.substring(name.length + 1);
+
+/**
+ * Repeat the CSRF cookie in a header, to prove the request was made by this app
+ */
+const csrfHeaders = (method: string): Record<string, string> => {
+  const token = method === "GET" ? undefined : getCookie(CSRF_COOKIE);
+  return token ? { [CSRF_HEADER]: decodeURIComponent(token) } : {};
+};
+
 const req =
   (method: string = "GET"): Fetcher =>
   (...args: FetchParams) =>
     fetch(args[0], {
       method: method,
       ...args[1],
+      headers: {
+        ...csrfHeaders(method),
+        ...(args[1]?.headers as Record<string, string> | undefined),
+      },
     }).then(processResponse);
 
 export const get = req();
@@ -0,0 +1,10 @@
+-- Single-use tokens to set a new password without knowing the current one.
+-- Only a digest of the token is stored.
+CREATE TABLE password_reset_tokens (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  token_hash VARCHAR NOT NULL,
+  created_on TIMESTAMPTZ NOT NULL DEFAULT now(),
+  exp TIMESTAMPTZ NOT NULL,
+  CONSTRAINT unique_reset_token_hash UNIQUE (token_hash)
+);
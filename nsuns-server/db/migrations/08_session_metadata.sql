@@ -0,0 +1,10 @@
+ALTER TABLE sessions
+ADD COLUMN created_on TIMESTAMPTZ NOT NULL DEFAULT now();
+
+ALTER TABLE sessions
+ADD COLUMN last_seen TIMESTAMPTZ NOT NULL DEFAULT now();
+
+ALTER TABLE sessions
+ADD COLUMN user_agent VARCHAR;
+
+CREATE INDEX sessions_by_owner_id ON sessions(owner_id);
@@ -0,0 +1,15 @@
+-- Long-lived, named tokens for scripts and integrations, sent as a bearer token.
+-- Only a digest of the token is stored.
+CREATE TABLE access_tokens (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  name VARCHAR NOT NULL,
+  -- 0 is read-only, 1 is read-write
+  scope SMALLINT NOT NULL,
+  token_hash VARCHAR NOT NULL,
+  created_on TIMESTAMPTZ NOT NULL DEFAULT now(),
+  last_used TIMESTAMPTZ,
+  CONSTRAINT unique_access_token_hash UNIQUE (token_hash)
+);
+
+CREATE INDEX access_tokens_by_user_id ON access_tokens(user_id);
@@ -0,0 +1,23 @@
+-- Users provisioned by an external identity provider do not have a password.
+ALTER TABLE users ALTER COLUMN password_hash DROP NOT NULL;
+
+-- Links a user to a subject at an OpenID Connect issuer.
+CREATE TABLE user_identities (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  issuer VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  created_on TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT unique_identity UNIQUE (issuer, subject)
+);
+
+-- Authorization requests that were sent to the identity provider, and have not returned yet.
+-- Only a digest of the state parameter is stored.
+CREATE TABLE oidc_pending_logins (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  state_hash VARCHAR NOT NULL,
+  code_verifier VARCHAR NOT NULL,
+  nonce VARCHAR NOT NULL,
+  exp TIMESTAMPTZ NOT NULL,
+  CONSTRAINT unique_oidc_state_hash UNIQUE (state_hash)
+);
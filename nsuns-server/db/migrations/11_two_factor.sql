@@ -0,0 +1,31 @@
+-- TOTP second factor for a user. The secret is stored until enrollment is confirmed with a valid code.
+CREATE TABLE user_totp (
+  user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+  secret BYTEA NOT NULL,
+  enabled BOOLEAN NOT NULL DEFAULT false,
+  -- the time step of the last accepted code, so a code cannot be used twice
+  last_used_step BIGINT,
+  -- wrong codes entered by a logged-in user since the last accepted one
+  failed_attempts SMALLINT NOT NULL DEFAULT 0,
+  created_on TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Single-use codes to log in when the authenticator is not available.
+-- Only a digest of the code is stored.
+CREATE TABLE recovery_codes (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  code_hash VARCHAR NOT NULL,
+  CONSTRAINT unique_recovery_code UNIQUE (user_id, code_hash)
+);
+
+-- Logins that passed the password check, and are waiting for the second factor.
+-- Only a digest of the challenge token is stored.
+CREATE TABLE login_challenges (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  token_hash VARCHAR NOT NULL,
+  attempts SMALLINT NOT NULL DEFAULT 0,
+  exp TIMESTAMPTZ NOT NULL,
+  CONSTRAINT unique_login_challenge_hash UNIQUE (token_hash)
+);
@@ -0,0 +1,7 @@
+-- 0 is a regular user, 1 is an administrator
+ALTER TABLE users
+ADD COLUMN role SMALLINT NOT NULL DEFAULT 0;
+
+-- disabled users cannot log in, and their sessions and access tokens are rejected
+ALTER TABLE users
+ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT false;
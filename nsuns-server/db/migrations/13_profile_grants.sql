@@ -0,0 +1,13 @@
+-- Access to a profile, granted by its owner to another user, such as a coach.
+-- Rows that belong to the profile keep the owner_id of the profile's owner.
+CREATE TABLE profile_grants (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  -- 0 is read-only, 1 is read-write
+  access SMALLINT NOT NULL,
+  created_on TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CONSTRAINT unique_profile_grant UNIQUE (profile_id, user_id)
+);
+
+CREATE INDEX profile_grants_by_user_id ON profile_grants(user_id);
@@ -0,0 +1,57 @@
+-- Programs are split into numbered weeks, starting at 0, each with its own ordered sets for every day.
+-- The primary key is deferrable so weeks can be renumbered in a single statement.
+CREATE TABLE program_weeks (
+  program_id UUID NOT NULL REFERENCES programs(id) ON DELETE CASCADE,
+  week SMALLINT NOT NULL CHECK (week >= 0),
+  set_ids_sunday UUID [] NOT NULL DEFAULT '{}',
+  set_ids_monday UUID [] NOT NULL DEFAULT '{}',
+  set_ids_tuesday UUID [] NOT NULL DEFAULT '{}',
+  set_ids_wednesday UUID [] NOT NULL DEFAULT '{}',
+  set_ids_thursday UUID [] NOT NULL DEFAULT '{}',
+  set_ids_friday UUID [] NOT NULL DEFAULT '{}',
+  set_ids_saturday UUID [] NOT NULL DEFAULT '{}',
+  owner_id UUID REFERENCES owners(id) ON DELETE CASCADE,
+  CONSTRAINT program_weeks_pkey PRIMARY KEY (program_id, week) DEFERRABLE INITIALLY IMMEDIATE
+);
+
+-- existing programs become single-week programs
+INSERT INTO program_weeks (
+    program_id,
+    week,
+    set_ids_sunday,
+    set_ids_monday,
+    set_ids_tuesday,
+    set_ids_wednesday,
+    set_ids_thursday,
+    set_ids_friday,
+    set_ids_saturday,
+    owner_id
+  )
+SELECT id,
+  0,
+  set_ids_sunday,
+  set_ids_monday,
+  set_ids_tuesday,
+  set_ids_wednesday,
+  set_ids_thursday,
+  set_ids_friday,
+  set_ids_saturday,
+  owner_id
+FROM programs;
+
+ALTER TABLE programs DROP COLUMN set_ids_sunday;
+
+ALTER TABLE programs DROP COLUMN set_ids_monday;
+
+ALTER TABLE programs DROP COLUMN set_ids_tuesday;
+
+ALTER TABLE programs DROP COLUMN set_ids_wednesday;
+
+ALTER TABLE programs DROP COLUMN set_ids_thursday;
+
+ALTER TABLE programs DROP COLUMN set_ids_friday;
+
+ALTER TABLE programs DROP COLUMN set_ids_saturday;
+
+ALTER TABLE program_sets
+ADD COLUMN week SMALLINT NOT NULL DEFAULT 0 CHECK (week >= 0);
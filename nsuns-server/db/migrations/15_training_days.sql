@@ -0,0 +1,42 @@
+-- Named training days, such as "Day A", that a program rotates through in order instead of following weekdays.
+-- The position is deferrable so training days can be reordered in a single statement.
+CREATE TABLE training_days (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  program_id UUID NOT NULL REFERENCES programs(id) ON DELETE CASCADE,
+  name VARCHAR NOT NULL,
+  position SMALLINT NOT NULL CHECK (position >= 0),
+  owner_id UUID REFERENCES owners(id) ON DELETE CASCADE,
+  CONSTRAINT training_days_position_key UNIQUE (program_id, position) DEFERRABLE INITIALLY IMMEDIATE
+);
+
+-- A set belongs to either a weekday, or a training day.
+-- Sets of a training day are ordered by their own column, since training days have no set id arrays.
+ALTER TABLE program_sets
+ALTER COLUMN day DROP NOT NULL;
+
+ALTER TABLE program_sets
+ADD COLUMN training_day_id UUID REFERENCES training_days(id) ON DELETE CASCADE;
+
+ALTER TABLE program_sets
+ADD COLUMN ordering INT;
+
+ALTER TABLE program_sets
+ADD CONSTRAINT program_sets_day_or_training_day CHECK (
+    (day IS NULL) <> (training_day_id IS NULL)
+    AND (training_day_id IS NULL) = (ordering IS NULL)
+  );
+
+ALTER TABLE program_sets
+ADD CONSTRAINT program_sets_training_day_ordering_key UNIQUE (training_day_id, week, ordering) DEFERRABLE INITIALLY IMMEDIATE;
+
+-- Each time a profile completes a training day, so we know which one comes next
+CREATE TABLE training_logs (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  profile_id UUID NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+  program_id UUID NOT NULL REFERENCES programs(id) ON DELETE CASCADE,
+  training_day_id UUID NOT NULL REFERENCES training_days(id) ON DELETE CASCADE,
+  completed_on TIMESTAMP NOT NULL DEFAULT now(),
+  owner_id UUID REFERENCES owners(id) ON DELETE CASCADE
+);
+
+CREATE INDEX training_logs_by_profile_id_completed_on ON training_logs(profile_id, completed_on);
@@ -0,0 +1,63 @@
+-- Sets of a weekday are ordered by their own column, like sets of training days,
+-- instead of by set id arrays on the week that could drift from the sets they point to.
+ALTER TABLE program_sets DROP CONSTRAINT program_sets_day_or_training_day;
+
+UPDATE program_sets s
+SET ordering = o.index
+FROM program_weeks w
+  CROSS JOIN LATERAL (
+    SELECT 0 AS day, a.id, a.index FROM unnest(w.set_ids_sunday) WITH ORDINALITY a(id, index)
+    UNION ALL
+    SELECT 1, a.id, a.index FROM unnest(w.set_ids_monday) WITH ORDINALITY a(id, index)
+    UNION ALL
+    SELECT 2, a.id, a.index FROM unnest(w.set_ids_tuesday) WITH ORDINALITY a(id, index)
+    UNION ALL
+    SELECT 3, a.id, a.index FROM unnest(w.set_ids_wednesday) WITH ORDINALITY a(id, index)
+    UNION ALL
+    SELECT 4, a.id, a.index FROM unnest(w.set_ids_thursday) WITH ORDINALITY a(id, index)
+    UNION ALL
+    SELECT 5, a.id, a.index FROM unnest(w.set_ids_friday) WITH ORDINALITY a(id, index)
+    UNION ALL
+    SELECT 6, a.id, a.index FROM unnest(w.set_ids_saturday) WITH ORDINALITY a(id, index)
+  ) o
+WHERE s.program_id = w.program_id
+  AND s.week = w.week
+  AND s.day = o.day
+  AND s.id = o.id;
+
+-- close the gaps left by ids of deleted sets, and keep sets that were missing from every array at the end of their day
+UPDATE program_sets s
+SET ordering = o.ordering
+FROM (
+    SELECT id,
+      ROW_NUMBER() OVER (
+        PARTITION BY program_id, week, day
+        ORDER BY ordering NULLS LAST, id
+      ) - 1 AS ordering
+    FROM program_sets
+    WHERE day IS NOT NULL
+  ) o
+WHERE s.id = o.id;
+
+ALTER TABLE program_sets
+ALTER COLUMN ordering SET NOT NULL;
+
+ALTER TABLE program_sets
+ADD CONSTRAINT program_sets_day_or_training_day CHECK ((day IS NULL) <> (training_day_id IS NULL));
+
+ALTER TABLE program_sets
+ADD CONSTRAINT program_sets_day_ordering_key UNIQUE (program_id, week, day, ordering) DEFERRABLE INITIALLY IMMEDIATE;
+
+ALTER TABLE program_weeks DROP COLUMN set_ids_sunday;
+
+ALTER TABLE program_weeks DROP COLUMN set_ids_monday;
+
+ALTER TABLE program_weeks DROP COLUMN set_ids_tuesday;
+
+ALTER TABLE program_weeks DROP COLUMN set_ids_wednesday;
+
+ALTER TABLE program_weeks DROP COLUMN set_ids_thursday;
+
+ALTER TABLE program_weeks DROP COLUMN set_ids_friday;
+
+ALTER TABLE program_weeks DROP COLUMN set_ids_saturday;
@@ -0,0 +1,28 @@
+use anyhow::{bail, Context};
+
+use crate::{
+    auth::user::{update_role, Role},
+    db,
+    settings::Settings,
+};
+
+/// Change the role of `username`, such as to appoint the first administrator.
+///
+/// This is intended to be run from the command line by whoever operates the server.
+pub async fn update_role_from_cli(
+    settings: &Settings,
+    username: &str,
+    role: Role,
+) -> anyhow::Result<()> {
+    let pool = db::prepare(&settings.database).await?;
+    let mut conn = crate::acquire_unlogged!(&pool).await?;
+
+    if !update_role(username, role, &mut *conn)
+        .await
+        .context("failed to update role")?
+    {
+        bail!("user {username:?} does not exist");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::Utc;
+use http::StatusCode;
+use uuid::Uuid;
+
+use crate::{
+    acquire,
+    auth::{
+        extract::AdminClaims,
+        reset::mint_reset_token,
+        token::{revoke_user_sessions, SessionLifetimes},
+        user::{select_user_by_id, update_disabled},
+    },
+    db::{commit_ok, Pool},
+    error::{ErrorWithStatus, OperationResult},
+    integrity::{check, IntegrityReport},
+    into_log_server_error, transaction,
+};
+
+use super::model::{CreatedResetToken, OwnerCounts, UserSummary};
+
+fn user_not_found() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(StatusCode::NOT_FOUND, anyhow!("User not found"))
+}
+
+/// List every registered user, with when they were last active
+#[tracing::instrument(skip_all)]
+pub async fn list_users(
+    State(pool): State<Pool>,
+    _admin: AdminClaims,
+) -> OperationResult<Json<Vec<UserSummary>>> {
+    let mut conn = acquire!(&pool).await?;
+
+    UserSummary::select_all(&mut *conn).await.map(Json)
+}
+
+/// Count owners, including anonymous owners that are waiting to be cleaned up
+#[tracing::instrument(skip_all)]
+pub async fn owner_counts(
+    State(pool): State<Pool>,
+    _admin: AdminClaims,
+) -> OperationResult<Json<OwnerCounts>> {
+    let mut conn = acquire!(&pool).await?;
+
+    OwnerCounts::select(&mut *conn).await.map(Json)
+}
+
+/// Check every program for sets with problems, without changing anything
+#[tracing::instrument(skip_all)]
+pub async fn check_integrity(
+    State(pool): State<Pool>,
+    _admin: AdminClaims,
+) -> OperationResult<Json<IntegrityReport>> {
+    let mut tx = transaction!(&pool).await?;
+    let res = check(false, &mut tx).await.map(Json);
+    commit_ok(res, tx).await
+}
+
+/// Repair the sets with problems in every program, reporting which sets were changed
+#[tracing::instrument(skip_all)]
+pub async fn repair_integrity(
+    State(pool): State<Pool>,
+    _admin: AdminClaims,
+) -> OperationResult<Json<IntegrityReport>> {
+    let mut tx = transaction!(&pool).await?;
+    let res = check(true, &mut tx).await.map(Json);
+    commit_ok(res, tx).await
+}
+
+/// Prevent a user from logging in. Their existing sessions and access tokens are rejected until they are enabled again.
+#[tracing::instrument(skip_all)]
+pub async fn disable_user(
+    State(pool): State<Pool>,
+    AdminClaims(claims): AdminClaims,
+    Path(id): Path<Uuid>,
+) -> OperationResult<StatusCode> {
+    if claims.user_id == Some(id) {
+        return Err(ErrorWithStatus::new(
+            StatusCode::CONFLICT,
+            anyhow!("Administrators cannot disable themselves"),
+        ));
+    }
+
+    let mut conn = acquire!(&pool).await?;
+
+    if update_disabled(id, true, &mut *conn)
+        .await
+        .context("failed to disable user")
+        .map_err(into_log_server_error!())?
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(user_not_found())
+    }
+}
+
+/// Allow a disabled user to log in again
+#[tracing::instrument(skip_all)]
+pub async fn enable_user(
+    State(pool): State<Pool>,
+    _admin: AdminClaims,
+    Path(id): Path<Uuid>,
+) -> OperationResult<StatusCode> {
+    let mut conn = acquire!(&pool).await?;
+
+    if update_disabled(id, false, &mut *conn)
+        .await
+        .context("failed to enable user")
+        .map_err(into_log_server_error!())?
+    {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(user_not_found())
+    }
+}
+
+/// Revoke every session of a user, logging them out everywhere
+#[tracing::instrument(skip_all)]
+pub async fn revoke_sessions(
+    State(pool): State<Pool>,
+    _admin: AdminClaims,
+    Path(id): Path<Uuid>,
+) -> OperationResult<StatusCode> {
+    let mut conn = acquire!(&pool).await?;
+
+    select_user_by_id(id, &mut *conn)
+        .await
+        .context("failed to fetch user")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(user_not_found)?;
+
+    revoke_user_sessions(id, None, &mut *conn).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Create a password reset token for a user, to hand to them out of band
+#[tracing::instrument(skip_all)]
+pub async fn create_reset_token(
+    State(pool): State<Pool>,
+    State(lifetimes): State<SessionLifetimes>,
+    _admin: AdminClaims,
+    Path(id): Path<Uuid>,
+) -> OperationResult<Json<CreatedResetToken>> {
+    let mut conn = acquire!(&pool).await?;
+
+    select_user_by_id(id, &mut *conn)
+        .await
+        .context("failed to fetch user")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(user_not_found)?;
+
+    let expiry_date = Utc::now() + lifetimes.password_reset;
+
+    let token = mint_reset_token(&mut *conn, id, expiry_date)
+        .await
+        .context("failed to create password reset token")
+        .map_err(into_log_server_error!())?;
+
+    Ok(Json(CreatedResetToken { token, expiry_date }))
+}
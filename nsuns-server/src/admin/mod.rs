@@ -0,0 +1,7 @@
+pub mod cli;
+pub mod handler;
+pub mod model;
+pub mod openapi;
+pub mod router;
+
+pub use router::router;
@@ -0,0 +1,94 @@
+use anyhow::Context;
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, FromRow};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    auth::user::Role,
+    db::{
+        tracing::{statements::SELECT, InstrumentExecutor},
+        DB,
+    },
+    db_span,
+    error::OperationResult,
+    into_log_server_error,
+};
+
+const TABLE: &str = "users";
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSummary {
+    pub id: Uuid,
+    pub username: String,
+    pub role: Role,
+    pub disabled: bool,
+    /// When one of the user's sessions or access tokens was last used
+    #[schema(value_type = Option<i64>)]
+    #[serde(with = "ts_milliseconds_option")]
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+impl UserSummary {
+    pub async fn select_all(
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} u.id, u.username, u.role, u.disabled, greatest(
+                (SELECT max(s.last_seen) FROM sessions s WHERE s.user_id = u.id),
+                (SELECT max(t.last_used) FROM access_tokens t WHERE t.user_id = u.id)
+            ) AS last_activity
+            FROM {TABLE} u
+            ORDER BY u.username"
+        ))
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select users")
+        .map_err(into_log_server_error!())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnerCounts {
+    /// Owners that belong to a registered user
+    pub registered: i64,
+    /// Anonymous owners that have not expired yet
+    pub anonymous: i64,
+    /// Anonymous owners that have expired, and will be deleted by the next cleanup
+    pub pending_cleanup: i64,
+    pub disabled_users: i64,
+}
+
+impl OwnerCounts {
+    pub async fn select(executor: impl Executor<'_, Database = DB>) -> OperationResult<Self> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT}
+            count(*) FILTER (WHERE expiry_date IS NULL) AS registered,
+            count(*) FILTER (WHERE expiry_date > now()) AS anonymous,
+            count(*) FILTER (WHERE expiry_date <= now()) AS pending_cleanup,
+            (SELECT count(*) FROM {TABLE} WHERE disabled) AS disabled_users
+            FROM owners"
+        ))
+        .fetch_one(executor.instrument_executor(db_span!(SELECT, "owners")))
+        .await
+        .context("failed to count owners")
+        .map_err(into_log_server_error!())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedResetToken {
+    /// The plaintext token. This is only returned once.
+    pub token: String,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub expiry_date: DateTime<Utc>,
+}
@@ -0,0 +1,166 @@
+use const_format::{concatcp, formatcp};
+use utoipa::{
+    openapi::{
+        path::{OperationBuilder, PathItemBuilder},
+        ComponentsBuilder, PathItem, PathItemType, PathsBuilder, ResponseBuilder,
+    },
+    PartialSchema, ToSchema,
+};
+
+use crate::{
+    auth::user::Role,
+    integrity::IntegrityReport,
+    openapi::{
+        extensions::{id_path_param, no_content, ok, JsonContent},
+        Customizer,
+    },
+    router::ADMIN_PATH,
+};
+
+use super::model::{CreatedResetToken, OwnerCounts, UserSummary};
+
+pub struct AdminModule;
+
+const TAG: &str = "Admin";
+
+impl Customizer<ComponentsBuilder> for AdminModule {
+    fn customize(builder: ComponentsBuilder) -> ComponentsBuilder {
+        builder
+            .schema_from::<Role>()
+            .schema_from::<UserSummary>()
+            .schema_from::<OwnerCounts>()
+            .schema_from::<CreatedResetToken>()
+            .schema_from::<IntegrityReport>()
+    }
+}
+
+fn user_action_path(operation: PathItemType, description: &str) -> PathItem {
+    let op = OperationBuilder::new()
+        .description(Some(description))
+        .parameters(id_path_param(Some("The id of the user")))
+        .response(no_content(), ResponseBuilder::new().build())
+        .tag(TAG)
+        .build();
+
+    PathItemBuilder::new().operation(operation, op).build()
+}
+
+impl Customizer<PathsBuilder> for AdminModule {
+    fn customize(builder: PathsBuilder) -> PathsBuilder {
+        let list_users_op = OperationBuilder::new()
+            .description(Some(
+                "List every registered user, with when they were last active",
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<UserSummary>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let owner_counts_op = OperationBuilder::new()
+            .description(Some(
+                "Count owners, including anonymous owners that are waiting to be cleaned up",
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(OwnerCounts::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let reset_token_op = OperationBuilder::new()
+            .description(Some(
+                "Create a password reset token for a user, to hand to them out of band",
+            ))
+            .parameters(id_path_param(Some("The id of the user")))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(CreatedResetToken::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let check_integrity_op = OperationBuilder::new()
+            .description(Some(
+                "Check every program for sets with problems, without changing anything",
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(IntegrityReport::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let repair_integrity_op = OperationBuilder::new()
+            .description(Some(
+                "Repair the sets with problems in every program, reporting which sets were changed",
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(IntegrityReport::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        builder
+            .path(
+                formatcp!("{ADMIN_PATH}/users"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, list_users_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(ADMIN_PATH, "/users/{id}/disable"),
+                user_action_path(
+                    PathItemType::Post,
+                    "Prevent a user from logging in. Their sessions and access tokens are rejected until they are enabled again.",
+                ),
+            )
+            .path(
+                concatcp!(ADMIN_PATH, "/users/{id}/enable"),
+                user_action_path(PathItemType::Post, "Allow a disabled user to log in again"),
+            )
+            .path(
+                concatcp!(ADMIN_PATH, "/users/{id}/sessions"),
+                user_action_path(
+                    PathItemType::Delete,
+                    "Revoke every session of a user, logging them out everywhere",
+                ),
+            )
+            .path(
+                concatcp!(ADMIN_PATH, "/users/{id}/reset-token"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, reset_token_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{ADMIN_PATH}/owners"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, owner_counts_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{ADMIN_PATH}/integrity"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, check_integrity_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{ADMIN_PATH}/integrity/repair"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, repair_integrity_op)
+                    .build(),
+            )
+    }
+}
@@ -0,0 +1,28 @@
+use axum::{
+    extract::FromRef,
+    routing::{delete, get, post},
+    Router,
+};
+
+use crate::{auth::token::SessionLifetimes, db::Pool, router::State};
+
+use super::handler::{
+    check_integrity, create_reset_token, disable_user, enable_user, list_users, owner_counts,
+    repair_integrity, revoke_sessions,
+};
+
+pub fn router<S: State>() -> Router<S>
+where
+    Pool: FromRef<S>,
+    SessionLifetimes: FromRef<S>,
+{
+    Router::new()
+        .route("/users", get(list_users))
+        .route("/users/:id/disable", post(disable_user))
+        .route("/users/:id/enable", post(enable_user))
+        .route("/users/:id/sessions", delete(revoke_sessions))
+        .route("/users/:id/reset-token", post(create_reset_token))
+        .route("/owners", get(owner_counts))
+        .route("/integrity", get(check_integrity))
+        .route("/integrity/repair", post(repair_integrity))
+}
@@ -0,0 +1,222 @@
+//! Personal access tokens, for scripts and integrations that cannot use the session cookie.
+//!
+//! Tokens are sent with `Authorization: Bearer <token>`, and resolve to the owner of the user that created them.
+
+use anyhow::Context;
+use chrono::{
+    serde::{ts_milliseconds, ts_milliseconds_option},
+    DateTime, Utc,
+};
+use const_format::formatcp;
+use http::Method;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, FromRow};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    db::{
+        tracing::{
+            statements::{DELETE_FROM, INSERT_INTO, SELECT, UPDATE},
+            InstrumentExecutor,
+        },
+        DB,
+    },
+    db_span,
+    error::OperationResult,
+    into_log_server_error,
+};
+
+use super::{
+    secret::{generate_secret, hash_secret},
+    token::OwnerId,
+};
+
+const TABLE: &str = "access_tokens";
+
+/// Tokens are only marked as used at most once per this many seconds, to avoid a write on every request.
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60;
+
+/// What an access token is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "camelCase")]
+#[repr(i16)]
+pub enum AccessTokenScope {
+    /// Only safe methods, such as `GET`, are allowed
+    ReadOnly = 0,
+    /// All methods are allowed
+    ReadWrite = 1,
+}
+
+impl AccessTokenScope {
+    /// Whether a request with the given method is allowed with this scope
+    #[must_use]
+    pub fn allows(self, method: &Method) -> bool {
+        self == AccessTokenScope::ReadWrite || method.is_safe()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessToken {
+    pub id: Uuid,
+    pub name: String,
+    pub scope: AccessTokenScope,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub created_on: DateTime<Utc>,
+    #[schema(value_type = Option<i64>)]
+    #[serde(with = "ts_milliseconds_option")]
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// A newly created access token. This is the only time the token itself is returned.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedAccessToken {
+    pub access_token: AccessToken,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAccessToken {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    pub scope: AccessTokenScope,
+}
+
+impl CreateAccessToken {
+    pub async fn insert_one(
+        self,
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<CreatedAccessToken> {
+        let token = generate_secret();
+
+        let access_token = sqlx::query_as::<_, AccessToken>(formatcp!(
+            "{INSERT_INTO} {TABLE} (user_id, name, scope, token_hash) VALUES ($1, $2, $3, $4) RETURNING *"
+        ))
+        .bind(user_id)
+        .bind(self.name)
+        .bind(self.scope)
+        .bind(hash_secret(&token))
+        .fetch_one(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+        .await
+        .context("failed to create access token")
+        .map_err(into_log_server_error!())?;
+
+        Ok(CreatedAccessToken {
+            access_token,
+            token,
+        })
+    }
+}
+
+impl AccessToken {
+    pub async fn select_for_user(
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<AccessToken>> {
+        sqlx::query_as::<_, AccessToken>(formatcp!(
+            "{SELECT} * FROM {TABLE} WHERE user_id = $1 ORDER BY created_on"
+        ))
+        .bind(user_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select access tokens")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Delete an access token belonging to a user
+    ///
+    /// This returns [`None`] if the token did not exist, or [`Some`] if it did.
+    pub async fn delete_one(
+        id: Uuid,
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<()>> {
+        sqlx::query(formatcp!(
+            "{DELETE_FROM} {TABLE} WHERE id = $1 AND user_id = $2"
+        ))
+        .bind(id)
+        .bind(user_id)
+        .execute(executor.instrument_executor(db_span!(DELETE_FROM, TABLE)))
+        .await
+        .with_context(|| format!("failed to delete access token with id={id}"))
+        .map_err(into_log_server_error!())
+        .map(|res| {
+            if res.rows_affected() > 0 {
+                Some(())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Delete all access tokens belonging to a user, returning how many were deleted
+    pub async fn delete_for_user(
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<u64> {
+        sqlx::query(formatcp!("{DELETE_FROM} {TABLE} WHERE user_id = $1"))
+            .bind(user_id)
+            .execute(executor.instrument_executor(db_span!(DELETE_FROM, TABLE)))
+            .await
+            .with_context(|| format!("failed to delete access tokens for user_id={user_id}"))
+            .map_err(into_log_server_error!())
+            .map(|res| res.rows_affected())
+    }
+}
+
+/// The identity an access token resolves to
+#[derive(Debug, Clone, FromRow)]
+pub struct AccessTokenGrant {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub owner_id: OwnerId,
+    pub scope: AccessTokenScope,
+    pub last_used: Option<DateTime<Utc>>,
+    pub user_disabled: bool,
+}
+
+impl AccessTokenGrant {
+    /// Look up the grant for a token presented by a client
+    pub async fn select_by_token(
+        token: &str,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<AccessTokenGrant>> {
+        sqlx::query_as::<_, AccessTokenGrant>(formatcp!(
+            "{SELECT} t.id, t.user_id, u.owner_id, t.scope, t.last_used, u.disabled AS user_disabled FROM {TABLE} t JOIN users u ON u.id = t.user_id WHERE t.token_hash = $1"
+        ))
+        .bind(hash_secret(token))
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select access token")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Whether the last-used time of the token is out of date
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        self.last_used.is_none_or(|last_used| {
+            Utc::now().signed_duration_since(last_used).num_seconds()
+                >= LAST_USED_RESOLUTION_SECONDS
+        })
+    }
+
+    /// Record that the token was used just now
+    pub async fn touch(&self, executor: impl Executor<'_, Database = DB>) -> OperationResult<()> {
+        sqlx::query(formatcp!(
+            "{UPDATE} {TABLE} SET last_used = $1 WHERE id = $2"
+        ))
+        .bind(Utc::now())
+        .bind(self.id)
+        .execute(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+        .await
+        .with_context(|| format!("failed to update access token with id={}", self.id))
+        .map_err(into_log_server_error!())
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Context;
+use axum::{extract::State, response::IntoResponse, Json};
+use http::{header::CONTENT_DISPOSITION, StatusCode};
+
+use crate::{
+    auth::token::OwnerId,
+    db::{commit_ok, tracing::InstrumentExecutor, Pool},
+    db_span,
+    error::OperationResult,
+    into_log_server_error,
+    quotas::settings::QuotaSettings,
+    transaction,
+    validation::ValidatedJson,
+};
+
+use super::model::Archive;
+
+/// Download everything that belongs to the current owner
+#[tracing::instrument(skip_all)]
+pub async fn export(
+    State(pool): State<Pool>,
+    owner_id: OwnerId,
+) -> OperationResult<impl IntoResponse> {
+    // read everything from one snapshot, so the archive is consistent
+    let mut tx = transaction!(&pool).await?;
+
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute((&mut *tx).instrument_executor(db_span!("SET TRANSACTION")))
+        .await
+        .context("failed to set transaction isolation level")
+        .map_err(into_log_server_error!())?;
+
+    let res = Archive::select_for_owner(owner_id, &mut tx).await;
+
+    let archive = commit_ok(res, tx).await?;
+
+    Ok((
+        [(
+            CONTENT_DISPOSITION,
+            r#"attachment; filename="nsuns-export.json""#,
+        )],
+        Json(archive),
+    ))
+}
+
+/// Restore an exported archive into the current owner
+#[tracing::instrument(skip_all)]
+pub async fn restore(
+    State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
+    owner_id: OwnerId,
+    ValidatedJson(archive): ValidatedJson<Archive>,
+) -> OperationResult<StatusCode> {
+    let mut tx = transaction!(&pool).await?;
+
+    let res = archive.restore(owner_id, &quotas, &mut tx).await;
+
+    commit_ok(res, tx).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,7 @@
+//! Exporting everything an owner has, and restoring it.
+//!
+//! Restoring creates new copies of everything in the archive for the current owner, so an archive can be restored
+//! into a different account than the one it was exported from, or into the same account more than once.
+
+pub mod handler;
+pub mod model;
@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context};
+use chrono::{
+    naive::serde::ts_milliseconds, serde::ts_milliseconds as utc_ts_milliseconds, DateTime,
+    NaiveDateTime, Utc,
+};
+use const_format::formatcp;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use sqlx::Transaction;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::token::OwnerId,
+    db::{
+        tracing::{statements::INSERT_INTO, InstrumentExecutor},
+        DB,
+    },
+    db_span,
+    error::{ErrorWithStatus, OperationResult},
+    into_log_server_error,
+    maxes::model::Max,
+    movements::model::{CreateMovement, Movement},
+    profiles::model::{CreateProfile, Profile},
+    program::model::{append_weeks, Program, ProgramSetIds, MAX_WEEKS},
+    quotas::{self, settings::QuotaSettings, Resource},
+    reps::model::Reps,
+    sets::model::{CreateSet, Day, Set, Slot},
+    training_days::model::{TrainingDay, TrainingLog},
+};
+
+/// Bumped whenever the archive format changes incompatibly
+pub const ARCHIVE_VERSION: u16 = 2;
+
+/// The ids of the sets in one week of a program, in order for each day
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedWeek {
+    pub set_ids_sunday: Vec<Uuid>,
+    pub set_ids_monday: Vec<Uuid>,
+    pub set_ids_tuesday: Vec<Uuid>,
+    pub set_ids_wednesday: Vec<Uuid>,
+    pub set_ids_thursday: Vec<Uuid>,
+    pub set_ids_friday: Vec<Uuid>,
+    pub set_ids_saturday: Vec<Uuid>,
+}
+
+impl From<ProgramSetIds> for ArchivedWeek {
+    fn from(value: ProgramSetIds) -> Self {
+        ArchivedWeek {
+            set_ids_sunday: value.set_ids_sunday,
+            set_ids_monday: value.set_ids_monday,
+            set_ids_tuesday: value.set_ids_tuesday,
+            set_ids_wednesday: value.set_ids_wednesday,
+            set_ids_thursday: value.set_ids_thursday,
+            set_ids_friday: value.set_ids_friday,
+            set_ids_saturday: value.set_ids_saturday,
+        }
+    }
+}
+
+impl ArchivedWeek {
+    fn days(&self) -> [(Day, &[Uuid]); 7] {
+        [
+            (Day::Sunday, &self.set_ids_sunday),
+            (Day::Monday, &self.set_ids_monday),
+            (Day::Tuesday, &self.set_ids_tuesday),
+            (Day::Wednesday, &self.set_ids_wednesday),
+            (Day::Thursday, &self.set_ids_thursday),
+            (Day::Friday, &self.set_ids_friday),
+            (Day::Saturday, &self.set_ids_saturday),
+        ]
+    }
+}
+
+/// A training day of a program, with the ids of its sets in order for each week
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedTrainingDay {
+    pub id: Uuid,
+    #[validate(length(min = 1))]
+    pub name: String,
+    pub set_ids: Vec<Vec<Uuid>>,
+}
+
+/// A program, with the ids of its sets in order for each day of each week
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedProgram {
+    pub id: Uuid,
+    #[validate(length(min = 1))]
+    pub name: String,
+    #[validate(length(min = 1))]
+    pub description: Option<String>,
+    pub owner: Uuid,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub created_on: NaiveDateTime,
+    pub weeks: Vec<ArchivedWeek>,
+    /// The training days of the program, in the order of the rotation
+    #[serde(default)]
+    #[validate]
+    pub training_days: Vec<ArchivedTrainingDay>,
+}
+
+impl ArchivedProgram {
+    fn new(
+        program: Program,
+        weeks: Vec<ArchivedWeek>,
+        training_days: Vec<ArchivedTrainingDay>,
+    ) -> Self {
+        ArchivedProgram {
+            id: program.id,
+            name: program.name,
+            description: program.description,
+            owner: program.owner,
+            created_on: program.created_on,
+            weeks,
+            training_days,
+        }
+    }
+
+    /// Insert the program for a restored profile, keeping its creation date so programs stay in order
+    async fn insert_restored(
+        &self,
+        profile_id: Uuid,
+        owner_id: OwnerId,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Uuid> {
+        let program_id = sqlx::query_as::<_, (Uuid,)>(formatcp!(
+            "{INSERT_INTO} programs (name, description, owner, created_on, owner_id) VALUES ($1, $2, $3, $4, $5) RETURNING id"
+        ))
+        .bind(&self.name)
+        .bind(&self.description)
+        .bind(profile_id)
+        .bind(self.created_on)
+        .bind(owner_id)
+        .fetch_one((&mut **tx).instrument_executor(db_span!(INSERT_INTO, "programs")))
+        .await
+        .map(|(id,)| id)
+        .with_context(|| format!("failed to restore program with id={id}", id = self.id))
+        .map_err(into_log_server_error!())?;
+
+        let weeks = i16::try_from(self.weeks.len())
+            .ok()
+            .filter(|weeks| (1..=MAX_WEEKS).contains(weeks))
+            .ok_or_else(|| {
+                ErrorWithStatus::new(
+                    StatusCode::BAD_REQUEST,
+                    anyhow!(
+                        "archived program with id={id} must have between 1 and {MAX_WEEKS} weeks",
+                        id = self.id
+                    ),
+                )
+            })?;
+
+        append_weeks(program_id, weeks, owner_id, &mut **tx).await?;
+
+        Ok(program_id)
+    }
+}
+
+/// Everything that belongs to an owner
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Archive {
+    pub version: u16,
+    #[schema(value_type = i64)]
+    #[serde(with = "utc_ts_milliseconds")]
+    pub exported_on: DateTime<Utc>,
+    #[validate]
+    pub profiles: Vec<Profile>,
+    #[validate]
+    pub movements: Vec<Movement>,
+    #[validate]
+    pub programs: Vec<ArchivedProgram>,
+    #[validate]
+    pub sets: Vec<Set>,
+    #[validate]
+    pub maxes: Vec<Max>,
+    #[validate]
+    pub reps: Vec<Reps>,
+    #[serde(default)]
+    pub training_logs: Vec<TrainingLog>,
+}
+
+/// Select the programs of an owner, with the set ids of all of their weeks and training days
+async fn select_programs(
+    owner_id: OwnerId,
+    sets: &[Set],
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<Vec<ArchivedProgram>> {
+    let mut weeks: HashMap<Uuid, Vec<ArchivedWeek>> = HashMap::new();
+
+    // weeks are in order for each program
+    for week in ProgramSetIds::select_all(owner_id, &mut **tx).await? {
+        weeks.entry(week.program_id).or_default().push(week.into());
+    }
+
+    // sets are in order for each training day
+    let mut training_day_sets: HashMap<Uuid, Vec<&Set>> = HashMap::new();
+
+    for set in sets {
+        if let Some(training_day_id) = set.training_day_id {
+            training_day_sets
+                .entry(training_day_id)
+                .or_default()
+                .push(set);
+        }
+    }
+
+    let mut training_days: HashMap<Uuid, Vec<ArchivedTrainingDay>> = HashMap::new();
+
+    // training days are in order for each program
+    for training_day in TrainingDay::select_all(owner_id, &mut **tx).await? {
+        let program_weeks = weeks.get(&training_day.program_id).map_or(0, Vec::len);
+        let mut set_ids = vec![vec![]; program_weeks];
+
+        for set in training_day_sets
+            .remove(&training_day.id)
+            .unwrap_or_default()
+        {
+            if let Some(week) = set_ids.get_mut(set.week as usize) {
+                week.push(set.id);
+            }
+        }
+
+        training_days
+            .entry(training_day.program_id)
+            .or_default()
+            .push(ArchivedTrainingDay {
+                id: training_day.id,
+                name: training_day.name,
+                set_ids,
+            });
+    }
+
+    Ok(Program::select_all(owner_id, &mut **tx)
+        .await?
+        .into_iter()
+        .map(|program| {
+            let program_weeks = weeks.remove(&program.id).unwrap_or_default();
+            let program_training_days = training_days.remove(&program.id).unwrap_or_default();
+            ArchivedProgram::new(program, program_weeks, program_training_days)
+        })
+        .collect())
+}
+
+/// Map an id from the archive to the id of the restored copy
+fn restored_id(ids: &HashMap<Uuid, Uuid>, kind: &str, id: Uuid) -> OperationResult<Uuid> {
+    ids.get(&id).copied().ok_or_else(|| {
+        ErrorWithStatus::new(
+            StatusCode::BAD_REQUEST,
+            anyhow!("archive references a {kind} with id={id} that it does not include"),
+        )
+    })
+}
+
+impl Archive {
+    pub async fn select_for_owner(
+        owner_id: OwnerId,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Self> {
+        let sets = Set::select_all(owner_id, &mut **tx).await?;
+
+        Ok(Archive {
+            version: ARCHIVE_VERSION,
+            exported_on: Utc::now(),
+            profiles: Profile::select_all(owner_id, &mut **tx).await?,
+            movements: Movement::select_all(owner_id, &mut **tx).await?,
+            programs: select_programs(owner_id, &sets, tx).await?,
+            sets,
+            maxes: Max::select_all(owner_id, &mut **tx).await?,
+            reps: Reps::select_all(owner_id, &mut **tx).await?,
+            training_logs: TrainingLog::select_all(owner_id, &mut **tx).await?,
+        })
+    }
+
+    /// Create copies of everything in the archive for an owner.
+    ///
+    /// Movements are matched by name to the owner's existing movements, instead of being duplicated.
+    pub async fn restore(
+        self,
+        owner_id: OwnerId,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<()> {
+        if self.version != ARCHIVE_VERSION {
+            return Err(ErrorWithStatus::new(
+                StatusCode::BAD_REQUEST,
+                anyhow!(
+                    "archive version {} is not supported, expected {ARCHIVE_VERSION}",
+                    self.version
+                ),
+            ));
+        }
+
+        let mut profile_ids = HashMap::with_capacity(self.profiles.len());
+
+        for profile in self.profiles {
+            let restored = CreateProfile { name: profile.name }
+                .create_one(owner_id, quotas, tx)
+                .await?;
+            profile_ids.insert(profile.id, restored.id);
+        }
+
+        let movement_ids = restore_movements(self.movements, owner_id, quotas, tx).await?;
+
+        let sets: HashMap<Uuid, Set> = self.sets.into_iter().map(|set| (set.id, set)).collect();
+
+        quotas::enforce(
+            Resource::Programs,
+            self.programs.len(),
+            owner_id,
+            quotas,
+            tx,
+        )
+        .await?;
+
+        let mut program_ids = HashMap::with_capacity(self.programs.len());
+        let mut training_day_ids = HashMap::new();
+
+        for program in &self.programs {
+            let profile_id = restored_id(&profile_ids, "profile", program.owner)?;
+            let program_id = program.insert_restored(profile_id, owner_id, tx).await?;
+            program_ids.insert(program.id, program_id);
+
+            let restore_set =
+                |set_id: &Uuid, week: i16, slot: Slot| -> OperationResult<CreateSet> {
+                    let set = sets.get(set_id).ok_or_else(|| {
+                        ErrorWithStatus::new(
+                            StatusCode::BAD_REQUEST,
+                            anyhow!(
+                            "archive references a set with id={set_id} that it does not include"
+                        ),
+                        )
+                    })?;
+
+                    Ok(CreateSet {
+                        program_id,
+                        movement_id: restored_id(&movement_ids, "movement", set.movement_id)?,
+                        week,
+                        day: slot.day(),
+                        training_day_id: slot.training_day_id(),
+                        reps: set.reps,
+                        reps_is_minimum: set.reps_is_minimum,
+                        description: set.description.clone(),
+                        amount: set.amount,
+                        percentage_of_max: set
+                            .percentage_of_max
+                            .map(|id| restored_id(&movement_ids, "movement", id))
+                            .transpose()?,
+                    })
+                };
+
+            let mut program_sets = vec![];
+
+            for (week, days) in (0..).zip(&program.weeks) {
+                for (day, set_ids) in days.days() {
+                    for set_id in set_ids {
+                        program_sets.push(restore_set(set_id, week, Slot::Day(day))?);
+                    }
+                }
+            }
+
+            if !program.training_days.is_empty() {
+                let names: Vec<_> = program
+                    .training_days
+                    .iter()
+                    .map(|training_day| training_day.name.clone())
+                    .collect();
+
+                let restored =
+                    TrainingDay::insert_many(program_id, &names, owner_id, &mut **tx).await?;
+
+                for (training_day, restored) in program.training_days.iter().zip(restored) {
+                    training_day_ids.insert(training_day.id, restored.id);
+
+                    for (week, set_ids) in (0..).zip(&training_day.set_ids) {
+                        for set_id in set_ids {
+                            program_sets.push(restore_set(
+                                set_id,
+                                week,
+                                Slot::TrainingDay(restored.id),
+                            )?);
+                        }
+                    }
+                }
+            }
+
+            if !program_sets.is_empty() {
+                CreateSet::insert_many(&program_sets, program_id, owner_id, quotas, tx).await?;
+            }
+        }
+
+        let maxes = self
+            .maxes
+            .into_iter()
+            .map(|max| {
+                Ok(Max {
+                    profile_id: restored_id(&profile_ids, "profile", max.profile_id)?,
+                    movement_id: restored_id(&movement_ids, "movement", max.movement_id)?,
+                    ..max
+                })
+            })
+            .collect::<OperationResult<Vec<_>>>()?;
+
+        Max::insert_many(&maxes, owner_id, quotas, tx).await?;
+
+        let reps = self
+            .reps
+            .into_iter()
+            .map(|reps| {
+                Ok(Reps {
+                    profile_id: restored_id(&profile_ids, "profile", reps.profile_id)?,
+                    movement_id: restored_id(&movement_ids, "movement", reps.movement_id)?,
+                    ..reps
+                })
+            })
+            .collect::<OperationResult<Vec<_>>>()?;
+
+        Reps::insert_many(&reps, owner_id, quotas, tx).await?;
+
+        let training_logs = self
+            .training_logs
+            .into_iter()
+            .map(|log| {
+                Ok(TrainingLog {
+                    profile_id: restored_id(&profile_ids, "profile", log.profile_id)?,
+                    program_id: restored_id(&program_ids, "program", log.program_id)?,
+                    training_day_id: restored_id(
+                        &training_day_ids,
+                        "training day",
+                        log.training_day_id,
+                    )?,
+                    ..log
+                })
+            })
+            .collect::<OperationResult<Vec<_>>>()?;
+
+        if !training_logs.is_empty() {
+            TrainingLog::insert_many(&training_logs, owner_id, &mut **tx).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Restore movements, reusing existing movements with the same name
+async fn restore_movements(
+    movements: Vec<Movement>,
+    owner_id: OwnerId,
+    quotas: &QuotaSettings,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<HashMap<Uuid, Uuid>> {
+    let mut ids_by_name: HashMap<String, Uuid> = Movement::select_all(owner_id, &mut **tx)
+        .await?
+        .into_iter()
+        .map(|movement| (movement.name, movement.id))
+        .collect();
+
+    let missing: Vec<_> = movements
+        .iter()
+        .filter(|movement| !ids_by_name.contains_key(&movement.name))
+        .map(|movement| CreateMovement {
+            name: movement.name.clone(),
+            description: movement.description.clone(),
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        let created = CreateMovement::insert_many(&missing, owner_id, quotas, tx).await?;
+        ids_by_name.extend(
+            created
+                .into_iter()
+                .map(|movement| (movement.name, movement.id)),
+        );
+    }
+
+    Ok(movements
+        .into_iter()
+        .filter_map(|movement| {
+            ids_by_name
+                .get(&movement.name)
+                .map(|restored| (movement.id, *restored))
+        })
+        .collect())
+}
@@ -79,6 +79,48 @@ async fn run_cleanup(url: &str) {
         tracing::info!("Removed {} expired sessions", result.rows_affected());
     }
 
+    let result_reset_tokens = sqlx::query("DELETE FROM password_reset_tokens WHERE exp < $1")
+        .bind(now)
+        .execute((&mut conn).instrument_executor(db_span!("DELETE FROM", "password_reset_tokens")))
+        .await
+        .context("failed to delete expired password reset tokens")
+        .map_err(into_log_server_error!());
+
+    if let Ok(ref result) = result_reset_tokens {
+        tracing::info!(
+            "Removed {} expired password reset tokens",
+            result.rows_affected()
+        );
+    }
+
+    let result_oidc_logins = sqlx::query("DELETE FROM oidc_pending_logins WHERE exp < $1")
+        .bind(now)
+        .execute((&mut conn).instrument_executor(db_span!("DELETE FROM", "oidc_pending_logins")))
+        .await
+        .context("failed to delete expired OpenID Connect logins")
+        .map_err(into_log_server_error!());
+
+    if let Ok(ref result) = result_oidc_logins {
+        tracing::info!(
+            "Removed {} expired OpenID Connect logins",
+            result.rows_affected()
+        );
+    }
+
+    let result_challenges = sqlx::query("DELETE FROM login_challenges WHERE exp < $1")
+        .bind(now)
+        .execute((&mut conn).instrument_executor(db_span!("DELETE FROM", "login_challenges")))
+        .await
+        .context("failed to delete expired login challenges")
+        .map_err(into_log_server_error!());
+
+    if let Ok(ref result) = result_challenges {
+        tracing::info!(
+            "Removed {} expired login challenges",
+            result.rows_affected()
+        );
+    }
+
     let result_owners = sqlx::query("DELETE FROM owners WHERE expiry_date < $1")
         .bind(now)
         .execute((&mut conn).instrument_executor(db_span!("DELETE FROM", "owners")))
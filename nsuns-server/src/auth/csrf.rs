@@ -0,0 +1,139 @@
+//! Cross-site request forgery protection for requests authenticated with the session cookie.
+//!
+//! This uses the double-submit pattern: a random token is set in a cookie that scripts on our own origin can read,
+//! and unsafe requests must repeat it in the [`CSRF_HEADER_NAME`] header. Other sites cannot read the cookie, so
+//! they cannot forge the header.
+
+use anyhow::anyhow;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::headers::{authorization::Bearer, Authorization, HeaderMapExt};
+use http::StatusCode;
+use time::Duration;
+use tower_cookies::{
+    cookie::{CookieBuilder, SameSite},
+    Cookie, Cookies,
+};
+
+use crate::{error::ErrorWithStatus, router::AUTH_PATH};
+
+use super::{
+    secret::generate_secret,
+    token::{decode_claims_from_cookies, JwtKeys, SessionLifetimes, COOKIE_NAME},
+};
+
+pub const CSRF_COOKIE_NAME: &str = "XSRF-TOKEN";
+
+pub const CSRF_HEADER_NAME: &str = "X-XSRF-TOKEN";
+
+const LOGIN_PATH: &str = const_format::concatcp!(AUTH_PATH, "/login");
+
+fn create_csrf_cookie<'c>(token: String, max_age: Option<Duration>) -> Cookie<'c> {
+    let builder = CookieBuilder::new(CSRF_COOKIE_NAME, token)
+        .path("/")
+        // must be readable by the client, to copy it into the header
+        .http_only(false)
+        .same_site(SameSite::Lax);
+
+    match max_age {
+        Some(max_age) => builder.max_age(max_age).build(),
+        None => builder.build(),
+    }
+}
+
+fn create_empty_csrf_cookie<'c>() -> Cookie<'c> {
+    create_csrf_cookie(String::new(), None)
+}
+
+/// Issue a new token that lasts as long as the session cookie, so both survive a browser restart
+fn issue_csrf_cookie(keys: &JwtKeys, lifetimes: &SessionLifetimes, cookies: &Cookies) {
+    let max_age = decode_claims_from_cookies(keys, cookies)
+        .map(|claims| lifetimes.cookie_max_age(claims.user_id));
+
+    cookies.add(create_csrf_cookie(generate_secret(), max_age));
+}
+
+/// Whether an unsafe request must prove it came from our own origin
+fn requires_token(request: &Request, cookies: &Cookies) -> bool {
+    if request.method().is_safe() || request.uri().path() == LOGIN_PATH {
+        return false;
+    }
+
+    // Bearer tokens are never sent automatically by browsers
+    if request
+        .headers()
+        .typed_get::<Authorization<Bearer>>()
+        .is_some()
+    {
+        return false;
+    }
+
+    // Without a session cookie, there are no ambient credentials to abuse
+    cookies.get(COOKIE_NAME).is_some()
+}
+
+fn has_valid_token(request: &Request, cookies: &Cookies) -> bool {
+    let Some(expected) = cookies.get(CSRF_COOKIE_NAME) else {
+        return false;
+    };
+
+    let Some(provided) = request.headers().get(CSRF_HEADER_NAME) else {
+        return false;
+    };
+
+    !expected.value().is_empty()
+        && ring::constant_time::verify_slices_are_equal(
+            expected.value().as_bytes(),
+            provided.as_bytes(),
+        )
+        .is_ok()
+}
+
+/// Reject unsafe requests authenticated by the session cookie that do not repeat the CSRF token.
+///
+/// A new token is issued whenever the session cookie changes, and for sessions that do not have one yet, with the same
+/// max age as the session cookie.
+pub async fn protect_csrf(
+    State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
+    cookies: Cookies,
+    request: Request,
+    next: Next,
+) -> Response {
+    if requires_token(&request, &cookies) && !has_valid_token(&request, &cookies) {
+        return ErrorWithStatus::new(
+            StatusCode::FORBIDDEN,
+            anyhow!("Missing or invalid CSRF token"),
+        )
+        .into_response();
+    }
+
+    let session_before = cookies
+        .get(COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned());
+
+    let response = next.run(request).await;
+
+    let session_after = cookies
+        .get(COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|value| !value.is_empty());
+
+    match session_after {
+        None if cookies.get(CSRF_COOKIE_NAME).is_some() => {
+            cookies.remove(create_empty_csrf_cookie());
+        }
+        Some(session) if session_before.as_ref() != Some(&session) => {
+            issue_csrf_cookie(&keys, &lifetimes, &cookies);
+        }
+        Some(_) if cookies.get(CSRF_COOKIE_NAME).is_none() => {
+            issue_csrf_cookie(&keys, &lifetimes, &cookies);
+        }
+        _ => {}
+    }
+
+    response
+}
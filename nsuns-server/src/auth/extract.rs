@@ -1,18 +1,21 @@
-use std::sync::Arc;
+use std::{convert::Infallible, net::IpAddr, sync::Arc};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use async_trait::async_trait;
 use axum::{
-    extract::FromRequestParts,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
     response::{IntoResponse, Response},
 };
-use http::request::Parts;
+use http::{request::Parts, StatusCode};
 
-use crate::error::ErrorWithStatus;
+use crate::{acquire, db::Pool, error::ErrorWithStatus, into_log_server_error, server::ClientInfo};
 
 use super::{
+    access_token::AccessTokenScope,
     middleware::ClaimsResult,
+    throttle::LoginThrottle,
     token::{Claims, OwnerId},
+    user::{select_user_by_id, Role},
 };
 
 pub type ClaimsRejection = Response;
@@ -54,3 +57,96 @@ impl<S: Send + Sync> FromRequestParts<S> for OwnerId {
             .map(|claims| claims.owner_id)
     }
 }
+
+/// Claims for a session login, rejecting requests authenticated with a personal access token.
+///
+/// Use this for actions that manage the account itself, so a leaked access token cannot be escalated.
+#[derive(Debug, Clone)]
+pub struct SessionClaims(pub Claims);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SessionClaims {
+    type Rejection = ClaimsRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if parts.extensions.get::<AccessTokenScope>().is_some() {
+            return Err(ErrorWithStatus::new(
+                StatusCode::FORBIDDEN,
+                anyhow!("Access tokens cannot be used for this action"),
+            )
+            .into_response());
+        }
+
+        Claims::from_request_parts(parts, state)
+            .await
+            .map(SessionClaims)
+    }
+}
+
+/// Claims for a session of an administrator, rejecting everyone else.
+///
+/// The role is looked up on every request, so revoking it takes effect immediately.
+#[derive(Debug, Clone)]
+pub struct AdminClaims(pub Claims);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AdminClaims
+where
+    S: Send + Sync,
+    Pool: FromRef<S>,
+{
+    type Rejection = ClaimsRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let SessionClaims(claims) = SessionClaims::from_request_parts(parts, state).await?;
+
+        let forbidden = || {
+            ErrorWithStatus::new(
+                StatusCode::FORBIDDEN,
+                anyhow!("Only administrators can do this"),
+            )
+            .into_response()
+        };
+
+        let Some(user_id) = claims.user_id else {
+            return Err(forbidden());
+        };
+
+        let pool = Pool::from_ref(state);
+        let mut conn = acquire!(&pool).await.map_err(|e| e.into_response())?;
+
+        let user = select_user_by_id(user_id, &mut *conn)
+            .await
+            .context("failed to fetch user")
+            .map_err(into_log_server_error!())
+            .map_err(|e| e.into_response())?;
+
+        match user {
+            Some(user) if user.role == Role::Admin => Ok(AdminClaims(claims)),
+            _ => Err(forbidden()),
+        }
+    }
+}
+
+/// The address of the client, looked through any trusted proxies. [`None`] if the server does not know the remote
+/// address, such as in tests.
+pub struct ClientAddress(pub Option<IpAddr>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientAddress
+where
+    LoginThrottle: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let remote = parts
+            .extensions
+            .get::<ConnectInfo<ClientInfo>>()
+            .map(|ConnectInfo(client)| client.remote_addr.ip());
+
+        Ok(Self(
+            LoginThrottle::from_ref(state).client_address(remote, &parts.headers),
+        ))
+    }
+}
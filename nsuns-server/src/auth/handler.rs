@@ -1,39 +1,89 @@
-
 use anyhow::{anyhow, Context};
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
 use axum_extra::{
     headers::{authorization::Basic, Authorization},
     TypedHeader,
 };
-use chrono::{Days, Utc};
-use http::StatusCode;
+use chrono::Utc;
+use http::{header::RETRY_AFTER, StatusCode};
+use jsonwebtoken::jwk::JwkSet;
 use sqlx::{Executor, Transaction};
-use time::Duration;
 use tower_cookies::Cookies;
+use uuid::Uuid;
+use validator::ValidationError;
 
 use crate::{
+    acquire,
     db::{transaction::commit_ok, Pool, DB},
     error::{extract::WithErrorRejection, ErrorWithStatus, OperationResult},
-    into_log_server_error, transaction,
+    into_log_server_error, log_server_error,
+    response_transforms::created,
+    transaction,
+    validation::ValidatedJson,
 };
 
 use super::{
+    access_token::{AccessToken, CreateAccessToken, CreatedAccessToken},
+    extract::{ClientAddress, SessionClaims},
+    middleware::account_disabled,
+    reset::redeem_reset_token,
+    throttle::LoginThrottle,
     token::{
-        create_empty_cookie, create_new_expiry_date, create_token_cookie,
-        decode_claims_from_cookies, Claims, JwtKeys, OwnerId,
+        create_empty_cookie, create_token_cookie, decode_claims_from_cookies, revoke_owner_session,
+        revoke_user_sessions, Claims, JwtKeys, OwnerId, Session, SessionInfo, SessionLifetimes,
     },
+    two_factor::{handler::create_login_challenge, model::LoginChallenge},
     user::{
-        authenticate, create_anonymous_user, delete_owner, select_owner_expiry,
-        select_user_info_by_owner_id, AgentInfo, AnonymousInfo,
+        self, authenticate, create_anonymous_user, create_owner, delete_owner, persist_owner,
+        select_owner_expiry, select_user_by_id, select_user_info_by_owner_id, update_password,
+        AgentInfo, AnonymousInfo, ChangePassword, CreateUser, DeleteAccount, ResetPassword,
+        UserInfo,
     },
 };
 
+/// Create a new session for a persistent user, and set the token cookie
+pub(crate) async fn create_user_session(
+    executor: impl Executor<'_, Database = DB>,
+    keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
+    owner_id: Uuid,
+    user_id: Uuid,
+    cookies: &Cookies,
+) -> OperationResult<Claims> {
+    let claims = Claims::insert_one(
+        executor,
+        owner_id,
+        Some(user_id),
+        lifetimes.create_expiry_date(Some(user_id)),
+    )
+    .await?;
+    let token = keys.encode(&claims).map_err(into_log_server_error!())?;
+
+    cookies.add(create_token_cookie(
+        token,
+        lifetimes.cookie_max_age(Some(user_id)),
+    ));
+
+    Ok(claims)
+}
+
+enum LoginOutcome {
+    LoggedIn,
+    /// The password was correct, but the second factor is still required
+    Challenged(LoginChallenge),
+}
+
 async fn login_user(
     tx: &mut Transaction<'_, DB>,
     keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
     auth: Basic,
     cookies: Cookies,
-) -> OperationResult<()> {
+) -> OperationResult<LoginOutcome> {
     // authenticate user
     let Some(user) = authenticate(&mut **tx, auth)
         .await
@@ -46,43 +96,392 @@ async fn login_user(
         ));
     };
 
+    if user.disabled {
+        return Err(account_disabled());
+    }
+
+    if let Some(challenge) = create_login_challenge(tx, lifetimes, user.id).await? {
+        return Ok(LoginOutcome::Challenged(challenge));
+    }
+
     if let Some(claims) = decode_claims_from_cookies(keys, &cookies) {
         revoke_and_logout(claims, tx).await?;
     }
 
-    let claims = Claims::insert_one(
-        &mut **tx,
-        user.owner_id,
-        Some(user.id),
-        Some(
-            Utc::now()
-                .checked_add_days(Days::new(30))
-                .expect("future timestamp does not overflow"),
-        ),
-    )
-    .await?;
-    let token = keys.encode(&claims).map_err(into_log_server_error!())?;
-    let cookie = create_token_cookie(token, Duration::days(30));
+    let claims =
+        create_user_session(&mut **tx, keys, lifetimes, user.owner_id, user.id, &cookies).await?;
 
     delete_owner_if_anonymous(Some(claims), &mut **tx).await?;
 
-    cookies.add(cookie);
+    Ok(LoginOutcome::LoggedIn)
+}
 
-    Ok(())
+fn too_many_attempts(retry_after: std::time::Duration) -> Response {
+    // round up, so clients never retry too early
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    (
+        [(RETRY_AFTER, seconds.to_string())],
+        ErrorWithStatus::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            anyhow!("Too many failed login attempts, try again later"),
+        ),
+    )
+        .into_response()
 }
 
+/// Log in with a username and password.
+///
+/// If the user has two-factor authentication enabled, this responds with a login challenge instead of a session.
 #[tracing::instrument(skip_all)]
 pub async fn login(
     State(pool): State<Pool>,
     State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
+    State(throttle): State<LoginThrottle>,
+    ClientAddress(address): ClientAddress,
     WithErrorRejection(TypedHeader(Authorization(creds))): WithErrorRejection<
         TypedHeader<Authorization<Basic>>,
     >,
     WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+) -> Result<Response, Response> {
+    let username = creds.username().to_owned();
+
+    if let Err(retry_after) = throttle.begin_attempt(&username, address) {
+        metrics::increment_counter!("auth.login.failures", "reason" => "throttled");
+        return Err(too_many_attempts(retry_after));
+    }
+
+    let res = match transaction!(&pool).await {
+        Ok(mut tx) => {
+            let res = login_user(&mut tx, &keys, &lifetimes, creds, cookies).await;
+            commit_ok(res, tx).await
+        }
+        Err(e) => Err(e),
+    };
+
+    // The attempt stays counted as a failure until the second factor is entered as well
+    match res {
+        Ok(LoginOutcome::LoggedIn) => {
+            throttle.record_success(&username, address);
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Ok(LoginOutcome::Challenged(challenge)) => {
+            Ok((StatusCode::ACCEPTED, Json(challenge)).into_response())
+        }
+        Err(e) if e.status == StatusCode::UNAUTHORIZED => {
+            metrics::increment_counter!("auth.login.failures", "reason" => "invalid_credentials");
+            throttle.record_failure(&username, address);
+            Err(e.into_response())
+        }
+        Err(e) => {
+            throttle.cancel_attempt(&username, address);
+            Err(e.into_response())
+        }
+    }
+}
+
+fn create_user_error(e: user::Error) -> ErrorWithStatus<anyhow::Error> {
+    match e {
+        user::Error::UsernameTaken | user::Error::OwnerClaimed => {
+            ErrorWithStatus::new(StatusCode::CONFLICT, e.into())
+        }
+        e => anyhow::Error::from(e)
+            .context("failed to create user")
+            .into(),
+    }
+}
+
+async fn register_user(
+    tx: &mut Transaction<'_, DB>,
+    keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
+    user: CreateUser,
+    cookies: Cookies,
+) -> OperationResult<UserInfo> {
+    if let Some(claims) = decode_claims_from_cookies(keys, &cookies) {
+        revoke_and_logout(claims, tx).await?;
+    }
+
+    let owner_id = create_owner(&mut **tx)
+        .await
+        .context("failed to create new owner")
+        .map_err(into_log_server_error!())?;
+
+    let user_info = user
+        .insert_one(owner_id, &mut **tx)
+        .await
+        .map_err(create_user_error)
+        .map_err(log_server_error!())?;
+
+    create_user_session(
+        &mut **tx,
+        keys,
+        lifetimes,
+        owner_id.as_uuid(),
+        user_info.id,
+        &cookies,
+    )
+    .await?;
+
+    Ok(user_info)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn register(
+    State(pool): State<Pool>,
+    State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+    ValidatedJson(user): ValidatedJson<CreateUser>,
+) -> OperationResult<impl IntoResponse> {
+    let mut tx = transaction!(&pool).await?;
+
+    let res = register_user(&mut tx, &keys, &lifetimes, user, cookies).await;
+
+    commit_ok(res, tx).await.map(Json).map(created)
+}
+
+async fn claim_anonymous_owner(
+    tx: &mut Transaction<'_, DB>,
+    keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
+    claims: Claims,
+    user: CreateUser,
+    cookies: Cookies,
+) -> OperationResult<UserInfo> {
+    if claims.user_id.is_some() {
+        return Err(ErrorWithStatus::new(
+            StatusCode::CONFLICT,
+            anyhow!("Only anonymous sessions can be claimed"),
+        ));
+    }
+
+    let user_info = user
+        .insert_one(claims.owner_id, &mut **tx)
+        .await
+        .map_err(create_user_error)
+        .map_err(log_server_error!())?;
+
+    persist_owner(&mut **tx, claims.owner_id)
+        .await
+        .context("failed to remove owner expiry date")
+        .map_err(into_log_server_error!())?;
+
+    // Revoke the anonymous session without deleting the owner, since the new user now owns its data.
+    claims.revoke(&mut **tx).await?;
+
+    create_user_session(
+        &mut **tx,
+        keys,
+        lifetimes,
+        claims.owner_id.as_uuid(),
+        user_info.id,
+        &cookies,
+    )
+    .await?;
+
+    Ok(user_info)
+}
+
+/// Convert the current anonymous owner into a persistent user, keeping all of its data.
+#[tracing::instrument(skip_all)]
+pub async fn claim(
+    State(pool): State<Pool>,
+    State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
+    claims: Claims,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+    ValidatedJson(user): ValidatedJson<CreateUser>,
+) -> OperationResult<impl IntoResponse> {
+    let mut tx = transaction!(&pool).await?;
+
+    let res = claim_anonymous_owner(&mut tx, &keys, &lifetimes, claims, user, cookies).await;
+
+    commit_ok(res, tx).await.map(Json)
+}
+
+/// A new password that is rejected by the password policy
+fn weak_password(e: ValidationError) -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(StatusCode::UNPROCESSABLE_ENTITY, anyhow!(e))
+}
+
+async fn change_user_password(
+    tx: &mut Transaction<'_, DB>,
+    claims: &Claims,
+    change: ChangePassword,
+) -> OperationResult<()> {
+    let Some(user_id) = claims.user_id else {
+        return Err(ErrorWithStatus::new(
+            StatusCode::FORBIDDEN,
+            anyhow!("Anonymous sessions do not have a password"),
+        ));
+    };
+
+    let user = select_user_by_id(user_id, &mut **tx)
+        .await
+        .context("failed to fetch user")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(|| ErrorWithStatus::new(StatusCode::UNAUTHORIZED, anyhow!("Unauthorized")))?;
+
+    let Some(user) = user
+        .verify(change.current_password)
+        .await
+        .context("failed to verify password")
+        .map_err(into_log_server_error!())?
+    else {
+        return Err(ErrorWithStatus::new(
+            StatusCode::FORBIDDEN,
+            anyhow!("Current password is incorrect"),
+        ));
+    };
+
+    user::validate_password_for(&user.username, &change.new_password).map_err(weak_password)?;
+
+    update_password(user_id, change.new_password, &mut **tx)
+        .await
+        .context("failed to update password")
+        .map_err(into_log_server_error!())?;
+
+    revoke_user_sessions(user_id, Some(claims.id), &mut **tx).await?;
+    AccessToken::delete_for_user(user_id, &mut **tx).await?;
+
+    Ok(())
+}
+
+/// Change the password of the logged-in user. All other sessions and all access tokens for the user are revoked.
+#[tracing::instrument(skip_all)]
+pub async fn change_password(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    ValidatedJson(change): ValidatedJson<ChangePassword>,
+) -> OperationResult<StatusCode> {
+    let mut tx = transaction!(&pool).await?;
+
+    let res = change_user_password(&mut tx, &claims, change).await;
+
+    commit_ok(res, tx).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_user_account(
+    tx: &mut Transaction<'_, DB>,
+    claims: &Claims,
+    lifetimes: &SessionLifetimes,
+    confirmation: DeleteAccount,
+) -> OperationResult<()> {
+    let user_id = require_user(claims)?;
+
+    let user = select_user_by_id(user_id, &mut **tx)
+        .await
+        .context("failed to fetch user")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(|| ErrorWithStatus::new(StatusCode::UNAUTHORIZED, anyhow!("Unauthorized")))?;
+
+    if user.has_password() {
+        if user
+            .verify(confirmation.password.unwrap_or_default())
+            .await
+            .context("failed to verify password")
+            .map_err(into_log_server_error!())?
+            .is_none()
+        {
+            return Err(ErrorWithStatus::new(
+                StatusCode::FORBIDDEN,
+                anyhow!("Password is incorrect"),
+            ));
+        }
+    } else {
+        // users from an identity provider confirm by having logged in recently
+        let session = Session::select_one(claims.id, &mut **tx)
+            .await?
+            .ok_or_else(|| {
+                ErrorWithStatus::new(StatusCode::UNAUTHORIZED, anyhow!("Unauthorized"))
+            })?;
+
+        if session.created_on + lifetimes.recent_login < Utc::now() {
+            return Err(ErrorWithStatus::new(
+                StatusCode::FORBIDDEN,
+                anyhow!("Log in again to confirm deleting your account"),
+            ));
+        }
+    }
+
+    // cascades to the user, its sessions, and everything it owns
+    delete_owner(&mut **tx, claims.owner_id)
+        .await
+        .context("failed to delete owner")
+        .map_err(into_log_server_error!())
+}
+
+/// Permanently delete the logged-in user and all of their data, after confirming the password.
+///
+/// Users without a password confirm by having logged in recently instead.
+#[tracing::instrument(skip_all)]
+pub async fn delete_account(
+    State(pool): State<Pool>,
+    State(lifetimes): State<SessionLifetimes>,
+    SessionClaims(claims): SessionClaims,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+    ValidatedJson(confirmation): ValidatedJson<DeleteAccount>,
 ) -> OperationResult<StatusCode> {
     let mut tx = transaction!(&pool).await?;
 
-    let res = login_user(&mut tx, &keys, creds, cookies).await;
+    let res = delete_user_account(&mut tx, &claims, &lifetimes, confirmation).await;
+
+    commit_ok(res, tx).await?;
+
+    cookies.remove(create_empty_cookie());
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn reset_user_password(
+    tx: &mut Transaction<'_, DB>,
+    reset: ResetPassword,
+) -> OperationResult<()> {
+    let Some(user_id) = redeem_reset_token(tx, &reset.token)
+        .await
+        .context("failed to redeem password reset token")
+        .map_err(into_log_server_error!())?
+    else {
+        return Err(ErrorWithStatus::new(
+            StatusCode::BAD_REQUEST,
+            anyhow!("Invalid or expired reset token"),
+        ));
+    };
+
+    let user = select_user_by_id(user_id, &mut **tx)
+        .await
+        .context("failed to fetch user")
+        .map_err(into_log_server_error!())?
+        .context("reset token belongs to a user that does not exist")
+        .map_err(into_log_server_error!())?;
+
+    // the token is only used up if the new password is accepted
+    user::validate_password_for(&user.username, &reset.new_password).map_err(weak_password)?;
+
+    update_password(user_id, reset.new_password, &mut **tx)
+        .await
+        .context("failed to update password")
+        .map_err(into_log_server_error!())?;
+
+    revoke_user_sessions(user_id, None, &mut **tx).await?;
+    AccessToken::delete_for_user(user_id, &mut **tx).await?;
+
+    Ok(())
+}
+
+/// Set a new password using a single-use reset token. All sessions and access tokens for the user are revoked.
+#[tracing::instrument(skip_all)]
+pub async fn reset_password(
+    State(pool): State<Pool>,
+    ValidatedJson(reset): ValidatedJson<ResetPassword>,
+) -> OperationResult<StatusCode> {
+    let mut tx = transaction!(&pool).await?;
+
+    let res = reset_user_password(&mut tx, reset).await;
 
     commit_ok(res, tx).await?;
 
@@ -92,22 +491,23 @@ pub async fn login(
 async fn login_anonymous(
     tx: &mut Transaction<'_, DB>,
     keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
     cookies: Cookies,
 ) -> OperationResult<()> {
     if let Some(claims) = decode_claims_from_cookies(keys, &cookies) {
         revoke_and_logout(claims, tx).await?;
     }
 
-    let exp = create_new_expiry_date();
+    let exp = lifetimes.create_expiry_date(None);
 
     let owner_id = create_anonymous_user(&mut **tx, exp)
         .await
         .context("failed to create new anonymous owner")
         .map_err(into_log_server_error!())?;
 
-    let claims = Claims::insert_one(&mut **tx, owner_id.as_uuid(), None, Some(exp)).await?;
+    let claims = Claims::insert_one(&mut **tx, owner_id.as_uuid(), None, exp).await?;
     let token = keys.encode(&claims).map_err(into_log_server_error!())?;
-    let cookie = create_token_cookie(token, Duration::days(2));
+    let cookie = create_token_cookie(token, lifetimes.cookie_max_age(None));
 
     cookies.add(cookie);
 
@@ -118,11 +518,12 @@ async fn login_anonymous(
 pub async fn anonymous(
     State(pool): State<Pool>,
     State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
     WithErrorRejection(cookies): WithErrorRejection<Cookies>,
 ) -> OperationResult<StatusCode> {
     let mut tx = transaction!(&pool).await?;
 
-    let res = login_anonymous(&mut tx, &keys, cookies).await;
+    let res = login_anonymous(&mut tx, &keys, &lifetimes, cookies).await;
 
     commit_ok(res, tx).await?;
 
@@ -145,7 +546,10 @@ async fn delete_owner_if_anonymous(
     Ok(())
 }
 
-async fn revoke_and_logout(claims: Claims, tx: &mut Transaction<'_, DB>) -> OperationResult<()> {
+pub(crate) async fn revoke_and_logout(
+    claims: Claims,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<()> {
     if claims.revoke(&mut **tx).await?.is_some() {
         // Only delete the owner if the token was not revoked.
         // This prevents revoked tokens from deleting anonymous users.
@@ -171,6 +575,87 @@ pub async fn logout(
     Ok(StatusCode::NO_CONTENT)
 }
 
+pub(crate) fn require_user(claims: &Claims) -> OperationResult<Uuid> {
+    claims.user_id.ok_or_else(|| {
+        ErrorWithStatus::new(
+            StatusCode::FORBIDDEN,
+            anyhow!("Only registered users can do this"),
+        )
+    })
+}
+
+/// List the active sessions of the logged-in user
+#[tracing::instrument(skip_all)]
+pub async fn list_sessions(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+) -> OperationResult<Json<Vec<SessionInfo>>> {
+    require_user(&claims)?;
+
+    let mut conn = acquire!(&pool).await?;
+
+    let sessions = Session::select_active_for_owner(claims.owner_id, &mut *conn).await?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| SessionInfo::new(session, claims.id))
+            .collect(),
+    ))
+}
+
+/// Revoke one of the logged-in user's sessions. Revoking the current session logs out.
+#[tracing::instrument(skip_all)]
+pub async fn revoke_session(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+    Path(id): Path<Uuid>,
+) -> OperationResult<StatusCode> {
+    require_user(&claims)?;
+
+    let mut conn = acquire!(&pool).await?;
+
+    revoke_owner_session(id, claims.owner_id, &mut *conn)
+        .await?
+        .ok_or_else(|| ErrorWithStatus::new(StatusCode::NOT_FOUND, anyhow!("Session not found")))?;
+
+    if id == claims.id {
+        cookies.remove(create_empty_cookie());
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke every session of the logged-in user, including the current one
+#[tracing::instrument(skip_all)]
+pub async fn logout_everywhere(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+) -> OperationResult<StatusCode> {
+    let user_id = require_user(&claims)?;
+
+    let mut conn = acquire!(&pool).await?;
+
+    revoke_user_sessions(user_id, None, &mut *conn).await?;
+
+    cookies.remove(create_empty_cookie());
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The public keys that session tokens are signed with, if publishing them is enabled
+#[tracing::instrument(skip_all)]
+pub async fn jwks(State(keys): State<JwtKeys>) -> OperationResult<Json<JwkSet>> {
+    keys.jwks().cloned().map(Json).ok_or_else(|| {
+        ErrorWithStatus::new(
+            StatusCode::NOT_FOUND,
+            anyhow!("Signing keys are not published"),
+        )
+    })
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn agent_info(State(pool): State<Pool>, owner_id: OwnerId) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
@@ -193,3 +678,56 @@ pub async fn agent_info(State(pool): State<Pool>, owner_id: OwnerId) -> impl Int
         Err(e)
     }
 }
+
+/// List the personal access tokens of the logged-in user
+#[tracing::instrument(skip_all)]
+pub async fn list_access_tokens(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+) -> OperationResult<Json<Vec<AccessToken>>> {
+    let user_id = require_user(&claims)?;
+
+    let mut conn = acquire!(&pool).await?;
+
+    AccessToken::select_for_user(user_id, &mut *conn)
+        .await
+        .map(Json)
+}
+
+/// Create a personal access token for the logged-in user
+#[tracing::instrument(skip_all)]
+pub async fn create_access_token(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    ValidatedJson(access_token): ValidatedJson<CreateAccessToken>,
+) -> OperationResult<impl IntoResponse> {
+    let user_id = require_user(&claims)?;
+
+    let mut conn = acquire!(&pool).await?;
+
+    access_token
+        .insert_one(user_id, &mut *conn)
+        .await
+        .map(Json::<CreatedAccessToken>)
+        .map(created)
+}
+
+/// Revoke a personal access token of the logged-in user
+#[tracing::instrument(skip_all)]
+pub async fn revoke_access_token(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    Path(id): Path<Uuid>,
+) -> OperationResult<StatusCode> {
+    let user_id = require_user(&claims)?;
+
+    let mut conn = acquire!(&pool).await?;
+
+    AccessToken::delete_one(id, user_id, &mut *conn)
+        .await?
+        .ok_or_else(|| {
+            ErrorWithStatus::new(StatusCode::NOT_FOUND, anyhow!("Access token not found"))
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
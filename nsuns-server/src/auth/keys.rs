@@ -0,0 +1,308 @@
+//! Keys for signing and verifying session tokens.
+//!
+//! Several keys can be configured at once, identified by the `kid` header of the tokens they sign, so keys can be
+//! rotated without logging everyone out. The last configured key signs new tokens, and tokens signed by any
+//! configured key are accepted.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::{Error, ErrorKind},
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::{
+    rsa::PublicKeyComponents,
+    signature::{Ed25519KeyPair, KeyPair, RsaKeyPair},
+};
+use secrecy::ExposeSecret;
+
+use super::{
+    settings::{AuthSettings, JwtKeySettings},
+    token::Claims,
+};
+
+struct JwtKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+fn invalid_key(kid: &str) -> impl FnOnce(ring::error::KeyRejected) -> anyhow::Error + '_ {
+    move |e| anyhow!("JWT key {kid:?} is invalid: {e}")
+}
+
+fn common_parameters(kid: &str, algorithm: KeyAlgorithm) -> CommonParameters {
+    CommonParameters {
+        public_key_use: Some(PublicKeyUse::Signature),
+        key_algorithm: Some(algorithm),
+        key_id: Some(kid.to_owned()),
+        ..Default::default()
+    }
+}
+
+/// Load a configured key, and its public JWK if it is an asymmetric key
+fn load_key(settings: &JwtKeySettings) -> anyhow::Result<(JwtKey, Option<Jwk>)> {
+    let kid = settings.kid.as_str();
+    let key = settings.key.expose_secret().as_bytes();
+
+    let (encoding, decoding, jwk) = match settings.algorithm {
+        Algorithm::HS256 => (
+            EncodingKey::from_secret(key),
+            DecodingKey::from_secret(key),
+            None,
+        ),
+        Algorithm::RS256 => {
+            let pem = pem::parse(key).with_context(|| format!("JWT key {kid:?} is not PEM"))?;
+
+            let key_pair = match pem.tag() {
+                "RSA PRIVATE KEY" => RsaKeyPair::from_der(pem.contents()),
+                _ => RsaKeyPair::from_pkcs8(pem.contents()),
+            }
+            .map_err(invalid_key(kid))?;
+
+            let public = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+
+            let jwk = Jwk {
+                common: common_parameters(kid, KeyAlgorithm::RS256),
+                algorithm: AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(&public.n),
+                    e: URL_SAFE_NO_PAD.encode(&public.e),
+                }),
+            };
+
+            (
+                EncodingKey::from_rsa_pem(key)?,
+                DecodingKey::from_rsa_raw_components(&public.n, &public.e),
+                Some(jwk),
+            )
+        }
+        Algorithm::EdDSA => {
+            let pem = pem::parse(key).with_context(|| format!("JWT key {kid:?} is not PEM"))?;
+
+            let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(pem.contents())
+                .map_err(invalid_key(kid))?;
+            let public = key_pair.public_key().as_ref();
+
+            let jwk = Jwk {
+                common: common_parameters(kid, KeyAlgorithm::EdDSA),
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(public),
+                }),
+            };
+
+            (
+                EncodingKey::from_ed_pem(key)?,
+                DecodingKey::from_ed_der(public),
+                Some(jwk),
+            )
+        }
+        algorithm => bail!(
+            "JWT key {kid:?} uses {algorithm:?}, but only HS256, RS256 and EdDSA are supported"
+        ),
+    };
+
+    Ok((
+        JwtKey {
+            kid: Some(settings.kid.clone()),
+            algorithm: settings.algorithm,
+            encoding,
+            decoding,
+        },
+        jwk,
+    ))
+}
+
+/// Load the key from the `jwt_encoding_secret` and `jwt_decoding_secret` settings, which signs without a `kid`
+fn load_secret_key(settings: &AuthSettings) -> Option<JwtKey> {
+    let (Some(encoding), Some(decoding)) =
+        (&settings.jwt_encoding_secret, &settings.jwt_decoding_secret)
+    else {
+        return None;
+    };
+
+    Some(JwtKey {
+        kid: None,
+        algorithm: Algorithm::HS256,
+        encoding: EncodingKey::from_secret(encoding.expose_secret().as_bytes()),
+        decoding: DecodingKey::from_secret(decoding.expose_secret().as_bytes()),
+    })
+}
+
+#[derive(Clone)]
+pub struct JwtKeys {
+    keys: Arc<Vec<JwtKey>>,
+    jwks: Option<Arc<JwkSet>>,
+}
+
+impl JwtKeys {
+    /// Load the configured keys
+    ///
+    /// # Errors
+    /// Returns an error if no keys are configured, or a key cannot be parsed
+    pub fn new(settings: &AuthSettings) -> anyhow::Result<Self> {
+        // Keep accepting tokens signed with the secrets after moving to a key set, so nobody is logged out.
+        // The secrets only sign new tokens when there is no key set.
+        let mut keys: Vec<JwtKey> = load_secret_key(settings).into_iter().collect();
+        let mut jwks = JwkSet { keys: vec![] };
+
+        for key_settings in &settings.jwt_keys {
+            if keys
+                .iter()
+                .any(|key| key.kid.as_deref() == Some(&key_settings.kid))
+            {
+                bail!("JWT key id {:?} is used more than once", key_settings.kid);
+            }
+
+            let (key, jwk) = load_key(key_settings)?;
+            keys.push(key);
+            jwks.keys.extend(jwk);
+        }
+
+        if keys.is_empty() {
+            bail!("either jwt_keys, or jwt_encoding_secret and jwt_decoding_secret must be set");
+        }
+
+        Ok(Self {
+            keys: Arc::new(keys),
+            jwks: settings.publish_jwks.then(|| Arc::new(jwks)),
+        })
+    }
+
+    fn signing_key(&self) -> &JwtKey {
+        self.keys.last().expect("at least one key is configured")
+    }
+
+    pub fn decode(&self, token: &str) -> Result<Claims, Error> {
+        let header = decode_header(token)?;
+
+        let key = self
+            .keys
+            .iter()
+            .find(|key| key.kid == header.kid)
+            .ok_or_else(|| Error::from(ErrorKind::InvalidSignature))?;
+
+        let token_data = decode::<Claims>(token, &key.decoding, &Validation::new(key.algorithm))?;
+
+        Ok(token_data.claims)
+    }
+
+    pub fn encode(&self, claims: &Claims) -> Result<String, Error> {
+        let key = self.signing_key();
+
+        let mut header = Header::new(key.algorithm);
+        header.kid.clone_from(&key.kid);
+
+        encode(&header, claims, &key.encoding)
+    }
+
+    /// The public keys that tokens can be verified with, if publishing them is enabled.
+    ///
+    /// Shared secrets are never published.
+    #[must_use]
+    pub fn jwks(&self) -> Option<&JwkSet> {
+        self.jwks.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use jsonwebtoken::{errors::ErrorKind, Algorithm};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    use crate::auth::{
+        settings::{AuthSettings, JwtKeySettings},
+        token::Claims,
+    };
+
+    use super::JwtKeys;
+
+    const ED25519_KEY: &str = include_str!("../../tests/fixtures/jwt-ed25519-key.pem");
+
+    const RSA_KEY: &str = include_str!("../../tests/fixtures/oidc-issuer-key.pem");
+
+    fn key(kid: &str, algorithm: Algorithm, key: &str) -> JwtKeySettings {
+        JwtKeySettings {
+            kid: kid.to_owned(),
+            algorithm,
+            key: SecretString::new(key.to_owned()),
+        }
+    }
+
+    fn keys(jwt_keys: Vec<JwtKeySettings>) -> JwtKeys {
+        let settings: AuthSettings = serde_json::from_value(serde_json::json!({
+            "publish_jwks": true,
+        }))
+        .unwrap();
+
+        JwtKeys::new(&AuthSettings {
+            jwt_keys,
+            ..settings
+        })
+        .unwrap()
+    }
+
+    fn claims() -> Claims {
+        serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "owner_id": Uuid::new_v4(),
+            "user_id": null,
+            "exp": (Utc::now() + chrono::Duration::hours(1)).timestamp_millis(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn old_keys_are_accepted() {
+        let old = keys(vec![key("old", Algorithm::HS256, "secret")]);
+        let rotated = keys(vec![
+            key("old", Algorithm::HS256, "secret"),
+            key("new", Algorithm::EdDSA, ED25519_KEY),
+        ]);
+        let retired = keys(vec![key("new", Algorithm::EdDSA, ED25519_KEY)]);
+
+        let old_token = old.encode(&claims()).unwrap();
+        assert!(rotated.decode(&old_token).is_ok());
+        assert_eq!(
+            &ErrorKind::InvalidSignature,
+            retired.decode(&old_token).unwrap_err().kind()
+        );
+
+        let new_token = rotated.encode(&claims()).unwrap();
+        assert!(retired.decode(&new_token).is_ok());
+        assert!(old.decode(&new_token).is_err());
+    }
+
+    #[test]
+    fn only_public_keys_are_published() {
+        let keys = keys(vec![
+            key("hmac", Algorithm::HS256, "secret"),
+            key("rsa", Algorithm::RS256, RSA_KEY),
+            key("ed", Algorithm::EdDSA, ED25519_KEY),
+        ]);
+
+        let token = keys.encode(&claims()).unwrap();
+        assert!(keys.decode(&token).is_ok());
+
+        let jwks = keys.jwks().unwrap();
+        assert_eq!(2, jwks.keys.len());
+        assert!(jwks.find("hmac").is_none());
+
+        // published keys can verify tokens
+        let key = jsonwebtoken::DecodingKey::from_jwk(jwks.find("ed").unwrap()).unwrap();
+        let validation = jsonwebtoken::Validation::new(Algorithm::EdDSA);
+        assert!(jsonwebtoken::decode::<Claims>(&token, &key, &validation).is_ok());
+    }
+}
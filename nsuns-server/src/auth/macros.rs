@@ -1,9 +1,36 @@
+/// Generates a SQL condition that matches rows owned by the owner in `$owner_id`, or rows of a profile that the owner
+/// was granted `$access` to.
+///
+/// `$profile_id` is the column, or subquery, with the id of the profile a row belongs to. The table must have an
+/// `owner_id` column.
+#[macro_export]
+macro_rules! has_access {
+    ($access:ident, $profile_id:expr, $owner_id:expr) => {
+        const_format::concatcp!(
+            "(owner_id = ",
+            $owner_id,
+            " OR ",
+            $profile_id,
+            " IN (SELECT g.profile_id FROM profile_grants g JOIN users u ON u.id = g.user_id WHERE u.owner_id = ",
+            $owner_id,
+            " AND g.access >= ",
+            $crate::grants::model::Access::$access as i16,
+            "))"
+        )
+    };
+}
+
 /// Generates an expression that will execute a DB query to validate a row is owned by `owner_id`.
 ///
 /// The table must use `id` as the column name of the primary key.
 ///
 /// The expression evaluates to `OperationResult<()>`, with a status code of 409 (conflict) if the row does not match
 /// or does not exist.
+///
+/// When given an access level and the column with the profile id, rows of profiles shared with `owner_id` are also
+/// accepted. The expression then evaluates to `OperationResult<OwnerId>`, with the owner of the row, which new rows
+/// that reference it must be created with. Rows that are shared with less than the given access fail with a status
+/// code of 403 (forbidden).
 #[macro_export]
 macro_rules! assert_owner {
     ($table:expr, $entity_name:expr, $id:ident, $owner_id:ident, $executor:ident) => {
@@ -25,17 +52,54 @@ macro_rules! assert_owner {
             if exists {
                 Ok(())
             } else {
-                Err($crate::error::ErrorWithStatus::new(
-                    http::StatusCode::CONFLICT,
-                    anyhow::anyhow!(const_format::concatcp!(
-                        "referenced ",
-                        $entity_name,
-                        " does not exist"
-                    )),
-                ))
+                Err($crate::assert_owner!(@error $entity_name))
             }
         })
     };
+    ($table:expr, $entity_name:expr, $id:ident, $owner_id:ident, $executor:ident, $access:ident, $profile_id:expr) => {
+        sqlx::query_as::<_, ($crate::auth::token::OwnerId, bool)>(const_format::concatcp!(
+            "SELECT owner_id, ",
+            $crate::has_access!($access, $profile_id, "$2"),
+            " FROM ",
+            $table,
+            " WHERE id = $1 AND ",
+            $crate::has_access!(Read, $profile_id, "$2")
+        ))
+        .bind($id)
+        .bind($owner_id)
+        .fetch_optional($executor.instrument_executor($crate::db_span!(
+            $crate::db::tracing::statements::SELECT,
+            $table
+        )))
+        .await
+        .with_context(|| format!("failed to select {} with id={}", $entity_name, $id))
+        .map_err($crate::into_log_server_error!())
+        .and_then(|row| match row {
+            Some((owner_id, true)) => Ok(owner_id),
+            Some((_, false)) => Err($crate::assert_owner!(@forbidden $entity_name)),
+            None => Err($crate::assert_owner!(@error $entity_name)),
+        })
+    };
+    (@error $entity_name:expr) => {
+        $crate::error::ErrorWithStatus::new(
+            http::StatusCode::CONFLICT,
+            anyhow::anyhow!(const_format::concatcp!(
+                "referenced ",
+                $entity_name,
+                " does not exist"
+            )),
+        )
+    };
+    (@forbidden $entity_name:expr) => {
+        $crate::error::ErrorWithStatus::new(
+            http::StatusCode::FORBIDDEN,
+            anyhow::anyhow!(const_format::concatcp!(
+                "referenced ",
+                $entity_name,
+                " is shared without write access"
+            )),
+        )
+    };
 }
 
 /// Generates an expression that will execute a DB query to validate a number of rows are owned by `owner_id`.
@@ -46,13 +110,42 @@ macro_rules! assert_owner {
 /// or do not exist.
 ///
 /// The ids do not need to be unique.
+///
+/// When given an access level and the column with the profile id, rows of profiles shared with `owner_id` are also
+/// accepted, and rows that are shared with less than the given access fail with a status code of 403 (forbidden).
 #[macro_export]
 macro_rules! assert_all_owner {
     ($table:expr, $entity_name:expr, $ids:ident, $owner_id:ident, $executor:ident) => {
-        sqlx::query_as::<_, (i64,)>(const_format::concatcp!(
-            "SELECT COUNT(1) FROM unnest($1) id JOIN ",
+        $crate::assert_all_owner!(
+            @query $table,
+            $entity_name,
+            $ids,
+            $owner_id,
+            $executor,
+            "owner_id = $2",
+            "owner_id = $2"
+        )
+    };
+    ($table:expr, $entity_name:expr, $ids:ident, $owner_id:ident, $executor:ident, $access:ident, $profile_id:expr) => {
+        $crate::assert_all_owner!(
+            @query $table,
+            $entity_name,
+            $ids,
+            $owner_id,
+            $executor,
+            $crate::has_access!(Read, $profile_id, "$2"),
+            $crate::has_access!($access, $profile_id, "$2")
+        )
+    };
+    (@query $table:expr, $entity_name:expr, $ids:ident, $owner_id:ident, $executor:ident, $visible:expr, $allowed:expr) => {
+        sqlx::query_as::<_, (i64, i64)>(const_format::concatcp!(
+            "SELECT COUNT(1), COUNT(1) FILTER (WHERE ",
+            $allowed,
+            ") FROM unnest($1) id JOIN ",
             $table,
-            " USING (id) WHERE owner_id = $2;"
+            " USING (id) WHERE ",
+            $visible,
+            ";"
         ))
         .bind($ids)
         .bind($owner_id)
@@ -63,18 +156,13 @@ macro_rules! assert_all_owner {
         .await
         .with_context(|| format!("failed to select {} in ids={:?}", $entity_name, $ids))
         .map_err($crate::into_log_server_error!())
-        .and_then(|(count,)| {
-            if count == ($ids.len() as i64) {
-                Ok(())
+        .and_then(|(count, allowed)| {
+            if count != ($ids.len() as i64) {
+                Err($crate::assert_owner!(@error $entity_name))
+            } else if allowed != count {
+                Err($crate::assert_owner!(@forbidden $entity_name))
             } else {
-                Err($crate::error::ErrorWithStatus::new(
-                    http::StatusCode::CONFLICT,
-                    anyhow::anyhow!(const_format::concatcp!(
-                        "referenced ",
-                        $entity_name,
-                        " does not exist"
-                    )),
-                ))
+                Ok(())
             }
         })
     };
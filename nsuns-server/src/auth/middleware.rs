@@ -1,22 +1,32 @@
 use std::sync::Arc;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use axum::{
     extract::{Request, State},
     middleware::Next,
     response::{IntoResponse, Redirect, Response},
 };
-use chrono::Utc;
-use http::{StatusCode, Uri};
+use axum_extra::headers::{authorization::Bearer, Authorization, HeaderMapExt};
+use chrono::{DateTime, Utc};
+use http::{header::USER_AGENT, HeaderMap, Method, StatusCode, Uri};
+use sqlx::Transaction;
 use tower_cookies::Cookies;
 
 use crate::{
     acquire,
-    db::Pool,
+    db::{transaction::commit_ok, Pool, DB},
     error::{extract::WithErrorRejection, ErrorWithStatus, OperationResult},
+    into_log_server_error, transaction,
 };
 
-use super::token::{create_empty_cookie, Claims, JwtKeys, COOKIE_NAME};
+use super::{
+    access_token::{AccessTokenGrant, AccessTokenScope},
+    token::{
+        create_empty_cookie, create_token_cookie, Claims, JwtKeys, Session, SessionLifetimes,
+        COOKIE_NAME,
+    },
+    user::extend_owner_expiry,
+};
 
 #[derive(Debug, Clone)]
 pub struct ClaimsResult(pub Result<Claims, Arc<ErrorWithStatus<anyhow::Error>>>);
@@ -40,11 +50,49 @@ fn unauthorized() -> ErrorWithStatus<anyhow::Error> {
     ErrorWithStatus::new(StatusCode::UNAUTHORIZED, anyhow!("Unauthorized"))
 }
 
+pub(crate) fn account_disabled() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(StatusCode::FORBIDDEN, anyhow!("This account is disabled"))
+}
+
+/// Extend the session, and the anonymous owner if there is one, then reissue the token cookie
+async fn renew_session(
+    tx: &mut Transaction<'_, DB>,
+    keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
+    claims: Claims,
+    cookies: &Cookies,
+) -> OperationResult<Claims> {
+    let exp = lifetimes.create_expiry_date(claims.user_id);
+
+    let Some(claims) = claims.renew(exp, &mut **tx).await? else {
+        cookies.remove(create_empty_cookie());
+        return Err(unauthorized());
+    };
+
+    if claims.user_id.is_none() {
+        extend_owner_expiry(&mut **tx, claims.owner_id, exp)
+            .await
+            .context("failed to extend anonymous owner expiry date")
+            .map_err(into_log_server_error!())?;
+    }
+
+    let token = keys.encode(&claims).map_err(into_log_server_error!())?;
+
+    cookies.add(create_token_cookie(
+        token,
+        lifetimes.cookie_max_age(claims.user_id),
+    ));
+
+    Ok(claims)
+}
+
 #[tracing::instrument(skip_all)]
 async fn get_and_validate_token(
     pool: Pool,
     keys: JwtKeys,
+    lifetimes: SessionLifetimes,
     cookies: Cookies,
+    user_agent: Option<&str>,
 ) -> OperationResult<Claims> {
     let Some(cookie) = cookies.get(COOKIE_NAME) else {
         return Err(unauthorized());
@@ -70,24 +118,94 @@ async fn get_and_validate_token(
     // Verify the token has not been revoked
     let mut conn = acquire!(&pool).await?;
 
-    let stored_claims = Claims::select_one(claims.id, &mut *conn).await?;
+    let Some(session) = Session::select_one(claims.id, &mut *conn).await? else {
+        cookies.remove(create_empty_cookie());
+        return Err(unauthorized());
+    };
 
-    if stored_claims.is_none() {
+    if session.user_disabled {
         cookies.remove(create_empty_cookie());
+        return Err(account_disabled());
+    }
+
+    if session.is_stale(user_agent) {
+        session.touch(user_agent, &mut *conn).await?;
+    }
+
+    if !lifetimes.should_renew(&claims) {
+        return Ok(claims);
+    }
+
+    drop(conn);
+
+    let mut tx = transaction!(&pool).await?;
+    let res = renew_session(&mut tx, &keys, &lifetimes, claims, &cookies).await;
+    commit_ok(res, tx).await
+}
+
+/// Resolve a personal access token to claims for the owner of the user that created it
+#[tracing::instrument(skip_all)]
+async fn get_and_validate_access_token(
+    pool: Pool,
+    token: &str,
+    method: &Method,
+) -> OperationResult<(Claims, AccessTokenScope)> {
+    let mut conn = acquire!(&pool).await?;
+
+    let Some(grant) = AccessTokenGrant::select_by_token(token, &mut *conn).await? else {
         return Err(unauthorized());
+    };
+
+    if grant.user_disabled {
+        return Err(account_disabled());
     }
 
-    Ok(claims)
+    if !grant.scope.allows(method) {
+        return Err(ErrorWithStatus::new(
+            StatusCode::FORBIDDEN,
+            anyhow!("This access token is read-only"),
+        ));
+    }
+
+    if grant.is_stale() {
+        grant.touch(&mut *conn).await?;
+    }
+
+    // Access tokens do not expire, they must be revoked instead
+    let claims = Claims {
+        id: grant.id,
+        owner_id: grant.owner_id,
+        user_id: Some(grant.user_id),
+        exp: DateTime::<Utc>::MAX_UTC,
+    };
+
+    Ok((claims, grant.scope))
 }
 
 pub async fn manage_tokens(
     State(pool): State<Pool>,
     State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
     cookies: Cookies,
+    headers: HeaderMap,
     mut request: Request,
     next: Next,
 ) -> OperationResult<Response> {
-    let claims_result = get_and_validate_token(pool, keys, cookies).await;
+    let claims_result =
+        if let Some(Authorization(bearer)) = headers.typed_get::<Authorization<Bearer>>() {
+            get_and_validate_access_token(pool, bearer.token(), request.method())
+                .await
+                .map(|(claims, scope)| {
+                    request.extensions_mut().insert(scope);
+                    claims
+                })
+        } else {
+            let user_agent = headers
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok());
+
+            get_and_validate_token(pool, keys, lifetimes, cookies, user_agent).await
+        };
 
     request
         .extensions_mut()
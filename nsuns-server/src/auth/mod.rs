@@ -1,12 +1,21 @@
+pub mod access_token;
+pub mod archive;
 pub mod cleanup;
+pub mod csrf;
 pub mod extract;
 pub mod handler;
+pub mod keys;
 pub mod macros;
 pub mod middleware;
+pub mod oidc;
 pub mod openapi;
+pub mod reset;
 pub mod router;
+pub mod secret;
 pub mod settings;
+pub mod throttle;
 pub mod token;
+pub mod two_factor;
 pub mod user;
 
 pub use router::router;
@@ -0,0 +1,179 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Context};
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, DecodingKey, Validation};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use urlencoding::encode;
+
+use super::settings::OidcSettings;
+
+/// The parts of the provider's discovery document that are needed for the authorization code flow
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// Verified claims from an ID token
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    nonce: Option<String>,
+    #[serde(flatten)]
+    extra: HashMap<String, serde_json::Value>,
+}
+
+impl IdTokenClaims {
+    /// Look up a string claim that is not part of the core set
+    #[must_use]
+    pub fn get_str(&self, claim: &str) -> Option<&str> {
+        self.extra.get(claim).and_then(serde_json::Value::as_str)
+    }
+}
+
+/// Client for an OpenID Connect identity provider.
+///
+/// The discovery document is fetched on first use, and signing keys are fetched for every login so key rotation
+/// at the provider is picked up without a restart.
+#[derive(Clone)]
+pub struct OidcClient {
+    pub settings: Arc<OidcSettings>,
+    http: reqwest::Client,
+    metadata: Arc<OnceCell<ProviderMetadata>>,
+}
+
+impl OidcClient {
+    #[must_use]
+    pub fn new(settings: OidcSettings) -> Self {
+        Self {
+            settings: Arc::new(settings),
+            http: reqwest::Client::new(),
+            metadata: Arc::new(OnceCell::new()),
+        }
+    }
+
+    async fn metadata(&self) -> anyhow::Result<&ProviderMetadata> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.settings.issuer.trim_end_matches('/')
+                );
+
+                self.http
+                    .get(url)
+                    .send()
+                    .await
+                    .and_then(reqwest::Response::error_for_status)
+                    .context("failed to fetch OpenID Connect discovery document")?
+                    .json::<ProviderMetadata>()
+                    .await
+                    .context("failed to parse OpenID Connect discovery document")
+            })
+            .await
+    }
+
+    /// Build the URL to send the browser to, to log in at the identity provider
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> anyhow::Result<String> {
+        let endpoint = &self.metadata().await?.authorization_endpoint;
+        let separator = if endpoint.contains('?') { '&' } else { '?' };
+
+        Ok(format!(
+            "{endpoint}{separator}response_type=code&client_id={}&redirect_uri={}&scope={}&state={}&nonce={}&code_challenge={}&code_challenge_method=S256",
+            encode(&self.settings.client_id),
+            encode(&self.settings.redirect_url),
+            encode(&self.settings.scopes.join(" ")),
+            encode(state),
+            encode(nonce),
+            encode(code_challenge),
+        ))
+    }
+
+    /// Exchange an authorization code for an ID token
+    pub async fn exchange_code(&self, code: &str, code_verifier: &str) -> anyhow::Result<String> {
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.settings.redirect_url),
+            ("client_id", &self.settings.client_id),
+            ("code_verifier", code_verifier),
+        ];
+
+        if let Some(secret) = &self.settings.client_secret {
+            form.push(("client_secret", secret.expose_secret()));
+        }
+
+        self.http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("failed to exchange authorization code")?
+            .json::<TokenResponse>()
+            .await
+            .context("failed to parse token response")
+            .map(|res| res.id_token)
+    }
+
+    /// Validate the signature, issuer, audience, expiry and nonce of an ID token
+    pub async fn verify_id_token(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> anyhow::Result<IdTokenClaims> {
+        let metadata = self.metadata().await?;
+
+        let header = decode_header(id_token).context("failed to decode ID token header")?;
+
+        let jwks = self
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("failed to fetch identity provider signing keys")?
+            .json::<JwkSet>()
+            .await
+            .context("failed to parse identity provider signing keys")?;
+
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or_else(|| anyhow!("no signing key found for ID token"))?;
+
+        let key = DecodingKey::from_jwk(jwk).context("unsupported ID token signing key")?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.settings.client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .context("invalid ID token")?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(anyhow!("ID token nonce does not match"));
+        }
+
+        Ok(claims)
+    }
+}
@@ -0,0 +1,298 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Query, State},
+    response::Redirect,
+};
+use chrono::{Duration, Utc};
+use http::StatusCode;
+use rand::Rng;
+use serde::Deserialize;
+use sqlx::Transaction;
+use tower_cookies::{
+    cookie::{CookieBuilder, SameSite},
+    Cookie, Cookies,
+};
+
+use crate::{
+    auth::{
+        handler::{create_user_session, revoke_and_logout},
+        middleware::account_disabled,
+        secret::{generate_secret, hash_secret},
+        token::{decode_claims_from_cookies, JwtKeys, SessionLifetimes},
+        user::{create_owner, insert_passwordless_user, UserInfo},
+    },
+    db::{transaction::commit_ok, Pool, DB},
+    error::{extract::WithErrorRejection, ErrorWithStatus, OperationResult},
+    into_log_server_error, log_server_error,
+    router::OIDC_PATH,
+    transaction,
+};
+
+use super::{
+    client::{IdTokenClaims, OidcClient},
+    model::{link_identity, select_user_by_identity, LinkedUser, PendingLogin},
+};
+
+const STATE_COOKIE_NAME: &str = "OIDC_STATE";
+
+/// How long the user has to log in at the identity provider
+const LOGIN_TIMEOUT_MINUTES: i64 = 10;
+
+/// Attempts to find a free username for a newly provisioned user
+const USERNAME_ATTEMPTS: usize = 5;
+
+fn create_state_cookie<'c>(state: String) -> Cookie<'c> {
+    CookieBuilder::new(STATE_COOKIE_NAME, state)
+        .path(OIDC_PATH)
+        .max_age(time::Duration::minutes(LOGIN_TIMEOUT_MINUTES))
+        .http_only(true)
+        // must be sent on the top-level navigation back from the identity provider
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+fn create_empty_state_cookie<'c>() -> Cookie<'c> {
+    CookieBuilder::new(STATE_COOKIE_NAME, "")
+        .path(OIDC_PATH)
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build()
+}
+
+fn provider_error(e: anyhow::Error) -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(StatusCode::BAD_GATEWAY, e)
+}
+
+fn invalid_login() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(
+        StatusCode::BAD_REQUEST,
+        anyhow!("Invalid or expired login attempt"),
+    )
+}
+
+/// Start logging in with the identity provider
+#[tracing::instrument(skip_all)]
+pub async fn login(
+    State(pool): State<Pool>,
+    State(client): State<OidcClient>,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+) -> OperationResult<Redirect> {
+    let state = generate_secret();
+    let pending = PendingLogin {
+        code_verifier: generate_secret(),
+        nonce: generate_secret(),
+    };
+
+    let url = client
+        .authorization_url(&state, &pending.nonce, &hash_secret(&pending.code_verifier))
+        .await
+        .map_err(provider_error)
+        .map_err(log_server_error!())?;
+
+    let mut tx = transaction!(&pool).await?;
+
+    let res = pending
+        .insert_one(
+            &state,
+            Utc::now() + Duration::minutes(LOGIN_TIMEOUT_MINUTES),
+            &mut *tx,
+        )
+        .await
+        .context("failed to store pending login")
+        .map_err(into_log_server_error!());
+
+    commit_ok(res, tx).await?;
+
+    cookies.add(create_state_cookie(state));
+
+    Ok(Redirect::to(&url))
+}
+
+/// Make an ID token claim into a valid username, so it can be shown and used like any other
+fn sanitize_username(claim: &str) -> String {
+    let username: String = claim
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .take(32)
+        .collect();
+
+    if username.len() < 3 {
+        "user".to_string()
+    } else {
+        username
+    }
+}
+
+/// Create an owner and user for an identity that logged in for the first time
+async fn provision_user(
+    tx: &mut Transaction<'_, DB>,
+    client: &OidcClient,
+    id_token: &IdTokenClaims,
+) -> OperationResult<LinkedUser> {
+    let base = sanitize_username(
+        id_token
+            .get_str(&client.settings.username_claim)
+            .unwrap_or(&id_token.sub),
+    );
+
+    let owner_id = create_owner(&mut **tx)
+        .await
+        .context("failed to create new owner")
+        .map_err(into_log_server_error!())?;
+
+    let mut user_info: Option<UserInfo> = None;
+
+    for attempt in 0..USERNAME_ATTEMPTS {
+        let username = if attempt == 0 {
+            base.clone()
+        } else {
+            let suffix: u16 = rand::thread_rng().gen_range(1000..10000);
+            format!("{}-{suffix}", &base[..base.len().min(27)])
+        };
+
+        user_info = insert_passwordless_user(owner_id, &username, &mut **tx)
+            .await
+            .context("failed to create user")
+            .map_err(into_log_server_error!())?;
+
+        if user_info.is_some() {
+            break;
+        }
+    }
+
+    let user_info = user_info.ok_or_else(|| {
+        ErrorWithStatus::new(
+            StatusCode::CONFLICT,
+            anyhow!("Could not find a free username for the new user"),
+        )
+    })?;
+
+    link_identity(user_info.id, &id_token.iss, &id_token.sub, &mut **tx)
+        .await
+        .context("failed to link identity")
+        .map_err(into_log_server_error!())?;
+
+    Ok(LinkedUser {
+        id: user_info.id,
+        owner_id,
+        disabled: false,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+async fn complete_login(
+    tx: &mut Transaction<'_, DB>,
+    keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
+    client: &OidcClient,
+    params: CallbackParams,
+    cookies: &Cookies,
+) -> OperationResult<()> {
+    if let Some(error) = params.error {
+        return Err(ErrorWithStatus::new(
+            StatusCode::BAD_REQUEST,
+            anyhow!("The identity provider rejected the login: {error}"),
+        ));
+    }
+
+    let (Some(code), Some(state)) = (params.code, params.state) else {
+        return Err(invalid_login());
+    };
+
+    // the state must have been issued to this browser
+    if cookies
+        .get(STATE_COOKIE_NAME)
+        .is_none_or(|cookie| cookie.value() != state)
+    {
+        return Err(invalid_login());
+    }
+
+    cookies.remove(create_empty_state_cookie());
+
+    let pending = PendingLogin::redeem(&state, &mut **tx)
+        .await
+        .context("failed to redeem pending login")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(invalid_login)?;
+
+    let id_token = client
+        .exchange_code(&code, &pending.code_verifier)
+        .await
+        .map_err(provider_error)
+        .map_err(log_server_error!())?;
+
+    let id_token = client
+        .verify_id_token(&id_token, &pending.nonce)
+        .await
+        .map_err(|e| ErrorWithStatus::new(StatusCode::UNAUTHORIZED, e))
+        .map_err(log_server_error!())?;
+
+    let user = match select_user_by_identity(&id_token.iss, &id_token.sub, &mut **tx)
+        .await
+        .context("failed to fetch linked user")
+        .map_err(into_log_server_error!())?
+    {
+        Some(user) => user,
+        None => provision_user(tx, client, &id_token).await?,
+    };
+
+    if user.disabled {
+        return Err(account_disabled());
+    }
+
+    if let Some(claims) = decode_claims_from_cookies(keys, cookies) {
+        revoke_and_logout(claims, tx).await?;
+    }
+
+    create_user_session(
+        &mut **tx,
+        keys,
+        lifetimes,
+        user.owner_id.as_uuid(),
+        user.id,
+        cookies,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Complete logging in with the identity provider, creating the user on first login
+#[tracing::instrument(skip_all)]
+pub async fn callback(
+    State(pool): State<Pool>,
+    State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
+    State(client): State<OidcClient>,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+    WithErrorRejection(Query(params)): WithErrorRejection<Query<CallbackParams>>,
+) -> OperationResult<Redirect> {
+    let mut tx = transaction!(&pool).await?;
+
+    let res = complete_login(&mut tx, &keys, &lifetimes, &client, params, &cookies).await;
+
+    commit_ok(res, tx).await?;
+
+    Ok(Redirect::to(&client.settings.post_login_redirect))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_username;
+
+    #[test]
+    fn usernames_are_sanitized() {
+        assert_eq!("jane.doe", sanitize_username("jane.doe"));
+        assert_eq!(
+            "janedoeexample.com",
+            sanitize_username("jane doe@example.com")
+        );
+        assert_eq!("user", sanitize_username("李"));
+    }
+}
@@ -0,0 +1,67 @@
+//! Log in with an OpenID Connect identity provider, using the authorization code flow with PKCE.
+//!
+//! External identities are linked to users, and a new owner and user are provisioned on first login.
+
+pub mod client;
+pub mod handler;
+pub mod model;
+pub mod settings;
+
+use axum::{extract::FromRef, routing::get, Router};
+use axum_macros::FromRef;
+
+use crate::{
+    db::Pool,
+    router::{AppState, OIDC_PATH},
+};
+
+use self::{
+    client::OidcClient,
+    handler::{callback, login},
+    settings::OidcFeature,
+};
+
+use super::token::{JwtKeys, SessionLifetimes};
+
+#[derive(Clone, FromRef)]
+pub struct OidcState {
+    pub pool: Pool,
+    pub keys: JwtKeys,
+    pub lifetimes: SessionLifetimes,
+    pub client: OidcClient,
+}
+
+pub fn router<S>() -> Router<S>
+where
+    S: crate::router::State,
+    Pool: FromRef<S>,
+    JwtKeys: FromRef<S>,
+    SessionLifetimes: FromRef<S>,
+    OidcClient: FromRef<S>,
+{
+    Router::new()
+        .route("/login", get(login))
+        .route("/callback", get(callback))
+}
+
+pub trait WithOidc {
+    #[must_use]
+    fn with_oidc(self, state: &AppState, settings: &OidcFeature) -> Self;
+}
+
+impl WithOidc for Router<AppState> {
+    fn with_oidc(self, state: &AppState, settings: &OidcFeature) -> Self {
+        if let OidcFeature::Enabled(settings) = settings {
+            let oidc_state = OidcState {
+                pool: state.pool.clone(),
+                keys: state.keys.clone(),
+                lifetimes: state.lifetimes,
+                client: OidcClient::new(settings.clone()),
+            };
+
+            self.nest(OIDC_PATH, router().with_state(oidc_state))
+        } else {
+            self
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use const_format::formatcp;
+use sqlx::{Executor, FromRow};
+use uuid::Uuid;
+
+use crate::{
+    auth::{secret::hash_secret, token::OwnerId},
+    db::{
+        tracing::{
+            statements::{DELETE_FROM, INSERT_INTO, SELECT},
+            InstrumentExecutor,
+        },
+        DB,
+    },
+    db_span,
+};
+
+const PENDING_TABLE: &str = "oidc_pending_logins";
+
+const IDENTITIES_TABLE: &str = "user_identities";
+
+/// Secrets for an authorization request, needed to complete the login when the browser returns
+#[derive(Debug, Clone, FromRow)]
+pub struct PendingLogin {
+    pub code_verifier: String,
+    pub nonce: String,
+}
+
+impl PendingLogin {
+    pub async fn insert_one(
+        &self,
+        state: &str,
+        exp: DateTime<Utc>,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(formatcp!(
+            "{INSERT_INTO} {PENDING_TABLE} (state_hash, code_verifier, nonce, exp) VALUES ($1, $2, $3, $4)"
+        ))
+        .bind(hash_secret(state))
+        .bind(&self.code_verifier)
+        .bind(&self.nonce)
+        .bind(exp)
+        .execute(executor.instrument_executor(db_span!(INSERT_INTO, PENDING_TABLE)))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Consume the pending login for a state parameter, if it exists and has not expired
+    pub async fn redeem(
+        state: &str,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<Option<PendingLogin>, sqlx::Error> {
+        sqlx::query_as::<_, PendingLogin>(formatcp!(
+            "{DELETE_FROM} {PENDING_TABLE} WHERE state_hash = $1 AND exp > $2 RETURNING code_verifier, nonce"
+        ))
+        .bind(hash_secret(state))
+        .bind(Utc::now())
+        .fetch_optional(executor.instrument_executor(db_span!(DELETE_FROM, PENDING_TABLE)))
+        .await
+    }
+}
+
+/// A user linked to an external identity
+#[derive(Debug, Clone, FromRow)]
+pub struct LinkedUser {
+    pub id: Uuid,
+    pub owner_id: OwnerId,
+    pub disabled: bool,
+}
+
+pub async fn select_user_by_identity(
+    issuer: &str,
+    subject: &str,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<Option<LinkedUser>, sqlx::Error> {
+    sqlx::query_as::<_, LinkedUser>(formatcp!(
+        "{SELECT} u.id, u.owner_id, u.disabled FROM {IDENTITIES_TABLE} i JOIN users u ON u.id = i.user_id WHERE i.issuer = $1 AND i.subject = $2"
+    ))
+    .bind(issuer)
+    .bind(subject)
+    .fetch_optional(executor.instrument_executor(db_span!(SELECT, IDENTITIES_TABLE)))
+    .await
+}
+
+pub async fn link_identity(
+    user_id: Uuid,
+    issuer: &str,
+    subject: &str,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(formatcp!(
+        "{INSERT_INTO} {IDENTITIES_TABLE} (user_id, issuer, subject) VALUES ($1, $2, $3)"
+    ))
+    .bind(user_id)
+    .bind(issuer)
+    .bind(subject)
+    .execute(executor.instrument_executor(db_span!(INSERT_INTO, IDENTITIES_TABLE)))
+    .await?;
+
+    Ok(())
+}
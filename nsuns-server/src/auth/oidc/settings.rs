@@ -0,0 +1,43 @@
+use secrecy::SecretString;
+use serde::Deserialize;
+
+use crate::feature::Feature;
+
+fn default_scopes() -> Vec<String> {
+    vec!["openid".to_string(), "profile".to_string()]
+}
+
+fn default_username_claim() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_post_login_redirect() -> String {
+    "/".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcSettings {
+    /// The issuer URL of the identity provider, used for discovery and to validate ID tokens
+    pub issuer: String,
+    pub client_id: String,
+    /// Leave unset for public clients, which rely on PKCE alone
+    pub client_secret: Option<SecretString>,
+    /// The URL of `/api/auth/oidc/callback` on this server, as registered with the identity provider
+    pub redirect_url: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    /// The ID token claim to use as the username of newly provisioned users
+    #[serde(default = "default_username_claim")]
+    pub username_claim: String,
+    /// Where to send the browser after logging in
+    #[serde(default = "default_post_login_redirect")]
+    pub post_login_redirect: String,
+}
+
+pub type OidcFeature = Feature<OidcSettings>;
+
+impl Default for OidcFeature {
+    fn default() -> Self {
+        Self::Disabled
+    }
+}
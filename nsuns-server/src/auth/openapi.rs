@@ -1,28 +1,43 @@
-use const_format::formatcp;
+use axum::http::StatusCode;
+use const_format::{concatcp, formatcp};
 use utoipa::{
     openapi::{
         path::{OperationBuilder, PathItemBuilder},
+        request_body::RequestBodyBuilder,
         security::{Http, HttpAuthScheme, SecurityScheme},
-        ComponentsBuilder, PathItem, PathItemType, PathsBuilder, ResponseBuilder,
+        ComponentsBuilder, PathItem, PathItemType, PathsBuilder, RefOr, ResponseBuilder, Schema,
         SecurityRequirement,
     },
-    ToSchema,
+    PartialSchema, ToSchema,
 };
 
 use crate::{
     openapi::{
-        extensions::{no_content, ok, JsonContent},
+        extensions::{created, id_path_param, no_content, ok, JsonContent},
         Customizer,
     },
-    router::AUTH_PATH,
+    router::{AUTH_PATH, OIDC_PATH},
 };
 
-use super::user::{AgentInfo, AnonymousInfo, UserInfo};
+use super::{
+    access_token::{AccessToken, AccessTokenScope, CreateAccessToken, CreatedAccessToken},
+    archive::model::{Archive, ArchivedProgram},
+    token::SessionInfo,
+    two_factor::model::{
+        CompleteLogin, LoginChallenge, RecoveryCodes, SecondFactor, TotpEnrollment, TwoFactorStatus,
+    },
+    user::{
+        AgentInfo, AnonymousInfo, ChangePassword, CreateUser, DeleteAccount, ResetPassword,
+        UserInfo,
+    },
+};
 
 pub struct AuthModule;
 
 pub const BASIC_AUTH: &str = "basic_auth";
 
+pub const BEARER_AUTH: &str = "bearer_auth";
+
 const TAG: &str = "Auth";
 
 impl Customizer<ComponentsBuilder> for AuthModule {
@@ -32,12 +47,46 @@ impl Customizer<ComponentsBuilder> for AuthModule {
                 BASIC_AUTH,
                 SecurityScheme::Http(Http::new(HttpAuthScheme::Basic)),
             )
+            .security_scheme(
+                BEARER_AUTH,
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            )
             .schema_from::<UserInfo>()
             .schema_from::<AnonymousInfo>()
             .schema_from::<AgentInfo>()
+            .schema_from::<CreateUser>()
+            .schema_from::<ChangePassword>()
+            .schema_from::<ResetPassword>()
+            .schema_from::<SessionInfo>()
+            .schema_from::<AccessTokenScope>()
+            .schema_from::<AccessToken>()
+            .schema_from::<CreateAccessToken>()
+            .schema_from::<CreatedAccessToken>()
+            .schema_from::<TwoFactorStatus>()
+            .schema_from::<TotpEnrollment>()
+            .schema_from::<RecoveryCodes>()
+            .schema_from::<SecondFactor>()
+            .schema_from::<LoginChallenge>()
+            .schema_from::<CompleteLogin>()
+            .schema_from::<DeleteAccount>()
+            .schema_from::<ArchivedProgram>()
+            .schema_from::<Archive>()
     }
 }
 
+fn create_json_auth_path<S: Into<RefOr<Schema>>>(description: &str, body: S) -> PathItem {
+    let post_op = OperationBuilder::new()
+        .description(Some(description))
+        .request_body(Some(RequestBodyBuilder::new().json_content(body).build()))
+        .response(no_content(), ResponseBuilder::new().build())
+        .tag(TAG)
+        .build();
+
+    PathItemBuilder::new()
+        .operation(PathItemType::Post, post_op)
+        .build()
+}
+
 fn create_auth_path(description: &str) -> PathItem {
     let post_op = OperationBuilder::new()
         .description(Some(description))
@@ -50,22 +99,309 @@ fn create_auth_path(description: &str) -> PathItem {
         .build()
 }
 
+fn create_recovery_codes_path(description: &str) -> PathItem {
+    let post_op = OperationBuilder::new()
+        .description(Some(description))
+        .request_body(Some(
+            RequestBodyBuilder::new()
+                .json_content(SecondFactor::schema().1)
+                .build(),
+        ))
+        .response(
+            ok(),
+            ResponseBuilder::new()
+                .description("New recovery codes. These are only returned once.")
+                .json_content(RecoveryCodes::schema().1)
+                .build(),
+        )
+        .tag(TAG)
+        .build();
+
+    PathItemBuilder::new()
+        .operation(PathItemType::Post, post_op)
+        .build()
+}
+
+fn create_redirect_path(description: &str) -> PathItem {
+    let get_op = OperationBuilder::new()
+        .description(Some(description))
+        .response(
+            StatusCode::SEE_OTHER.as_str(),
+            ResponseBuilder::new().build(),
+        )
+        .tag(TAG)
+        .build();
+
+    PathItemBuilder::new()
+        .operation(PathItemType::Get, get_op)
+        .build()
+}
+
 impl Customizer<PathsBuilder> for AuthModule {
     fn customize(builder: PathsBuilder) -> PathsBuilder {
         let login_op = OperationBuilder::new()
-            .description(Some("Log in as a persistent user. Use the Authorize button to set the credentials to use for this request."))
+            .description(Some("Log in as a persistent user. Use the Authorize button to set the credentials to use for this request. If the user has two-factor authentication enabled, a login challenge is returned instead, to complete with the second factor."))
             .response(no_content(), ResponseBuilder::new().build())
+            .response(
+                StatusCode::ACCEPTED.as_str(),
+                ResponseBuilder::new()
+                    .description("The second factor is required")
+                    .json_content(LoginChallenge::schema().1)
+                    .build(),
+            )
             .security(SecurityRequirement::new::<_, _, &str>(BASIC_AUTH, []))
             .tag(TAG)
             .build();
 
+        let register_op = OperationBuilder::new()
+            .description(Some("Create a persistent user, and log in as that user"))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(CreateUser::schema().1)
+                    .build(),
+            ))
+            .response(
+                created(),
+                ResponseBuilder::new()
+                    .description("The new user")
+                    .json_content(UserInfo::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let claim_op = OperationBuilder::new()
+            .description(Some("Convert the current anonymous session into a persistent user. All data created while anonymous is kept."))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(CreateUser::schema().1)
+                    .build(),
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .description("The new user")
+                    .json_content(UserInfo::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let list_sessions_op = OperationBuilder::new()
+            .description(Some("List the active sessions of the logged-in user"))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<SessionInfo>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let logout_everywhere_op = OperationBuilder::new()
+            .description(Some(
+                "Log out everywhere, by revoking every session of the logged-in user",
+            ))
+            .response(no_content(), ResponseBuilder::new().build())
+            .tag(TAG)
+            .build();
+
+        let revoke_session_op = OperationBuilder::new()
+            .description(Some("Revoke a session of the logged-in user"))
+            .parameters(id_path_param(Some("The id of the session to revoke")))
+            .response(no_content(), ResponseBuilder::new().build())
+            .tag(TAG)
+            .build();
+
+        let list_tokens_op = OperationBuilder::new()
+            .description(Some(
+                "List the personal access tokens of the logged-in user",
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<AccessToken>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let create_token_op = OperationBuilder::new()
+            .description(Some("Create a personal access token. The token is only returned once, and can be used with the bearer_auth security scheme."))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(CreateAccessToken::schema().1)
+                    .build(),
+            ))
+            .response(
+                created(),
+                ResponseBuilder::new()
+                    .description("The new access token")
+                    .json_content(CreatedAccessToken::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let revoke_token_op = OperationBuilder::new()
+            .description(Some("Revoke a personal access token of the logged-in user"))
+            .parameters(id_path_param(Some("The id of the access token to revoke")))
+            .response(no_content(), ResponseBuilder::new().build())
+            .tag(TAG)
+            .build();
+
+        let two_factor_status_op = OperationBuilder::new()
+            .description(Some(
+                "Whether the logged-in user has two-factor authentication enabled",
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(TwoFactorStatus::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let enroll_op = OperationBuilder::new()
+            .description(Some("Generate a new TOTP secret for the logged-in user. Two-factor authentication is enabled once the secret is confirmed with a code."))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(TotpEnrollment::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let delete_account_op = OperationBuilder::new()
+            .description(Some("Permanently delete the logged-in user, and all of their data. Requires the current password, or for users without a password, a recent login."))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(DeleteAccount::schema().1)
+                    .build(),
+            ))
+            .response(no_content(), ResponseBuilder::new().build())
+            .tag(TAG)
+            .build();
+
+        let export_op = OperationBuilder::new()
+            .description(Some("Download everything that belongs to the current user, as an archive that can be restored later"))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Archive::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
         builder
+            .path(
+                formatcp!("{AUTH_PATH}/account"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Delete, delete_account_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/export"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, export_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/restore"),
+                create_json_auth_path(
+                    "Restore an exported archive. Copies of everything in the archive are created for the current user, and movements are matched by name.",
+                    Archive::schema().1,
+                ),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/login/two-factor"),
+                create_json_auth_path(
+                    "Complete a login challenge with a code from an authenticator app, or a recovery code",
+                    CompleteLogin::schema().1,
+                ),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/two-factor"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, two_factor_status_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/two-factor/enroll"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, enroll_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/two-factor/confirm"),
+                create_recovery_codes_path("Enable two-factor authentication with a code for the newly enrolled secret"),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/two-factor/recovery-codes"),
+                create_recovery_codes_path("Replace the recovery codes of the logged-in user. Requires a code from an authenticator app, or a recovery code. After 5 wrong codes, all sessions of the user are logged out."),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/two-factor/disable"),
+                create_json_auth_path(
+                    "Turn off two-factor authentication. Requires a code from an authenticator app, or a recovery code. After 5 wrong codes, all sessions of the user are logged out.",
+                    SecondFactor::schema().1,
+                ),
+            )
+            .path(
+                formatcp!("{OIDC_PATH}/login"),
+                create_redirect_path("Log in with the OpenID Connect identity provider, if one is configured. Redirects to the identity provider."),
+            )
+            .path(
+                formatcp!("{OIDC_PATH}/callback"),
+                create_redirect_path("Complete logging in with the OpenID Connect identity provider. The identity provider redirects here after logging in."),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/tokens"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, list_tokens_op)
+                    .operation(PathItemType::Post, create_token_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(AUTH_PATH, "/tokens/{id}"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Delete, revoke_token_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/sessions"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, list_sessions_op)
+                    .operation(PathItemType::Delete, logout_everywhere_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(AUTH_PATH, "/sessions/{id}"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Delete, revoke_session_op)
+                    .build(),
+            )
             .path(
                 formatcp!("{AUTH_PATH}/login"),
                 PathItemBuilder::new()
                     .operation(PathItemType::Post, login_op)
                     .build(),
             )
+            .path(
+                formatcp!("{AUTH_PATH}/register"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, register_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/claim"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, claim_op)
+                    .build(),
+            )
             .path(
                 formatcp!("{AUTH_PATH}/anonymous"),
                 create_auth_path("Log in as a temporary, anonymous user"),
@@ -74,6 +410,38 @@ impl Customizer<PathsBuilder> for AuthModule {
                 formatcp!("{AUTH_PATH}/logout"),
                 create_auth_path("Log out. This will delete data if the login is anonymous"),
             )
+            .path(
+                formatcp!("{AUTH_PATH}/password"),
+                create_json_auth_path(
+                    "Change the password of the logged-in user. This will log out all other sessions for the user, and revoke their access tokens.",
+                    ChangePassword::schema().1,
+                ),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/password-reset"),
+                create_json_auth_path(
+                    "Set a new password using a single-use reset token. This will log out all sessions for the user, and revoke their access tokens.",
+                    ResetPassword::schema().1,
+                ),
+            )
+            .path(
+                formatcp!("{AUTH_PATH}/jwks"),
+                PathItemBuilder::new()
+                    .operation(
+                        PathItemType::Get,
+                        OperationBuilder::new()
+                            .description(Some(
+                                "Get the public keys that session tokens are signed with, as a JSON Web Key Set. Only available if publishing them is enabled.",
+                            ))
+                            .response(
+                                ok(),
+                                ResponseBuilder::new().description("The JSON Web Key Set"),
+                            )
+                            .tag(TAG)
+                            .build(),
+                    )
+                    .build(),
+            )
             .path(
                 formatcp!("{AUTH_PATH}/user-info"),
                 PathItemBuilder::new()
@@ -0,0 +1,99 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use const_format::formatcp;
+use sqlx::{Executor, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        self,
+        tracing::{
+            statements::{DELETE_FROM, INSERT_INTO},
+            InstrumentExecutor,
+        },
+        DB,
+    },
+    db_span,
+    settings::Settings,
+};
+
+use super::{
+    secret::{generate_secret, hash_secret},
+    user::select_user_id_by_username,
+};
+
+const TABLE: &str = "password_reset_tokens";
+
+/// Create a new password reset token for a user.
+///
+/// Returns the plaintext token, which is not stored and cannot be recovered later.
+pub async fn mint_reset_token(
+    executor: impl Executor<'_, Database = DB>,
+    user_id: Uuid,
+    exp: DateTime<Utc>,
+) -> Result<String, sqlx::Error> {
+    let token = generate_secret();
+
+    sqlx::query(formatcp!(
+        "{INSERT_INTO} {TABLE} (user_id, token_hash, exp) VALUES ($1, $2, $3)"
+    ))
+    .bind(user_id)
+    .bind(hash_secret(&token))
+    .bind(exp)
+    .execute(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+    .await?;
+
+    Ok(token)
+}
+
+/// Consume a password reset token, returning the id of the user it was issued for.
+///
+/// Returns [`None`] if the token does not exist or is expired.
+/// Any other outstanding tokens for the same user are invalidated as well.
+pub async fn redeem_reset_token(
+    tx: &mut Transaction<'_, DB>,
+    token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let user_id = sqlx::query_as::<_, (Uuid,)>(formatcp!(
+        "{DELETE_FROM} {TABLE} WHERE token_hash = $1 AND exp > $2 RETURNING user_id"
+    ))
+    .bind(hash_secret(token))
+    .bind(Utc::now())
+    .fetch_optional((&mut **tx).instrument_executor(db_span!(DELETE_FROM, TABLE)))
+    .await?
+    .map(|(user_id,)| user_id);
+
+    if let Some(user_id) = user_id {
+        sqlx::query(formatcp!("{DELETE_FROM} {TABLE} WHERE user_id = $1"))
+            .bind(user_id)
+            .execute((&mut **tx).instrument_executor(db_span!(DELETE_FROM, TABLE)))
+            .await?;
+    }
+
+    Ok(user_id)
+}
+
+/// Mint a password reset token for `username` and print it to stdout.
+///
+/// This is intended to be run from the command line by an administrator.
+pub async fn mint_from_cli(settings: &Settings, username: &str) -> anyhow::Result<()> {
+    let pool = db::prepare(&settings.database).await?;
+    let mut conn = crate::acquire_unlogged!(&pool).await?;
+
+    let user_id = select_user_id_by_username(username, &mut *conn)
+        .await
+        .context("failed to look up user")?
+        .with_context(|| format!("user {username:?} does not exist"))?;
+
+    let exp = Utc::now()
+        + chrono::Duration::from_std(settings.auth.password_reset_ttl)
+            .context("password reset ttl is out of range")?;
+
+    let token = mint_reset_token(&mut *conn, user_id, exp)
+        .await
+        .context("failed to create password reset token")?;
+
+    println!("{token}");
+
+    Ok(())
+}
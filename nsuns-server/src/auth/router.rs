@@ -1,24 +1,59 @@
 use axum::{
     extract::FromRef,
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 
-use crate::{db::Pool, router::State};
+use crate::{db::Pool, quotas::settings::QuotaSettings, router::State};
 
 use super::{
-    handler::{agent_info, anonymous, login, logout},
-    token::JwtKeys,
+    archive::handler::{export, restore},
+    handler::{
+        agent_info, anonymous, change_password, claim, create_access_token, delete_account, jwks,
+        list_access_tokens, list_sessions, login, logout, logout_everywhere, register,
+        reset_password, revoke_access_token, revoke_session,
+    },
+    settings::TwoFactorSettings,
+    throttle::LoginThrottle,
+    token::{JwtKeys, SessionLifetimes},
+    two_factor::handler::{
+        complete_login, confirm, disable, enroll, regenerate_recovery_codes, status,
+    },
 };
 
 pub fn router<S: State>() -> Router<S>
 where
     Pool: FromRef<S>,
     JwtKeys: FromRef<S>,
+    SessionLifetimes: FromRef<S>,
+    LoginThrottle: FromRef<S>,
+    TwoFactorSettings: FromRef<S>,
+    QuotaSettings: FromRef<S>,
 {
     Router::new()
         .route("/login", post(login))
+        .route("/login/two-factor", post(complete_login))
+        .route("/register", post(register))
         .route("/anonymous", post(anonymous))
+        .route("/claim", post(claim))
         .route("/logout", post(logout))
+        .route("/password", post(change_password))
+        .route("/password-reset", post(reset_password))
         .route("/user-info", get(agent_info))
+        .route("/account", delete(delete_account))
+        .route("/export", get(export))
+        .route("/restore", post(restore))
+        .route("/jwks", get(jwks))
+        .route("/sessions", get(list_sessions).delete(logout_everywhere))
+        .route("/sessions/:id", delete(revoke_session))
+        .route("/tokens", get(list_access_tokens).post(create_access_token))
+        .route("/tokens/:id", delete(revoke_access_token))
+        .route("/two-factor", get(status))
+        .route("/two-factor/enroll", post(enroll))
+        .route("/two-factor/confirm", post(confirm))
+        .route("/two-factor/disable", post(disable))
+        .route(
+            "/two-factor/recovery-codes",
+            post(regenerate_recovery_codes),
+        )
 }
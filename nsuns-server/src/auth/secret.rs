@@ -0,0 +1,41 @@
+//! Opaque secrets handed out to clients, such as password reset tokens.
+//!
+//! Only a digest of the secret is stored. Unlike passwords, these secrets have enough entropy that a fast,
+//! unsalted digest is sufficient, which also allows looking them up by their digest.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+const SECRET_BYTES: usize = 32;
+
+/// Generate a new random, url-safe secret
+#[must_use]
+pub fn generate_secret() -> String {
+    let mut bytes = [0_u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Compute the digest of a secret to store or look up
+#[must_use]
+pub fn hash_secret(secret: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(secret.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_are_unique() {
+        assert_ne!(generate_secret(), generate_secret());
+    }
+
+    #[test]
+    fn hash_is_deterministic() {
+        let secret = generate_secret();
+        assert_eq!(hash_secret(&secret), hash_secret(&secret));
+        assert_ne!(hash_secret(&secret), secret);
+    }
+}
@@ -1,13 +1,301 @@
+use std::{net::IpAddr, time::Duration};
+
 use config::builder::BuilderState;
+use http::HeaderName;
+use jsonwebtoken::Algorithm;
 use secrecy::SecretString;
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer};
 
 use crate::settings::{CustomizeConfigBuilder, SetEnvOverride};
 
+use super::oidc::settings::OidcFeature;
+
+#[must_use]
+pub fn default_password_reset_ttl() -> Duration {
+    Duration::from_secs(60 * 60)
+}
+
+#[must_use]
+pub fn default_user_session_ttl() -> Duration {
+    Duration::from_secs(30 * 24 * 60 * 60)
+}
+
+#[must_use]
+pub fn default_anonymous_session_ttl() -> Duration {
+    Duration::from_secs(2 * 24 * 60 * 60)
+}
+
+#[must_use]
+pub fn default_recent_login_ttl() -> Duration {
+    Duration::from_secs(10 * 60)
+}
+
+#[must_use]
+pub fn default_session_renewal_threshold() -> f64 {
+    0.5
+}
+
+/// Only accept thresholds that are a fraction of the session lifetime
+fn deserialize_renewal_threshold<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let threshold = f64::deserialize(deserializer)?;
+
+    if (0.0..=1.0).contains(&threshold) {
+        Ok(threshold)
+    } else {
+        Err(de::Error::custom(format!(
+            "session_renewal_threshold must be between 0 and 1, found {threshold}"
+        )))
+    }
+}
+
+/// The longest lifetime accepted for sessions and tokens, which keeps their expiry dates representable
+const MAX_TTL: Duration = Duration::from_secs(10 * 365 * 24 * 60 * 60);
+
+/// Only accept lifetimes up to [`MAX_TTL`]
+fn deserialize_ttl<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ttl = crate::serde_duration::deserialize(deserializer)?;
+
+    if ttl <= MAX_TTL {
+        Ok(ttl)
+    } else {
+        Err(de::Error::custom(format!(
+            "lifetime must be at most 10 years, found {ttl:?}"
+        )))
+    }
+}
+
+/// The longest wait or lockout accepted for login throttling
+const MAX_THROTTLE_DURATION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Only accept throttling durations up to [`MAX_THROTTLE_DURATION`]
+fn deserialize_throttle_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let duration = crate::serde_duration::deserialize(deserializer)?;
+
+    if duration <= MAX_THROTTLE_DURATION {
+        Ok(duration)
+    } else {
+        Err(de::Error::custom(format!(
+            "login throttle durations must be at most 7 days, found {duration:?}"
+        )))
+    }
+}
+
+fn default_free_attempts() -> u32 {
+    3
+}
+
+fn default_base_delay() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_max_delay() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_username_lockout_attempts() -> u32 {
+    10
+}
+
+fn default_address_lockout_attempts() -> u32 {
+    50
+}
+
+fn default_username_total_lockout_attempts() -> u32 {
+    100
+}
+
+fn default_lockout_duration() -> Duration {
+    Duration::from_secs(15 * 60)
+}
+
+fn default_reset_after() -> Duration {
+    Duration::from_secs(15 * 60)
+}
+
+fn default_client_address_header() -> HeaderName {
+    HeaderName::from_static("x-forwarded-for")
+}
+
+fn deserialize_header_name<'de, D>(deserializer: D) -> Result<HeaderName, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+
+    HeaderName::try_from(name.as_str())
+        .map_err(|_| de::Error::custom(format!("invalid header name {name:?}")))
+}
+
+/// Limits on failed password logins, tracked per username and client address, per client address, and per username
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoginThrottleSettings {
+    /// Failed attempts allowed before the client must wait between attempts
+    #[serde(default = "default_free_attempts")]
+    pub free_attempts: u32,
+    /// Wait after the first failure beyond the free attempts, which doubles with each further failure
+    #[serde(
+        default = "default_base_delay",
+        deserialize_with = "deserialize_throttle_duration"
+    )]
+    pub base_delay: Duration,
+    #[serde(
+        default = "default_max_delay",
+        deserialize_with = "deserialize_throttle_duration"
+    )]
+    pub max_delay: Duration,
+    /// Failed attempts for a single username from a single client address before it is locked out
+    #[serde(default = "default_username_lockout_attempts")]
+    pub username_lockout_attempts: u32,
+    /// Failed attempts from a single client address before it is locked out
+    #[serde(default = "default_address_lockout_attempts")]
+    pub address_lockout_attempts: u32,
+    /// Failed attempts for a single username from all client addresses before it is locked out.
+    ///
+    /// This should be higher than `address_lockout_attempts`, so a single client address cannot lock a user out.
+    #[serde(default = "default_username_total_lockout_attempts")]
+    pub username_total_lockout_attempts: u32,
+    #[serde(
+        default = "default_lockout_duration",
+        deserialize_with = "deserialize_throttle_duration"
+    )]
+    pub lockout_duration: Duration,
+    /// Failures are forgotten after this long without another failure
+    #[serde(
+        default = "default_reset_after",
+        deserialize_with = "deserialize_throttle_duration"
+    )]
+    pub reset_after: Duration,
+    /// Reverse proxies trusted to report the client address in `client_address_header`
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// A comma-separated list of addresses, where each trusted proxy appends the address it received the request from
+    #[serde(
+        default = "default_client_address_header",
+        deserialize_with = "deserialize_header_name"
+    )]
+    pub client_address_header: HeaderName,
+}
+
+impl Default for LoginThrottleSettings {
+    fn default() -> Self {
+        Self {
+            free_attempts: default_free_attempts(),
+            base_delay: default_base_delay(),
+            max_delay: default_max_delay(),
+            username_lockout_attempts: default_username_lockout_attempts(),
+            address_lockout_attempts: default_address_lockout_attempts(),
+            username_total_lockout_attempts: default_username_total_lockout_attempts(),
+            lockout_duration: default_lockout_duration(),
+            reset_after: default_reset_after(),
+            trusted_proxies: Vec::new(),
+            client_address_header: default_client_address_header(),
+        }
+    }
+}
+
+fn default_totp_issuer() -> String {
+    "nsuns".to_string()
+}
+
+fn default_challenge_ttl() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TwoFactorSettings {
+    /// The name authenticator apps show next to the account
+    #[serde(default = "default_totp_issuer")]
+    pub issuer: String,
+    /// How long a user has to enter the second factor after entering their password
+    #[serde(
+        default = "default_challenge_ttl",
+        deserialize_with = "deserialize_ttl"
+    )]
+    pub challenge_ttl: Duration,
+}
+
+impl Default for TwoFactorSettings {
+    fn default() -> Self {
+        Self {
+            issuer: default_totp_issuer(),
+            challenge_ttl: default_challenge_ttl(),
+        }
+    }
+}
+
+fn default_jwt_algorithm() -> Algorithm {
+    Algorithm::HS256
+}
+
+/// A key for signing session tokens
+#[derive(Debug, Clone, Deserialize)]
+pub struct JwtKeySettings {
+    /// Identifies the key in the `kid` header of the tokens it signs
+    pub kid: String,
+    /// One of `HS256`, `RS256` or `EdDSA`
+    #[serde(default = "default_jwt_algorithm")]
+    pub algorithm: Algorithm,
+    /// The shared secret for `HS256`, or the PEM-encoded private key for `RS256` and `EdDSA`
+    pub key: SecretString,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthSettings {
-    pub jwt_encoding_secret: SecretString,
-    pub jwt_decoding_secret: SecretString,
+    /// Keys for signing session tokens. The last key signs new tokens, and tokens signed by any of them are accepted.
+    #[serde(default)]
+    pub jwt_keys: Vec<JwtKeySettings>,
+    /// The HS256 secrets to use when `jwt_keys` is empty
+    pub jwt_encoding_secret: Option<SecretString>,
+    pub jwt_decoding_secret: Option<SecretString>,
+    /// Serve the public signing keys at `/api/auth/jwks`, so other services can verify session tokens
+    #[serde(default)]
+    pub publish_jwks: bool,
+    /// How long a password reset token can be used after it is created
+    #[serde(
+        default = "default_password_reset_ttl",
+        deserialize_with = "deserialize_ttl"
+    )]
+    pub password_reset_ttl: Duration,
+    /// How long a session for a registered user lasts without activity
+    #[serde(
+        default = "default_user_session_ttl",
+        deserialize_with = "deserialize_ttl"
+    )]
+    pub user_session_ttl: Duration,
+    /// How long an anonymous session, and its data, lasts without activity
+    #[serde(
+        default = "default_anonymous_session_ttl",
+        deserialize_with = "deserialize_ttl"
+    )]
+    pub anonymous_session_ttl: Duration,
+    /// Fraction of a session's lifetime that must pass before it is renewed on activity
+    #[serde(
+        default = "default_session_renewal_threshold",
+        deserialize_with = "deserialize_renewal_threshold"
+    )]
+    pub session_renewal_threshold: f64,
+    /// How long after logging in a user without a password can confirm deleting their account with the session alone
+    #[serde(
+        default = "default_recent_login_ttl",
+        deserialize_with = "deserialize_ttl"
+    )]
+    pub recent_login_ttl: Duration,
+    #[serde(default)]
+    pub login_throttle: LoginThrottleSettings,
+    #[serde(default)]
+    pub two_factor: TwoFactorSettings,
+    /// Log in with an OpenID Connect identity provider
+    #[serde(default)]
+    pub oidc: OidcFeature,
 }
 
 impl<S: BuilderState> CustomizeConfigBuilder<S> for AuthSettings {
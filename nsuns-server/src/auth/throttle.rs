@@ -0,0 +1,483 @@
+//! Brute-force protection for password logins.
+//!
+//! Failed attempts are tracked in memory, per username and client address, per client address, and per username
+//! across all client addresses, so attacks on one account spread over many addresses are slowed as well. After a
+//! number of free attempts, each failure doubles the time before the next attempt is allowed, until the key is
+//! locked out. Each tracker holds a bounded number of entries, and forgets the oldest failures first when it is full.
+//!
+//! Each attempt is counted as a failure as soon as it starts, so concurrent attempts cannot get past the limit, and
+//! is only forgiven once the login fully succeeds, including any second factor.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use http::{HeaderMap, HeaderName};
+
+use super::settings::{AuthSettings, LoginThrottleSettings};
+
+/// Stale entries are only pruned once there are at least this many, to keep the common case cheap.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// The most entries a tracker holds, so an attack from many addresses or on many usernames cannot exhaust memory.
+const MAX_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Failures {
+    count: u32,
+    last_failure: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limits {
+    free_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    lockout_attempts: u32,
+    lockout_duration: Duration,
+    reset_after: Duration,
+}
+
+impl Limits {
+    fn is_expired(&self, failures: &Failures, now: Instant) -> bool {
+        now.saturating_duration_since(failures.last_failure)
+            > self.reset_after.max(self.lockout_duration)
+    }
+
+    /// How long after the last failure the next attempt is allowed
+    fn delay(&self, count: u32) -> Duration {
+        if count >= self.lockout_attempts {
+            self.lockout_duration
+        } else if count >= self.free_attempts {
+            let exponent = count - self.free_attempts;
+            self.base_delay
+                .checked_mul(2_u32.saturating_pow(exponent))
+                .unwrap_or(self.max_delay)
+                .min(self.max_delay)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn retry_after(&self, failures: &Failures, now: Instant) -> Option<Duration> {
+        if self.is_expired(failures, now) {
+            return None;
+        }
+
+        let delay = self.delay(failures.count);
+
+        // a wait that cannot be represented has not ended yet
+        let Some(allowed_at) = failures.last_failure.checked_add(delay) else {
+            return Some(delay);
+        };
+
+        allowed_at
+            .checked_duration_since(now)
+            .filter(|remaining| !remaining.is_zero())
+    }
+}
+
+#[derive(Debug)]
+struct Tracker<K> {
+    limits: Limits,
+    failures: HashMap<K, Failures>,
+    /// Prune again once there are this many entries, which grows with the entries that survive pruning, so the cost
+    /// of pruning is spread over the failures recorded in between
+    next_prune: usize,
+}
+
+impl<K: Eq + Hash> Tracker<K> {
+    fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            failures: HashMap::new(),
+            next_prune: PRUNE_THRESHOLD,
+        }
+    }
+
+    fn retry_after(&self, key: &K, now: Instant) -> Option<Duration> {
+        self.failures
+            .get(key)
+            .and_then(|failures| self.limits.retry_after(failures, now))
+    }
+
+    /// Forget expired failures, and the oldest tenth of them if the tracker is still full
+    fn prune(&mut self, now: Instant) {
+        let limits = self.limits;
+        self.failures
+            .retain(|_, failures| !limits.is_expired(failures, now));
+
+        if self.failures.len() >= MAX_ENTRIES {
+            let mut last_failures = self
+                .failures
+                .values()
+                .map(|failures| failures.last_failure)
+                .collect::<Vec<_>>();
+            let (_, cutoff, _) = last_failures.select_nth_unstable(MAX_ENTRIES / 10);
+            let cutoff = *cutoff;
+
+            self.failures
+                .retain(|_, failures| failures.last_failure > cutoff);
+        }
+
+        self.next_prune = (self.failures.len() * 2).clamp(PRUNE_THRESHOLD, MAX_ENTRIES);
+    }
+
+    fn record_failure(&mut self, key: K, now: Instant) {
+        if self.failures.len() >= self.next_prune && !self.failures.contains_key(&key) {
+            self.prune(now);
+        }
+
+        let limits = self.limits;
+        self.failures
+            .entry(key)
+            .and_modify(|failures| {
+                if limits.is_expired(failures, now) {
+                    failures.count = 0;
+                }
+                failures.count = failures.count.saturating_add(1);
+                failures.last_failure = now;
+            })
+            .or_insert(Failures {
+                count: 1,
+                last_failure: now,
+            });
+    }
+
+    /// Restart the wait before the next attempt, for a failure that took a while to find out about
+    fn touch(&mut self, key: &K, now: Instant) {
+        if let Some(failures) = self.failures.get_mut(key) {
+            failures.last_failure = failures.last_failure.max(now);
+        }
+    }
+
+    /// Take back one failure, without changing when the last failure happened
+    fn refund(&mut self, key: &K) {
+        if let Some(failures) = self.failures.get_mut(key) {
+            failures.count = failures.count.saturating_sub(1);
+        }
+    }
+
+    fn clear(&mut self, key: &K) {
+        self.failures.remove(key);
+    }
+}
+
+type AccountKey = (String, Option<IpAddr>);
+
+#[derive(Debug)]
+struct Trackers {
+    accounts: Tracker<AccountKey>,
+    addresses: Tracker<IpAddr>,
+    usernames: Tracker<String>,
+}
+
+/// Where to find the address of the client behind a reverse proxy
+#[derive(Debug)]
+struct Proxies {
+    trusted: Vec<IpAddr>,
+    header: HeaderName,
+}
+
+/// Tracks failed logins, and decides when a login attempt must wait
+#[derive(Debug, Clone)]
+pub struct LoginThrottle {
+    trackers: Arc<Mutex<Trackers>>,
+    proxies: Arc<Proxies>,
+}
+
+fn normalize_username(username: &str) -> String {
+    username.to_lowercase()
+}
+
+impl LoginThrottle {
+    #[must_use]
+    pub fn new(settings: &LoginThrottleSettings) -> Self {
+        let limits = |lockout_attempts| Limits {
+            free_attempts: settings.free_attempts,
+            base_delay: settings.base_delay,
+            max_delay: settings.max_delay,
+            lockout_attempts,
+            lockout_duration: settings.lockout_duration,
+            reset_after: settings.reset_after,
+        };
+
+        Self {
+            trackers: Arc::new(Mutex::new(Trackers {
+                accounts: Tracker::new(limits(settings.username_lockout_attempts)),
+                addresses: Tracker::new(limits(settings.address_lockout_attempts)),
+                usernames: Tracker::new(limits(settings.username_total_lockout_attempts)),
+            })),
+            proxies: Arc::new(Proxies {
+                trusted: settings.trusted_proxies.clone(),
+                header: settings.client_address_header.clone(),
+            }),
+        }
+    }
+
+    /// The address of the client, which is the remote address unless that is a trusted proxy.
+    ///
+    /// Behind trusted proxies, this is the last address in the client address header that was not added by a trusted
+    /// proxy, since anything before it may have been forged by the client.
+    #[must_use]
+    pub fn client_address(&self, remote: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+        let mut client = remote?;
+
+        let forwarded = headers
+            .get_all(&self.proxies.header)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect::<Vec<_>>();
+
+        for hop in forwarded.into_iter().rev() {
+            if !self.proxies.trusted.contains(&client) {
+                break;
+            }
+
+            match hop.trim().parse() {
+                Ok(address) => client = address,
+                Err(_) => break,
+            }
+        }
+
+        Some(client)
+    }
+
+    /// Start a login attempt, which counts as a failure until [`LoginThrottle::record_success`] is called.
+    ///
+    /// Returns how long the client must wait instead, if the attempt is not allowed yet.
+    ///
+    /// # Errors
+    /// Returns the wait if the username, client address, or both together have failed too often recently
+    ///
+    /// # Panics
+    /// Panics if the lock is poisoned
+    pub fn begin_attempt(&self, username: &str, address: Option<IpAddr>) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut trackers = self.trackers.lock().unwrap();
+
+        let username = normalize_username(username);
+        let account = (username.clone(), address);
+
+        let account_wait = trackers.accounts.retry_after(&account, now);
+        let address_wait =
+            address.and_then(|address| trackers.addresses.retry_after(&address, now));
+        let username_wait = trackers.usernames.retry_after(&username, now);
+
+        if let Some(wait) = account_wait.max(address_wait).max(username_wait) {
+            return Err(wait);
+        }
+
+        trackers.accounts.record_failure(account, now);
+        if let Some(address) = address {
+            trackers.addresses.record_failure(address, now);
+        }
+        trackers.usernames.record_failure(username, now);
+
+        Ok(())
+    }
+
+    /// Record that an attempt failed, so the wait before the next attempt starts now rather than when it began
+    ///
+    /// # Panics
+    /// Panics if the lock is poisoned
+    pub fn record_failure(&self, username: &str, address: Option<IpAddr>) {
+        let now = Instant::now();
+        let mut trackers = self.trackers.lock().unwrap();
+        let username = normalize_username(username);
+
+        trackers.usernames.touch(&username, now);
+        trackers.accounts.touch(&(username, address), now);
+        if let Some(address) = address {
+            trackers.addresses.touch(&address, now);
+        }
+    }
+
+    /// Take back an attempt that did not get as far as checking the credentials, such as one that hit a server error
+    ///
+    /// # Panics
+    /// Panics if the lock is poisoned
+    pub fn cancel_attempt(&self, username: &str, address: Option<IpAddr>) {
+        let mut trackers = self.trackers.lock().unwrap();
+        let username = normalize_username(username);
+
+        trackers.usernames.refund(&username);
+        trackers.accounts.refund(&(username, address));
+        if let Some(address) = address {
+            trackers.addresses.refund(&address);
+        }
+    }
+
+    /// Forget failed logins for a username from a client address after a login fully succeeded.
+    ///
+    /// Only the successful attempt is taken back from the client address, so one valid account cannot be used to
+    /// reset the failures for others, and from the username, so failures from other addresses still count.
+    ///
+    /// # Panics
+    /// Panics if the lock is poisoned
+    pub fn record_success(&self, username: &str, address: Option<IpAddr>) {
+        let mut trackers = self.trackers.lock().unwrap();
+        let username = normalize_username(username);
+
+        trackers.usernames.refund(&username);
+        trackers.accounts.clear(&(username, address));
+        if let Some(address) = address {
+            trackers.addresses.refund(&address);
+        }
+    }
+}
+
+impl<'a> From<&'a AuthSettings> for LoginThrottle {
+    fn from(value: &'a AuthSettings) -> Self {
+        Self::new(&value.login_throttle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::IpAddr,
+        time::{Duration, Instant},
+    };
+
+    use http::{HeaderMap, HeaderValue};
+
+    use crate::auth::settings::LoginThrottleSettings;
+
+    use super::{Limits, LoginThrottle, Tracker, MAX_ENTRIES};
+
+    fn tracker() -> Tracker<&'static str> {
+        Tracker::new(Limits {
+            free_attempts: 2,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(4),
+            lockout_attempts: 6,
+            lockout_duration: Duration::from_secs(60),
+            reset_after: Duration::from_secs(30),
+        })
+    }
+
+    #[test]
+    fn backoff_is_exponential() {
+        let mut tracker = tracker();
+        let now = Instant::now();
+
+        let mut waits = vec![];
+        for _ in 0..6 {
+            tracker.record_failure("lifter", now);
+            waits.push(tracker.retry_after(&"lifter", now).unwrap_or_default());
+        }
+
+        assert_eq!(
+            vec![0, 1, 2, 4, 4, 60],
+            waits.iter().map(Duration::as_secs).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn failures_are_forgotten() {
+        let mut tracker = tracker();
+        let now = Instant::now();
+
+        for _ in 0..6 {
+            tracker.record_failure("lifter", now);
+        }
+
+        let later = now + Duration::from_secs(61);
+        assert_eq!(None, tracker.retry_after(&"lifter", later));
+
+        tracker.record_failure("lifter", later);
+        assert_eq!(None, tracker.retry_after(&"lifter", later));
+    }
+
+    #[test]
+    fn trackers_are_capped() {
+        let mut tracker = Tracker::<usize>::new(tracker().limits);
+        let now = Instant::now();
+
+        for key in 0..=MAX_ENTRIES {
+            tracker.record_failure(key, now + Duration::from_nanos(key as u64));
+        }
+
+        assert!(tracker.failures.len() <= MAX_ENTRIES);
+        assert!(tracker.failures.contains_key(&MAX_ENTRIES));
+        assert!(!tracker.failures.contains_key(&0));
+    }
+
+    #[test]
+    fn long_lockouts_do_not_overflow() {
+        let mut tracker = Tracker::new(Limits {
+            lockout_attempts: 1,
+            lockout_duration: Duration::MAX,
+            ..tracker().limits
+        });
+        let now = Instant::now();
+
+        tracker.record_failure("lifter", now);
+        assert_eq!(Some(Duration::MAX), tracker.retry_after(&"lifter", now));
+    }
+
+    #[test]
+    fn attempts_count_until_success() {
+        let throttle = LoginThrottle::new(&LoginThrottleSettings {
+            free_attempts: 1,
+            ..Default::default()
+        });
+        let address = Some(IpAddr::from([192, 0, 2, 1]));
+
+        assert_eq!(Ok(()), throttle.begin_attempt("Lifter", address));
+        assert!(throttle.begin_attempt("lifter", address).is_err());
+
+        throttle.record_success("lifter", address);
+        assert_eq!(Ok(()), throttle.begin_attempt("lifter", address));
+    }
+
+    #[test]
+    fn usernames_are_tracked_across_addresses() {
+        let throttle = LoginThrottle::new(&LoginThrottleSettings {
+            free_attempts: 10,
+            username_total_lockout_attempts: 3,
+            ..Default::default()
+        });
+
+        for last_octet in 1..=3 {
+            let address = Some(IpAddr::from([192, 0, 2, last_octet]));
+            assert_eq!(Ok(()), throttle.begin_attempt("lifter", address));
+        }
+
+        let address = Some(IpAddr::from([192, 0, 2, 4]));
+        assert!(throttle.begin_attempt("lifter", address).is_err());
+        assert_eq!(Ok(()), throttle.begin_attempt("coach", address));
+    }
+
+    #[test]
+    fn client_address_from_trusted_proxies() {
+        let proxy = IpAddr::from([10, 0, 0, 1]);
+        let throttle = LoginThrottle::new(&LoginThrottleSettings {
+            trusted_proxies: vec![proxy],
+            ..Default::default()
+        });
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("203.0.113.7, 198.51.100.2, 10.0.0.1"),
+        );
+
+        assert_eq!(
+            Some(IpAddr::from([198, 51, 100, 2])),
+            throttle.client_address(Some(proxy), &headers)
+        );
+
+        let client = IpAddr::from([198, 51, 100, 9]);
+        assert_eq!(
+            Some(client),
+            throttle.client_address(Some(client), &headers)
+        );
+    }
+}
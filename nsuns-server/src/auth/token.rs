@@ -1,13 +1,8 @@
 use anyhow::{anyhow, Context};
-use chrono::{serde::ts_milliseconds, DateTime, Days, Utc};
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
 use const_format::formatcp;
 use http::StatusCode;
-use jsonwebtoken::{
-    decode, encode,
-    errors::{Error, ErrorKind},
-    DecodingKey, EncodingKey, Header, Validation,
-};
-use secrecy::ExposeSecret;
+use jsonwebtoken::errors::{Error, ErrorKind};
 use serde::{Deserialize, Serialize};
 use sqlx::{Executor, FromRow};
 use time::Duration;
@@ -15,12 +10,13 @@ use tower_cookies::{
     cookie::{CookieBuilder, SameSite},
     Cookie, Cookies,
 };
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
     db::{
         tracing::{
-            statements::{DELETE_FROM, INSERT_INTO, SELECT},
+            statements::{DELETE_FROM, INSERT_INTO, SELECT, UPDATE},
             InstrumentExecutor,
         },
         DB,
@@ -32,6 +28,8 @@ use crate::{
 
 use super::settings::AuthSettings;
 
+pub use super::keys::JwtKeys;
+
 const TABLE: &str = "sessions";
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -47,15 +45,13 @@ impl Claims {
     /// Create a new session
     ///
     /// Pass [`None`] for `user_id` for anonymous users.
-    /// Pass [`None`] for `exp` to generate a new expiry date.
     #[must_use]
     pub async fn insert_one(
         executor: impl Executor<'_, Database = DB>,
         owner_id: Uuid,
         user_id: Option<Uuid>,
-        exp: Option<DateTime<Utc>>,
+        exp: DateTime<Utc>,
     ) -> OperationResult<Claims> {
-        let exp = exp.unwrap_or_else(create_new_expiry_date);
         sqlx::query_as::<_, Claims>(formatcp!(
             "{INSERT_INTO} {TABLE} (user_id, owner_id, exp) VALUES ($1, $2, $3) RETURNING *"
         ))
@@ -81,6 +77,25 @@ impl Claims {
             .map_err(into_log_server_error!())
     }
 
+    /// Move the expiry date of a session
+    ///
+    /// This returns [`None`] if the session did not exist.
+    pub async fn renew(
+        &self,
+        exp: DateTime<Utc>,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<Claims>> {
+        sqlx::query_as::<_, Claims>(formatcp!(
+            "{UPDATE} {TABLE} SET exp = $1 WHERE id = $2 RETURNING *"
+        ))
+        .bind(exp)
+        .bind(self.id)
+        .fetch_optional(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+        .await
+        .with_context(|| format!("failed to renew session with id={}", self.id))
+        .map_err(into_log_server_error!())
+    }
+
     /// Delete a session
     ///
     /// This returns [`None`] if the session did not exist, or [`Some`] if it did.
@@ -105,6 +120,164 @@ impl Claims {
     }
 }
 
+/// Sessions are only marked as seen at most once per this many seconds, to avoid a write on every request.
+const LAST_SEEN_RESOLUTION_SECONDS: i64 = 60;
+
+/// A stored session, with metadata about its use
+#[derive(Debug, Clone, FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub owner_id: OwnerId,
+    pub user_id: Option<Uuid>,
+    pub exp: DateTime<Utc>,
+    pub created_on: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    /// Whether the session belongs to a disabled user. Only selected by [`Session::select_one`].
+    #[sqlx(default)]
+    pub user_disabled: bool,
+}
+
+impl Session {
+    pub async fn select_one(
+        id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<Session>> {
+        sqlx::query_as::<_, Session>(formatcp!(
+            "{SELECT} s.*, coalesce(u.disabled, false) AS user_disabled FROM {TABLE} s LEFT JOIN users u ON u.id = s.user_id WHERE s.id = $1"
+        ))
+            .bind(id)
+            .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+            .await
+            .context("failed to select session")
+            .map_err(into_log_server_error!())
+    }
+
+    /// Select all unexpired sessions for an owner, most recently used first
+    pub async fn select_active_for_owner(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Session>> {
+        sqlx::query_as::<_, Session>(formatcp!(
+            "{SELECT} * FROM {TABLE} WHERE owner_id = $1 AND exp > $2 ORDER BY last_seen DESC"
+        ))
+        .bind(owner_id)
+        .bind(Utc::now())
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select sessions")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Whether the session's usage metadata is out of date
+    #[must_use]
+    pub fn is_stale(&self, user_agent: Option<&str>) -> bool {
+        Utc::now()
+            .signed_duration_since(self.last_seen)
+            .num_seconds()
+            >= LAST_SEEN_RESOLUTION_SECONDS
+            || self.user_agent.as_deref() != user_agent
+    }
+
+    /// Record that the session was used just now, by the given user agent
+    pub async fn touch(
+        &self,
+        user_agent: Option<&str>,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<()> {
+        sqlx::query(formatcp!(
+            "{UPDATE} {TABLE} SET last_seen = $1, user_agent = $2 WHERE id = $3"
+        ))
+        .bind(Utc::now())
+        .bind(user_agent)
+        .bind(self.id)
+        .execute(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+        .await
+        .with_context(|| format!("failed to update session with id={}", self.id))
+        .map_err(into_log_server_error!())
+        .map(|_| ())
+    }
+}
+
+/// Information about an active session, for display to the user
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub id: Uuid,
+    /// Whether this is the session making the request
+    pub current: bool,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub created_on: DateTime<Utc>,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub last_seen: DateTime<Utc>,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub expiry_date: DateTime<Utc>,
+    pub user_agent: Option<String>,
+}
+
+impl SessionInfo {
+    #[must_use]
+    pub fn new(session: Session, current_session_id: Uuid) -> Self {
+        Self {
+            id: session.id,
+            current: session.id == current_session_id,
+            created_on: session.created_on,
+            last_seen: session.last_seen,
+            expiry_date: session.exp,
+            user_agent: session.user_agent,
+        }
+    }
+}
+
+/// Delete a session belonging to an owner
+///
+/// This returns [`None`] if the session did not exist, or [`Some`] if it did.
+pub async fn revoke_owner_session(
+    id: Uuid,
+    owner_id: OwnerId,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<Option<()>> {
+    sqlx::query(formatcp!(
+        "{DELETE_FROM} {TABLE} WHERE id = $1 AND owner_id = $2"
+    ))
+    .bind(id)
+    .bind(owner_id)
+    .execute(executor.instrument_executor(db_span!(DELETE_FROM, TABLE)))
+    .await
+    .with_context(|| format!("failed to revoke token with id={id}"))
+    .map_err(into_log_server_error!())
+    .map(|res| {
+        if res.rows_affected() > 0 {
+            Some(())
+        } else {
+            None
+        }
+    })
+}
+
+/// Delete all sessions for a user, optionally keeping one session alive.
+///
+/// Returns the number of revoked sessions.
+pub async fn revoke_user_sessions(
+    user_id: Uuid,
+    except: Option<Uuid>,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<u64> {
+    sqlx::query(formatcp!(
+        "{DELETE_FROM} {TABLE} WHERE user_id = $1 AND ($2::UUID IS NULL OR id <> $2)"
+    ))
+    .bind(user_id)
+    .bind(except)
+    .execute(executor.instrument_executor(db_span!(DELETE_FROM, TABLE)))
+    .await
+    .with_context(|| format!("failed to revoke sessions for user_id={user_id}"))
+    .map_err(into_log_server_error!())
+    .map(|res| res.rows_affected())
+}
+
 /// Decode claims from cookies, returning [`None`] if the cookie does not contain a token or contains an invalid token.
 pub fn decode_claims_from_cookies(keys: &JwtKeys, cookies: &Cookies) -> Option<Claims> {
     cookies
@@ -125,36 +298,6 @@ impl OwnerId {
 
 pub const COOKIE_NAME: &str = "JWT";
 
-#[derive(Clone)]
-pub struct JwtKeys {
-    pub encoding: EncodingKey,
-    pub decoding: DecodingKey,
-}
-
-impl JwtKeys {
-    pub fn decode(&self, token: &str) -> Result<Claims, Error> {
-        let token_data = decode::<Claims>(token, &self.decoding, &Validation::default())?;
-
-        Ok(token_data.claims)
-    }
-    pub fn encode(&self, claims: &Claims) -> Result<String, Error> {
-        encode(&Header::default(), claims, &self.encoding)
-    }
-}
-
-impl<'a> From<&'a AuthSettings> for JwtKeys {
-    fn from(value: &'a AuthSettings) -> Self {
-        Self {
-            encoding: EncodingKey::from_secret(
-                value.jwt_encoding_secret.expose_secret().as_bytes(),
-            ),
-            decoding: DecodingKey::from_secret(
-                value.jwt_decoding_secret.expose_secret().as_bytes(),
-            ),
-        }
-    }
-}
-
 impl From<Error> for ErrorWithStatus<anyhow::Error> {
     fn from(error: Error) -> Self {
         let status_code = match error.kind() {
@@ -166,7 +309,9 @@ impl From<Error> for ErrorWithStatus<anyhow::Error> {
             | ErrorKind::InvalidSubject
             | ErrorKind::ImmatureSignature
             | ErrorKind::InvalidAlgorithm
-            | ErrorKind::MissingAlgorithm => StatusCode::UNAUTHORIZED,
+            | ErrorKind::MissingAlgorithm
+            // signed by a key that was rotated out, or forged
+            | ErrorKind::InvalidSignature => StatusCode::UNAUTHORIZED,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -201,13 +346,116 @@ pub fn create_empty_cookie<'c>() -> Cookie<'c> {
         .build()
 }
 
-/// Create an expiry date for a new JWT
-///
-/// # Panics
-/// Panics if the new date would overflow an i64 in its seconds representation
-#[must_use]
-pub fn create_new_expiry_date() -> DateTime<Utc> {
-    Utc::now()
-        .checked_add_days(Days::new(2))
-        .expect("future timestamp does not overflow")
+/// How long sessions last, and when they are renewed
+#[derive(Debug, Clone, Copy)]
+pub struct SessionLifetimes {
+    pub user: chrono::Duration,
+    pub anonymous: chrono::Duration,
+    pub renewal_threshold: f64,
+    /// How long a login challenge for the second factor lasts
+    pub login_challenge: chrono::Duration,
+    /// How long a password reset token lasts
+    pub password_reset: chrono::Duration,
+    /// How long after logging in a session counts as a recent login
+    pub recent_login: chrono::Duration,
+}
+
+impl SessionLifetimes {
+    /// The lifetime of a session for a user, or an anonymous owner if `user_id` is [`None`]
+    #[must_use]
+    pub fn ttl(&self, user_id: Option<Uuid>) -> chrono::Duration {
+        if user_id.is_some() {
+            self.user
+        } else {
+            self.anonymous
+        }
+    }
+
+    /// Create an expiry date for a new or renewed session
+    ///
+    /// # Panics
+    /// Panics if the new date would overflow an i64 in its seconds representation, which the lifetime limits in
+    /// [`AuthSettings`] prevent
+    #[must_use]
+    pub fn create_expiry_date(&self, user_id: Option<Uuid>) -> DateTime<Utc> {
+        Utc::now()
+            .checked_add_signed(self.ttl(user_id))
+            .expect("future timestamp does not overflow")
+    }
+
+    /// The max age of the token cookie for a session
+    #[must_use]
+    pub fn cookie_max_age(&self, user_id: Option<Uuid>) -> Duration {
+        Duration::seconds(self.ttl(user_id).num_seconds())
+    }
+
+    /// Whether enough of the session's lifetime has passed that it should be renewed
+    #[must_use]
+    pub fn should_renew(&self, claims: &Claims) -> bool {
+        let ttl = self.ttl(claims.user_id);
+        let elapsed = ttl - claims.exp.signed_duration_since(Utc::now());
+
+        elapsed.num_seconds() as f64 >= ttl.num_seconds() as f64 * self.renewal_threshold
+    }
+}
+
+impl<'a> From<&'a AuthSettings> for SessionLifetimes {
+    /// # Panics
+    /// Panics if a configured lifetime is too large to represent, which cannot happen for lifetimes within the limits
+    /// checked when the settings load
+    fn from(value: &'a AuthSettings) -> Self {
+        Self {
+            user: chrono::Duration::from_std(value.user_session_ttl)
+                .expect("user session ttl is in range"),
+            anonymous: chrono::Duration::from_std(value.anonymous_session_ttl)
+                .expect("anonymous session ttl is in range"),
+            renewal_threshold: value.session_renewal_threshold,
+            login_challenge: chrono::Duration::from_std(value.two_factor.challenge_ttl)
+                .expect("login challenge ttl is in range"),
+            password_reset: chrono::Duration::from_std(value.password_reset_ttl)
+                .expect("password reset ttl is in range"),
+            recent_login: chrono::Duration::from_std(value.recent_login_ttl)
+                .expect("recent login ttl is in range"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use super::{Claims, OwnerId, SessionLifetimes};
+
+    fn lifetimes() -> SessionLifetimes {
+        SessionLifetimes {
+            user: Duration::days(30),
+            anonymous: Duration::days(2),
+            renewal_threshold: 0.5,
+            login_challenge: Duration::minutes(5),
+            password_reset: Duration::hours(1),
+            recent_login: Duration::minutes(10),
+        }
+    }
+
+    fn claims(user_id: Option<Uuid>, remaining: Duration) -> Claims {
+        Claims {
+            id: Uuid::nil(),
+            owner_id: OwnerId(Uuid::nil()),
+            user_id,
+            exp: Utc::now() + remaining,
+        }
+    }
+
+    #[test]
+    fn fresh_sessions_are_not_renewed() {
+        assert!(!lifetimes().should_renew(&claims(Some(Uuid::nil()), Duration::days(29))));
+        assert!(!lifetimes().should_renew(&claims(None, Duration::days(2))));
+    }
+
+    #[test]
+    fn old_sessions_are_renewed() {
+        assert!(lifetimes().should_renew(&claims(Some(Uuid::nil()), Duration::days(14))));
+        assert!(lifetimes().should_renew(&claims(None, Duration::hours(23))));
+    }
 }
@@ -0,0 +1,413 @@
+use anyhow::{anyhow, Context};
+use axum::{extract::State, Json};
+use chrono::Utc;
+use http::StatusCode;
+use sqlx::Transaction;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    acquire,
+    auth::{
+        extract::{ClientAddress, SessionClaims},
+        handler::{create_user_session, require_user, revoke_and_logout},
+        settings::TwoFactorSettings,
+        throttle::LoginThrottle,
+        token::{decode_claims_from_cookies, revoke_user_sessions, JwtKeys, SessionLifetimes},
+        user::select_user_by_id,
+    },
+    db::{transaction::commit_ok, Pool, DB},
+    error::{extract::WithErrorRejection, ErrorWithStatus, OperationResult},
+    into_log_server_error, transaction,
+    validation::ValidatedJson,
+};
+
+use super::{
+    model::{
+        count_recovery_codes, replace_recovery_codes, CompleteLogin, LoginChallenge,
+        PendingChallenge, RecoveryCodes, SecondFactor, TotpCredential, TotpEnrollment,
+        TwoFactorStatus, MAX_SECOND_FACTOR_ATTEMPTS,
+    },
+    totp::{self, encode_base32, generate_totp_secret, provisioning_uri},
+    verify_second_factor,
+};
+
+fn invalid_code() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(StatusCode::FORBIDDEN, anyhow!("Invalid code"))
+}
+
+fn invalid_challenge() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(
+        StatusCode::UNAUTHORIZED,
+        anyhow!("Invalid or expired login challenge"),
+    )
+}
+
+fn not_enabled() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(
+        StatusCode::CONFLICT,
+        anyhow!("Two-factor authentication is not enabled"),
+    )
+}
+
+async fn select_enabled_credential(
+    tx: &mut Transaction<'_, DB>,
+    user_id: Uuid,
+) -> OperationResult<Option<TotpCredential>> {
+    Ok(TotpCredential::select_for_user(user_id, &mut **tx)
+        .await
+        .context("failed to fetch two-factor credential")
+        .map_err(into_log_server_error!())?
+        .filter(|credential| credential.enabled))
+}
+
+/// Start a login challenge if the user has two-factor authentication enabled.
+///
+/// Returns [`None`] if the password alone is enough to log in.
+pub(crate) async fn create_login_challenge(
+    tx: &mut Transaction<'_, DB>,
+    lifetimes: &SessionLifetimes,
+    user_id: Uuid,
+) -> OperationResult<Option<LoginChallenge>> {
+    if select_enabled_credential(tx, user_id).await?.is_none() {
+        return Ok(None);
+    }
+
+    let expiry_date = Utc::now() + lifetimes.login_challenge;
+
+    let challenge = PendingChallenge::insert_one(user_id, expiry_date, &mut **tx)
+        .await
+        .context("failed to create login challenge")
+        .map_err(into_log_server_error!())?;
+
+    Ok(Some(LoginChallenge {
+        challenge,
+        expiry_date,
+    }))
+}
+
+/// Returns the completed challenge, or [`None`] if the code was wrong, in which case the failed attempt must still be
+/// committed.
+async fn complete_login_challenge(
+    tx: &mut Transaction<'_, DB>,
+    keys: &JwtKeys,
+    lifetimes: &SessionLifetimes,
+    complete: CompleteLogin,
+    cookies: Cookies,
+) -> OperationResult<Option<PendingChallenge>> {
+    let challenge = PendingChallenge::select_by_token(&complete.challenge, &mut **tx)
+        .await
+        .context("failed to fetch login challenge")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(invalid_challenge)?;
+
+    let credential = select_enabled_credential(tx, challenge.user_id)
+        .await?
+        .ok_or_else(invalid_challenge)?;
+
+    if !verify_second_factor(tx, challenge.user_id, &credential, &complete.code)
+        .await
+        .context("failed to verify second factor")
+        .map_err(into_log_server_error!())?
+    {
+        challenge
+            .record_failure(&mut **tx)
+            .await
+            .context("failed to update login challenge")
+            .map_err(into_log_server_error!())?;
+
+        return Ok(None);
+    }
+
+    challenge
+        .delete(&mut **tx)
+        .await
+        .context("failed to delete login challenge")
+        .map_err(into_log_server_error!())?;
+
+    if let Some(claims) = decode_claims_from_cookies(keys, &cookies) {
+        revoke_and_logout(claims, tx).await?;
+    }
+
+    create_user_session(
+        &mut **tx,
+        keys,
+        lifetimes,
+        challenge.owner_id,
+        challenge.user_id,
+        &cookies,
+    )
+    .await?;
+
+    Ok(Some(challenge))
+}
+
+/// Finish logging in with a code from an authenticator app, or a recovery code
+#[tracing::instrument(skip_all)]
+pub async fn complete_login(
+    State(pool): State<Pool>,
+    State(keys): State<JwtKeys>,
+    State(lifetimes): State<SessionLifetimes>,
+    State(throttle): State<LoginThrottle>,
+    ClientAddress(address): ClientAddress,
+    WithErrorRejection(cookies): WithErrorRejection<Cookies>,
+    ValidatedJson(complete): ValidatedJson<CompleteLogin>,
+) -> OperationResult<StatusCode> {
+    let mut tx = transaction!(&pool).await?;
+
+    let res = complete_login_challenge(&mut tx, &keys, &lifetimes, complete, cookies).await;
+
+    if let Some(challenge) = commit_ok(res, tx).await? {
+        throttle.record_success(&challenge.username, address);
+
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        metrics::increment_counter!("auth.login.failures", "reason" => "invalid_second_factor");
+        Err(invalid_code())
+    }
+}
+
+/// Whether the logged-in user has two-factor authentication enabled
+#[tracing::instrument(skip_all)]
+pub async fn status(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+) -> OperationResult<Json<TwoFactorStatus>> {
+    let user_id = require_user(&claims)?;
+
+    let mut tx = transaction!(&pool).await?;
+
+    let enabled = select_enabled_credential(&mut tx, user_id).await?.is_some();
+
+    let recovery_codes_remaining = count_recovery_codes(user_id, &mut *tx)
+        .await
+        .context("failed to count recovery codes")
+        .map_err(into_log_server_error!())?;
+
+    Ok(Json(TwoFactorStatus {
+        enabled,
+        recovery_codes_remaining,
+    }))
+}
+
+/// Generate a new TOTP secret for the logged-in user. It is not used until enrollment is confirmed.
+#[tracing::instrument(skip_all)]
+pub async fn enroll(
+    State(pool): State<Pool>,
+    State(settings): State<TwoFactorSettings>,
+    SessionClaims(claims): SessionClaims,
+) -> OperationResult<Json<TotpEnrollment>> {
+    let user_id = require_user(&claims)?;
+
+    let mut conn = acquire!(&pool).await?;
+
+    let user = select_user_by_id(user_id, &mut *conn)
+        .await
+        .context("failed to fetch user")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(|| ErrorWithStatus::new(StatusCode::UNAUTHORIZED, anyhow!("Unauthorized")))?;
+
+    let secret = generate_totp_secret();
+
+    if !TotpCredential::insert_pending(user_id, &secret, &mut *conn)
+        .await
+        .context("failed to store two-factor secret")
+        .map_err(into_log_server_error!())?
+    {
+        return Err(ErrorWithStatus::new(
+            StatusCode::CONFLICT,
+            anyhow!("Two-factor authentication is already enabled"),
+        ));
+    }
+
+    Ok(Json(TotpEnrollment {
+        secret: encode_base32(&secret),
+        provisioning_uri: provisioning_uri(&settings.issuer, &user.username, &secret),
+    }))
+}
+
+async fn confirm_enrollment(
+    tx: &mut Transaction<'_, DB>,
+    user_id: Uuid,
+    code: &str,
+) -> OperationResult<RecoveryCodes> {
+    let credential = TotpCredential::select_for_user(user_id, &mut **tx)
+        .await
+        .context("failed to fetch two-factor credential")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(|| {
+            ErrorWithStatus::new(
+                StatusCode::CONFLICT,
+                anyhow!("Two-factor enrollment has not been started"),
+            )
+        })?;
+
+    if credential.enabled {
+        return Err(ErrorWithStatus::new(
+            StatusCode::CONFLICT,
+            anyhow!("Two-factor authentication is already enabled"),
+        ));
+    }
+
+    // recovery codes are not accepted here, since there are none yet
+    let step = totp::verify(&credential.secret, code, Utc::now()).ok_or_else(invalid_code)?;
+
+    TotpCredential::use_step(user_id, step, &mut **tx)
+        .await
+        .context("failed to mark code as used")
+        .map_err(into_log_server_error!())?;
+
+    TotpCredential::enable(user_id, &mut **tx)
+        .await
+        .context("failed to enable two-factor authentication")
+        .map_err(into_log_server_error!())?;
+
+    let recovery_codes = replace_recovery_codes(user_id, tx)
+        .await
+        .context("failed to create recovery codes")
+        .map_err(into_log_server_error!())?;
+
+    Ok(RecoveryCodes { recovery_codes })
+}
+
+/// Enable two-factor authentication, by proving the authenticator app was set up with the new secret
+#[tracing::instrument(skip_all)]
+pub async fn confirm(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    ValidatedJson(second_factor): ValidatedJson<SecondFactor>,
+) -> OperationResult<Json<RecoveryCodes>> {
+    let user_id = require_user(&claims)?;
+
+    let mut tx = transaction!(&pool).await?;
+
+    let res = confirm_enrollment(&mut tx, user_id, &second_factor.code).await;
+
+    commit_ok(res, tx).await.map(Json)
+}
+
+fn too_many_attempts() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(
+        StatusCode::UNAUTHORIZED,
+        anyhow!("Too many invalid codes, log in again"),
+    )
+}
+
+/// Verify the second factor of a user that has it enabled.
+///
+/// A wrong code is returned as the inner error, after the failed attempt is recorded, so the attempt must still be
+/// committed. Too many wrong codes log out all sessions of the user, so further guesses need the password again.
+async fn require_second_factor(
+    tx: &mut Transaction<'_, DB>,
+    user_id: Uuid,
+    code: &str,
+) -> OperationResult<OperationResult<()>> {
+    let credential = TotpCredential::select_for_update(user_id, &mut **tx)
+        .await
+        .context("failed to fetch two-factor credential")
+        .map_err(into_log_server_error!())?
+        .filter(|credential| credential.enabled)
+        .ok_or_else(not_enabled)?;
+
+    if verify_second_factor(tx, user_id, &credential, code)
+        .await
+        .context("failed to verify second factor")
+        .map_err(into_log_server_error!())?
+    {
+        if credential.failed_attempts > 0 {
+            TotpCredential::reset_failures(user_id, &mut **tx)
+                .await
+                .context("failed to reset second factor attempts")
+                .map_err(into_log_server_error!())?;
+        }
+
+        return Ok(Ok(()));
+    }
+
+    let attempts = TotpCredential::record_failure(user_id, &mut **tx)
+        .await
+        .context("failed to record second factor attempt")
+        .map_err(into_log_server_error!())?;
+
+    if attempts < MAX_SECOND_FACTOR_ATTEMPTS {
+        return Ok(Err(invalid_code()));
+    }
+
+    // a new login gets a fresh set of attempts
+    TotpCredential::reset_failures(user_id, &mut **tx)
+        .await
+        .context("failed to reset second factor attempts")
+        .map_err(into_log_server_error!())?;
+
+    revoke_user_sessions(user_id, None, &mut **tx).await?;
+
+    Ok(Err(too_many_attempts()))
+}
+
+async fn disable_two_factor(
+    tx: &mut Transaction<'_, DB>,
+    user_id: Uuid,
+    code: &str,
+) -> OperationResult<OperationResult<()>> {
+    if let Err(e) = require_second_factor(tx, user_id, code).await? {
+        return Ok(Err(e));
+    }
+
+    TotpCredential::delete(user_id, tx)
+        .await
+        .context("failed to disable two-factor authentication")
+        .map_err(into_log_server_error!())?;
+
+    Ok(Ok(()))
+}
+
+/// Turn off two-factor authentication for the logged-in user
+#[tracing::instrument(skip_all)]
+pub async fn disable(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    ValidatedJson(second_factor): ValidatedJson<SecondFactor>,
+) -> OperationResult<StatusCode> {
+    let user_id = require_user(&claims)?;
+
+    let mut tx = transaction!(&pool).await?;
+
+    let res = disable_two_factor(&mut tx, user_id, &second_factor.code).await;
+
+    commit_ok(res, tx).await??;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn regenerate_codes(
+    tx: &mut Transaction<'_, DB>,
+    user_id: Uuid,
+    code: &str,
+) -> OperationResult<OperationResult<RecoveryCodes>> {
+    if let Err(e) = require_second_factor(tx, user_id, code).await? {
+        return Ok(Err(e));
+    }
+
+    let recovery_codes = replace_recovery_codes(user_id, tx)
+        .await
+        .context("failed to create recovery codes")
+        .map_err(into_log_server_error!())?;
+
+    Ok(Ok(RecoveryCodes { recovery_codes }))
+}
+
+/// Replace the recovery codes of the logged-in user. Any unused codes stop working.
+#[tracing::instrument(skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(pool): State<Pool>,
+    SessionClaims(claims): SessionClaims,
+    ValidatedJson(second_factor): ValidatedJson<SecondFactor>,
+) -> OperationResult<Json<RecoveryCodes>> {
+    let user_id = require_user(&claims)?;
+
+    let mut tx = transaction!(&pool).await?;
+
+    let res = regenerate_codes(&mut tx, user_id, &second_factor.code).await;
+
+    commit_ok(res, tx).await?.map(Json)
+}
@@ -0,0 +1,34 @@
+//! Optional TOTP second factor for registered users.
+//!
+//! When enabled, a correct password only yields a short-lived login challenge, which is exchanged for a session
+//! with a code from an authenticator app, or one of the user's single-use recovery codes.
+
+pub mod handler;
+pub mod model;
+pub mod totp;
+
+use chrono::Utc;
+use sqlx::Transaction;
+use uuid::Uuid;
+
+use crate::db::DB;
+
+use self::model::{redeem_recovery_code, TotpCredential};
+
+/// Check a TOTP code, or a recovery code, for a user.
+///
+/// Accepted codes are consumed, so they cannot be used again.
+pub async fn verify_second_factor(
+    tx: &mut Transaction<'_, DB>,
+    user_id: Uuid,
+    credential: &TotpCredential,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    if let Some(step) = totp::verify(&credential.secret, code, Utc::now()) {
+        TotpCredential::use_step(user_id, step, &mut **tx).await
+    } else if totp::is_totp_code(code) {
+        Ok(false)
+    } else {
+        redeem_recovery_code(user_id, code, &mut **tx).await
+    }
+}
@@ -0,0 +1,403 @@
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use const_format::formatcp;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, FromRow, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::secret::{generate_secret, hash_secret},
+    db::{
+        tracing::{
+            statements::{DELETE_FROM, INSERT_INTO, SELECT, UPDATE},
+            InstrumentExecutor,
+        },
+        DB,
+    },
+    db_span,
+};
+
+use super::totp::encode_base32;
+
+const TOTP_TABLE: &str = "user_totp";
+
+const RECOVERY_TABLE: &str = "recovery_codes";
+
+const CHALLENGE_TABLE: &str = "login_challenges";
+
+const RECOVERY_CODE_COUNT: usize = 10;
+
+const RECOVERY_CODE_BYTES: usize = 10;
+
+/// Wrong codes allowed for a login challenge before it is discarded, and the password must be entered again
+const MAX_CHALLENGE_ATTEMPTS: i16 = 5;
+
+/// Wrong codes allowed from a logged-in user before all of their sessions are logged out, and the password must be
+/// entered again
+pub const MAX_SECOND_FACTOR_ATTEMPTS: i16 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorStatus {
+    pub enabled: bool,
+    pub recovery_codes_remaining: i64,
+}
+
+/// A new TOTP secret, to add to an authenticator app
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollment {
+    /// The base32-encoded secret, for entering manually
+    pub secret: String,
+    /// An `otpauth://` URI, for showing as a QR code
+    pub provisioning_uri: String,
+}
+
+/// Single-use recovery codes. This is the only time the codes are returned.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// A code from an authenticator app, or a recovery code
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondFactor {
+    #[validate(length(min = 1, max = 64))]
+    pub code: String,
+}
+
+/// Returned from a password login when the second factor is required
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginChallenge {
+    pub challenge: String,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub expiry_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteLogin {
+    pub challenge: String,
+    #[validate(length(min = 1, max = 64))]
+    pub code: String,
+}
+
+/// The TOTP secret of a user
+#[derive(Debug, Clone, FromRow)]
+pub struct TotpCredential {
+    pub secret: Vec<u8>,
+    pub enabled: bool,
+    pub failed_attempts: i16,
+}
+
+impl TotpCredential {
+    pub async fn select_for_user(
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<Option<TotpCredential>, sqlx::Error> {
+        sqlx::query_as::<_, TotpCredential>(formatcp!(
+            "{SELECT} secret, enabled, failed_attempts FROM {TOTP_TABLE} WHERE user_id = $1"
+        ))
+        .bind(user_id)
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, TOTP_TABLE)))
+        .await
+    }
+
+    /// Look up the secret of a user, and lock it until the end of the transaction.
+    ///
+    /// The lock makes concurrent attempts by the same user wait for each other, so they cannot all read the same
+    /// attempt count and get past the limit.
+    pub async fn select_for_update(
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<Option<TotpCredential>, sqlx::Error> {
+        sqlx::query_as::<_, TotpCredential>(formatcp!(
+            "{SELECT} secret, enabled, failed_attempts FROM {TOTP_TABLE} WHERE user_id = $1 FOR UPDATE"
+        ))
+        .bind(user_id)
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, TOTP_TABLE)))
+        .await
+    }
+
+    /// Count a wrong code entered by a logged-in user.
+    ///
+    /// Returns the number of wrong codes since the last accepted one.
+    pub async fn record_failure(
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<i16, sqlx::Error> {
+        sqlx::query_as::<_, (i16,)>(formatcp!(
+            "{UPDATE} {TOTP_TABLE} SET failed_attempts = failed_attempts + 1 WHERE user_id = $1 RETURNING failed_attempts"
+        ))
+        .bind(user_id)
+        .fetch_one(executor.instrument_executor(db_span!(UPDATE, TOTP_TABLE)))
+        .await
+        .map(|(attempts,)| attempts)
+    }
+
+    pub async fn reset_failures(
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(formatcp!(
+            "{UPDATE} {TOTP_TABLE} SET failed_attempts = 0 WHERE user_id = $1"
+        ))
+        .bind(user_id)
+        .execute(executor.instrument_executor(db_span!(UPDATE, TOTP_TABLE)))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store a new secret that is not enabled until it is confirmed.
+    ///
+    /// Returns `false` without changing anything if two-factor authentication is already enabled.
+    pub async fn insert_pending(
+        user_id: Uuid,
+        secret: &[u8],
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query(formatcp!(
+            "{INSERT_INTO} {TOTP_TABLE} (user_id, secret) VALUES ($1, $2) ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL, created_on = now() WHERE {TOTP_TABLE}.enabled = false"
+        ))
+        .bind(user_id)
+        .bind(secret)
+        .execute(executor.instrument_executor(db_span!(INSERT_INTO, TOTP_TABLE)))
+        .await
+        .map(|res| res.rows_affected() > 0)
+    }
+
+    pub async fn enable(
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(formatcp!(
+            "{UPDATE} {TOTP_TABLE} SET enabled = true WHERE user_id = $1"
+        ))
+        .bind(user_id)
+        .execute(executor.instrument_executor(db_span!(UPDATE, TOTP_TABLE)))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove the second factor and all recovery codes of a user
+    pub async fn delete(user_id: Uuid, tx: &mut Transaction<'_, DB>) -> Result<(), sqlx::Error> {
+        sqlx::query(formatcp!("{DELETE_FROM} {TOTP_TABLE} WHERE user_id = $1"))
+            .bind(user_id)
+            .execute((&mut **tx).instrument_executor(db_span!(DELETE_FROM, TOTP_TABLE)))
+            .await?;
+
+        sqlx::query(formatcp!(
+            "{DELETE_FROM} {RECOVERY_TABLE} WHERE user_id = $1"
+        ))
+        .bind(user_id)
+        .execute((&mut **tx).instrument_executor(db_span!(DELETE_FROM, RECOVERY_TABLE)))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Mark the code for a time step as used.
+    ///
+    /// Returns `false` if a code for this or a later time step was already used, so codes cannot be replayed.
+    pub async fn use_step(
+        user_id: Uuid,
+        step: i64,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query(formatcp!(
+            "{UPDATE} {TOTP_TABLE} SET last_used_step = $2 WHERE user_id = $1 AND (last_used_step IS NULL OR last_used_step < $2)"
+        ))
+        .bind(user_id)
+        .bind(step)
+        .execute(executor.instrument_executor(db_span!(UPDATE, TOTP_TABLE)))
+        .await
+        .map(|res| res.rows_affected() > 0)
+    }
+}
+
+fn generate_recovery_code() -> String {
+    let mut bytes = [0_u8; RECOVERY_CODE_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+
+    let encoded = encode_base32(&bytes).to_lowercase();
+
+    encoded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Recovery codes are accepted regardless of case and separators
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Replace all recovery codes of a user with new ones.
+///
+/// Returns the plaintext codes, which are not stored and cannot be recovered later.
+pub async fn replace_recovery_codes(
+    user_id: Uuid,
+    tx: &mut Transaction<'_, DB>,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query(formatcp!(
+        "{DELETE_FROM} {RECOVERY_TABLE} WHERE user_id = $1"
+    ))
+    .bind(user_id)
+    .execute((&mut **tx).instrument_executor(db_span!(DELETE_FROM, RECOVERY_TABLE)))
+    .await?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| hash_secret(&normalize_recovery_code(code)))
+        .collect();
+
+    sqlx::query(formatcp!(
+        "{INSERT_INTO} {RECOVERY_TABLE} (user_id, code_hash) SELECT $1, * FROM UNNEST($2::VARCHAR[])"
+    ))
+    .bind(user_id)
+    .bind(hashes)
+    .execute((&mut **tx).instrument_executor(db_span!(INSERT_INTO, RECOVERY_TABLE)))
+    .await?;
+
+    Ok(codes)
+}
+
+/// Consume a recovery code. Returns `false` if the code does not exist.
+pub async fn redeem_recovery_code(
+    user_id: Uuid,
+    code: &str,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query(formatcp!(
+        "{DELETE_FROM} {RECOVERY_TABLE} WHERE user_id = $1 AND code_hash = $2"
+    ))
+    .bind(user_id)
+    .bind(hash_secret(&normalize_recovery_code(code)))
+    .execute(executor.instrument_executor(db_span!(DELETE_FROM, RECOVERY_TABLE)))
+    .await
+    .map(|res| res.rows_affected() > 0)
+}
+
+pub async fn count_recovery_codes(
+    user_id: Uuid,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_as::<_, (i64,)>(formatcp!(
+        "{SELECT} COUNT(*) FROM {RECOVERY_TABLE} WHERE user_id = $1"
+    ))
+    .bind(user_id)
+    .fetch_one(executor.instrument_executor(db_span!(SELECT, RECOVERY_TABLE)))
+    .await
+    .map(|(count,)| count)
+}
+
+/// A login that is waiting for the second factor
+#[derive(Debug, Clone, FromRow)]
+pub struct PendingChallenge {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub owner_id: Uuid,
+    pub username: String,
+}
+
+impl PendingChallenge {
+    /// Create a new challenge for a user that entered the correct password.
+    ///
+    /// Returns the plaintext challenge token, which is not stored and cannot be recovered later.
+    pub async fn insert_one(
+        user_id: Uuid,
+        exp: DateTime<Utc>,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<String, sqlx::Error> {
+        let token = generate_secret();
+
+        sqlx::query(formatcp!(
+            "{INSERT_INTO} {CHALLENGE_TABLE} (user_id, token_hash, exp) VALUES ($1, $2, $3)"
+        ))
+        .bind(user_id)
+        .bind(hash_secret(&token))
+        .bind(exp)
+        .execute(executor.instrument_executor(db_span!(INSERT_INTO, CHALLENGE_TABLE)))
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Look up an unexpired challenge by its token, and lock it until the end of the transaction.
+    ///
+    /// The lock makes concurrent attempts at the same challenge wait for each other, so they cannot all read the same
+    /// attempt count and get past the limit.
+    pub async fn select_by_token(
+        token: &str,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<Option<PendingChallenge>, sqlx::Error> {
+        sqlx::query_as::<_, PendingChallenge>(formatcp!(
+            "{SELECT} c.id, c.user_id, u.owner_id, u.username FROM {CHALLENGE_TABLE} c JOIN users u ON u.id = c.user_id WHERE c.token_hash = $1 AND c.exp > $2 AND c.attempts < $3 FOR UPDATE OF c"
+        ))
+        .bind(hash_secret(token))
+        .bind(Utc::now())
+        .bind(MAX_CHALLENGE_ATTEMPTS)
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, CHALLENGE_TABLE)))
+        .await
+    }
+
+    /// Count a wrong code against this challenge
+    pub async fn record_failure(
+        &self,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(formatcp!(
+            "{UPDATE} {CHALLENGE_TABLE} SET attempts = attempts + 1 WHERE id = $1"
+        ))
+        .bind(self.id)
+        .execute(executor.instrument_executor(db_span!(UPDATE, CHALLENGE_TABLE)))
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(
+        &self,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(formatcp!("{DELETE_FROM} {CHALLENGE_TABLE} WHERE id = $1"))
+            .bind(self.id)
+            .execute(executor.instrument_executor(db_span!(DELETE_FROM, CHALLENGE_TABLE)))
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovery_codes_are_normalized() {
+        let code = generate_recovery_code();
+
+        assert_eq!(19, code.len());
+        assert_eq!(
+            normalize_recovery_code(&code),
+            normalize_recovery_code(&code.to_uppercase().replace('-', " "))
+        );
+    }
+}
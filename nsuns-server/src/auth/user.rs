@@ -1,7 +1,7 @@
 use axum_extra::headers::authorization::Basic;
 use chrono::{serde::ts_milliseconds, DateTime, Utc};
 use const_format::formatcp;
-use password_auth::{verify_password, ParseError, VerifyError};
+use password_auth::{generate_hash, verify_password, ParseError, VerifyError};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use sqlx::{Executor, FromRow};
@@ -9,11 +9,12 @@ use thiserror::Error;
 use tracing::Instrument;
 use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::{Validate, ValidationError};
 
 use crate::{
     db::{
         tracing::{
-            statements::{DELETE_FROM, INSERT_INTO, SELECT},
+            statements::{DELETE_FROM, INSERT_INTO, SELECT, UPDATE},
             InstrumentExecutor,
         },
         DB,
@@ -25,12 +26,25 @@ use super::token::OwnerId;
 
 const TABLE: &str = "users";
 
+/// What a user is allowed to do, beyond managing their own data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, sqlx::Type)]
+#[serde(rename_all = "camelCase")]
+#[repr(i16)]
+pub enum Role {
+    User = 0,
+    /// Can manage other users
+    Admin = 1,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct User {
     pub id: Uuid,
     pub owner_id: Uuid,
     pub username: String,
-    password_hash: SecretString,
+    /// Users provisioned by an identity provider do not have a password
+    password_hash: Option<SecretString>,
+    pub role: Role,
+    pub disabled: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, FromRow)]
@@ -38,16 +52,18 @@ struct UserRow {
     id: Uuid,
     owner_id: Uuid,
     username: String,
-    password_hash: String,
+    password_hash: Option<String>,
+    role: Role,
+    disabled: bool,
 }
 
-#[derive(Clone, Serialize, Deserialize, FromRow, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct UserInfo {
     pub id: Uuid,
     pub username: String,
 }
 
-#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AnonymousInfo {
     #[schema(value_type = i64)]
@@ -55,7 +71,7 @@ pub struct AnonymousInfo {
     pub expiry_date: DateTime<Utc>,
 }
 
-#[derive(Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type")]
 pub enum AgentInfo {
     #[serde(rename = "user")]
@@ -70,7 +86,9 @@ impl From<UserRow> for User {
             id: value.id,
             owner_id: value.owner_id,
             username: value.username,
-            password_hash: value.password_hash.into(),
+            password_hash: value.password_hash.map(Into::into),
+            role: value.role,
+            disabled: value.disabled,
         }
     }
 }
@@ -82,6 +100,156 @@ pub enum Error {
 
     #[error(transparent)]
     ParseError(#[from] ParseError),
+
+    #[error("username is already taken")]
+    UsernameTaken,
+
+    #[error("this session was already claimed")]
+    OwnerClaimed,
+}
+
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new(
+            "username may only contain letters, numbers, '_', '-' and '.'",
+        ))
+    }
+}
+
+fn validate_password(password: &str) -> Result<(), ValidationError> {
+    if password.trim().is_empty() {
+        Err(ValidationError::new("password must not be blank"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check a new password against the username of the account it is for
+pub fn validate_password_for(username: &str, password: &str) -> Result<(), ValidationError> {
+    if password.eq_ignore_ascii_case(username) {
+        Err(ValidationError::new("password must not match the username"))
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_credentials(user: &CreateUser) -> Result<(), ValidationError> {
+    validate_password_for(&user.username, &user.password)
+}
+
+/// Credentials for a new user account.
+///
+/// Deliberately does not implement `Debug`, to keep the plaintext password out of logs.
+#[derive(Clone, Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "validate_credentials", skip_on_field_errors = false))]
+pub struct CreateUser {
+    #[validate(length(min = 3, max = 32), custom = "validate_username")]
+    pub username: String,
+    #[validate(length(min = 8, max = 256), custom = "validate_password")]
+    pub password: String,
+}
+
+impl CreateUser {
+    /// Hash the password and create a user for an existing owner
+    pub async fn insert_one(
+        self,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> Result<UserInfo, Error> {
+        let password_hash = hash_password(self.password).await;
+
+        sqlx::query_as::<_, UserInfo>(formatcp!(
+            "{INSERT_INTO} {TABLE} (owner_id, username, password_hash) VALUES ($1, $2, $3) RETURNING id, username"
+        ))
+        .bind(owner_id)
+        .bind(self.username)
+        .bind(password_hash)
+        .fetch_one(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.constraint() == Some("unique_owner") => Error::OwnerClaimed,
+            sqlx::Error::Database(e) if e.is_unique_violation() => Error::UsernameTaken,
+            e => e.into(),
+        })
+    }
+}
+
+/// A new password for the logged-in user.
+///
+/// Deliberately does not implement `Debug` or `Serialize`, to keep the plaintext passwords out of logs.
+#[derive(Clone, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePassword {
+    pub current_password: String,
+    #[validate(length(min = 8, max = 256), custom = "validate_password")]
+    pub new_password: String,
+}
+
+/// Confirmation to delete the logged-in user, along with all of their data.
+///
+/// Deliberately does not implement `Debug` or `Serialize`, to keep the plaintext password out of logs.
+#[derive(Clone, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteAccount {
+    /// Required if the user has a password. Users without one must have logged in recently instead.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+/// A new password set with a reset token.
+///
+/// Deliberately does not implement `Debug` or `Serialize`, to keep the plaintext password out of logs.
+#[derive(Clone, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetPassword {
+    pub token: String,
+    #[validate(length(min = 8, max = 256), custom = "validate_password")]
+    pub new_password: String,
+}
+
+/// Hash a password on the blocking thread pool
+///
+/// # Panics
+/// Panics if the task spawned to compute the password hash is cancelled (indicates a bug)
+pub async fn hash_password(password: String) -> String {
+    tokio::task::spawn_blocking(move || generate_hash(password))
+        .instrument(tracing::info_span!("hash_password"))
+        .await
+        .expect("password hashing is not cancellable (this is a bug)")
+}
+
+impl User {
+    /// Whether the user can log in with a password, rather than only with an identity provider
+    #[must_use]
+    pub fn has_password(&self) -> bool {
+        self.password_hash.is_some()
+    }
+
+    /// Check a password against the stored hash, returning the user if it matches.
+    ///
+    /// # Panics
+    /// Panics if the task spawned to compute the password hash is cancelled (indicates a bug)
+    pub async fn verify(self, password: String) -> Result<Option<User>, Error> {
+        let Some(password_hash) = self.password_hash.clone() else {
+            return Ok(None);
+        };
+
+        tokio::task::spawn_blocking(move || {
+            match verify_password(password, password_hash.expose_secret()) {
+                Ok(()) => Ok(Some(self)),
+                Err(VerifyError::Parse(e)) => Err(e.into()),
+                Err(VerifyError::PasswordInvalid) => Ok(None),
+            }
+        })
+        .instrument(tracing::info_span!("verify_pasword"))
+        .await
+        .expect("password hashing is not cancellable (this is a bug)")
+    }
 }
 
 /// Authenticate a user and return the user model
@@ -100,21 +268,105 @@ pub async fn authenticate(
             .map(Into::into);
 
     if let Some(user) = user {
-        tokio::task::spawn_blocking(move || {
-            match verify_password(auth.password(), user.password_hash.expose_secret()) {
-                Ok(()) => Ok(Some(user)),
-                Err(VerifyError::Parse(e)) => Err(e.into()),
-                Err(VerifyError::PasswordInvalid) => Ok(None),
-            }
-        })
-        .instrument(tracing::info_span!("verify_pasword"))
-        .await
-        .expect("password hashing is not cancellable (this is a bug)")
+        user.verify(auth.password().to_owned()).await
     } else {
         Ok(None)
     }
 }
 
+pub async fn select_user_by_id(
+    id: Uuid,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<Option<User>, Error> {
+    sqlx::query_as::<_, UserRow>(formatcp!("{SELECT} * FROM {TABLE} WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .map(|row| row.map(Into::into))
+        .map_err(Into::into)
+}
+
+pub async fn select_user_by_username(
+    username: &str,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<Option<User>, Error> {
+    sqlx::query_as::<_, UserRow>(formatcp!("{SELECT} * FROM {TABLE} WHERE username = $1"))
+        .bind(username)
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .map(|row| row.map(Into::into))
+        .map_err(Into::into)
+}
+
+/// Change the role of a user.
+///
+/// Returns `false` if the user does not exist.
+pub async fn update_role(
+    username: &str,
+    role: Role,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<bool, Error> {
+    sqlx::query(formatcp!(
+        "{UPDATE} {TABLE} SET role = $1 WHERE username = $2"
+    ))
+    .bind(role)
+    .bind(username)
+    .execute(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+    .await
+    .map(|res| res.rows_affected() > 0)
+    .map_err(Into::into)
+}
+
+/// Disable or enable a user.
+///
+/// Returns `false` if the user does not exist.
+pub async fn update_disabled(
+    id: Uuid,
+    disabled: bool,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<bool, Error> {
+    sqlx::query(formatcp!(
+        "{UPDATE} {TABLE} SET disabled = $1 WHERE id = $2"
+    ))
+    .bind(disabled)
+    .bind(id)
+    .execute(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+    .await
+    .map(|res| res.rows_affected() > 0)
+    .map_err(Into::into)
+}
+
+pub async fn select_user_id_by_username(
+    username: &str,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<Option<Uuid>, Error> {
+    sqlx::query_as::<_, (Uuid,)>(formatcp!("{SELECT} id FROM {TABLE} WHERE username = $1"))
+        .bind(username)
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .map(|row| row.map(|(id,)| id))
+        .map_err(Into::into)
+}
+
+/// Hash and store a new password for a user
+pub async fn update_password(
+    id: Uuid,
+    password: String,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<(), Error> {
+    let password_hash = hash_password(password).await;
+
+    sqlx::query(formatcp!(
+        "{UPDATE} {TABLE} SET password_hash = $1 WHERE id = $2"
+    ))
+    .bind(password_hash)
+    .bind(id)
+    .execute(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+    .await?;
+
+    Ok(())
+}
+
 pub async fn select_user_info_by_owner_id(
     owner_id: OwnerId,
     executor: impl Executor<'_, Database = DB>,
@@ -157,6 +409,68 @@ pub async fn create_anonymous_user(
     Ok(id)
 }
 
+/// Create a persistent owner, which does not expire
+pub async fn create_owner(executor: impl Executor<'_, Database = DB>) -> Result<OwnerId, Error> {
+    let id = sqlx::query_as::<_, (OwnerId,)>(formatcp!(
+        "{INSERT_INTO} owners DEFAULT VALUES RETURNING id"
+    ))
+    .fetch_one(executor.instrument_executor(db_span!(INSERT_INTO, "owners")))
+    .await?
+    .0;
+
+    Ok(id)
+}
+
+/// Remove the expiry date from an owner, so it is no longer cleaned up
+pub async fn persist_owner(
+    executor: impl Executor<'_, Database = DB>,
+    id: OwnerId,
+) -> Result<(), Error> {
+    sqlx::query(formatcp!(
+        "{UPDATE} owners SET expiry_date = NULL WHERE id = $1"
+    ))
+    .bind(id)
+    .execute(executor.instrument_executor(db_span!(UPDATE, "owners")))
+    .await?;
+
+    Ok(())
+}
+
+/// Create a user without a password, for an identity provided externally.
+///
+/// Returns [`None`] if the username is taken.
+pub async fn insert_passwordless_user(
+    owner_id: OwnerId,
+    username: &str,
+    executor: impl Executor<'_, Database = DB>,
+) -> Result<Option<UserInfo>, Error> {
+    sqlx::query_as::<_, UserInfo>(formatcp!(
+        "{INSERT_INTO} {TABLE} (owner_id, username) VALUES ($1, $2) ON CONFLICT (username) DO NOTHING RETURNING id, username"
+    ))
+    .bind(owner_id)
+    .bind(username)
+    .fetch_optional(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+    .await
+    .map_err(Into::into)
+}
+
+/// Push back the expiry date of an anonymous owner. Persistent owners are left untouched.
+pub async fn extend_owner_expiry(
+    executor: impl Executor<'_, Database = DB>,
+    id: OwnerId,
+    expiry_date: DateTime<Utc>,
+) -> Result<(), Error> {
+    sqlx::query(formatcp!(
+        "{UPDATE} owners SET expiry_date = $1 WHERE id = $2 AND expiry_date IS NOT NULL"
+    ))
+    .bind(expiry_date)
+    .bind(id)
+    .execute(executor.instrument_executor(db_span!(UPDATE, "owners")))
+    .await?;
+
+    Ok(())
+}
+
 pub async fn delete_owner(
     executor: impl Executor<'_, Database = DB>,
     id: OwnerId,
@@ -1,4 +1,10 @@
-use axum::{extract::Request, middleware::Next, response::IntoResponse, Json};
+use axum::{
+    extract::Request,
+    http::header::{CONTENT_LENGTH, CONTENT_TYPE},
+    middleware::Next,
+    response::IntoResponse,
+    Json,
+};
 use serde::Serialize;
 
 use super::StoredErrorMessage;
@@ -20,8 +26,14 @@ pub async fn json_errors(req: Request, next: Next) -> impl IntoResponse {
     let status = response.status();
 
     if let Some(StoredErrorMessage(message)) = response.extensions_mut().remove() {
+        // keep headers such as `Retry-After`, but not those describing the old body
+        let mut headers = std::mem::take(response.headers_mut());
+        headers.remove(CONTENT_LENGTH);
+        headers.remove(CONTENT_TYPE);
+
         (
             status,
+            headers,
             Json(ErrorResponse {
                 path,
                 message,
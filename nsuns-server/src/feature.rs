@@ -4,7 +4,7 @@ use serde_json::{Map, Number, Value};
 
 use crate::settings::CustomizeConfigBuilder;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Feature<T> {
     Enabled(T),
     Disabled,
@@ -0,0 +1,110 @@
+use anyhow::{anyhow, Context};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use sqlx::Transaction;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{
+    acquire,
+    auth::{token::OwnerId, user::select_user_by_username},
+    db::{commit_ok, Pool, DB},
+    error::{extract::WithErrorRejection, ErrorWithStatus, OperationResult},
+    into_log_server_error,
+    profiles::model::Profile,
+    response_transforms::no_content_or_404,
+    transaction,
+    validation::ValidatedJson,
+};
+
+use super::model::{CreateGrant, Grant, SharedProfile};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantsQuery {
+    pub profile_id: Uuid,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn grants_index(
+    State(pool): State<Pool>,
+    WithErrorRejection(Query(query)): WithErrorRejection<Query<GrantsQuery>>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut conn = acquire!(&pool).await?;
+
+    Grant::select_for_profile(query.profile_id, owner_id, &mut *conn)
+        .await
+        .map(Json)
+}
+
+async fn upsert_grant(
+    grant: CreateGrant,
+    owner_id: OwnerId,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<Grant> {
+    if Profile::select_one(grant.profile_id, owner_id, &mut **tx)
+        .await?
+        .is_none()
+    {
+        return Err(ErrorWithStatus::new(
+            StatusCode::NOT_FOUND,
+            anyhow!("Profile not found"),
+        ));
+    }
+
+    let user = select_user_by_username(&grant.username, &mut **tx)
+        .await
+        .context("failed to fetch user")
+        .map_err(into_log_server_error!())?
+        .ok_or_else(|| ErrorWithStatus::new(StatusCode::NOT_FOUND, anyhow!("User not found")))?;
+
+    if user.owner_id == owner_id.as_uuid() {
+        return Err(ErrorWithStatus::new(
+            StatusCode::CONFLICT,
+            anyhow!("Profiles cannot be shared with their owner"),
+        ));
+    }
+
+    grant.upsert(user.id, &mut **tx).await
+}
+
+/// Share a profile with a user, or change what an existing grant allows
+#[tracing::instrument(skip_all)]
+pub async fn share_profile(
+    State(pool): State<Pool>,
+    owner_id: OwnerId,
+    ValidatedJson(grant): ValidatedJson<CreateGrant>,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = upsert_grant(grant, owner_id, &mut tx).await.map(Json);
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn delete_grant(
+    State(pool): State<Pool>,
+    Path(id): Path<Uuid>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut conn = acquire!(&pool).await?;
+
+    Grant::delete_one(id, owner_id, &mut *conn)
+        .await
+        .map(no_content_or_404)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn shared_profiles(State(pool): State<Pool>, owner_id: OwnerId) -> impl IntoResponse {
+    let mut conn = acquire!(&pool).await?;
+
+    SharedProfile::select_for_owner(owner_id, &mut *conn)
+        .await
+        .map(Json)
+}
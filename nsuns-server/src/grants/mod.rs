@@ -0,0 +1,11 @@
+//! Sharing a profile with another user, such as a coach.
+//!
+//! A grant gives one user read-only or read-write access to one profile, along with its programs, maxes and reps.
+//! Everything created through a grant still belongs to the owner of the profile.
+
+pub mod handler;
+pub mod model;
+pub mod openapi;
+pub mod router;
+
+pub use router::router;
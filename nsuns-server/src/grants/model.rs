@@ -0,0 +1,168 @@
+use anyhow::Context;
+use chrono::{serde::ts_milliseconds, DateTime, Utc};
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, FromRow};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::token::OwnerId,
+    db::{
+        tracing::{
+            statements::{DELETE_FROM, INSERT_INTO, SELECT},
+            InstrumentExecutor,
+        },
+        DB,
+    },
+    db_span,
+    error::OperationResult,
+    into_log_server_error,
+};
+
+const TABLE: &str = "profile_grants";
+
+/// What a user is allowed to do with a profile that was shared with them
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema, sqlx::Type,
+)]
+#[serde(rename_all = "camelCase")]
+#[repr(i16)]
+pub enum Access {
+    /// View the profile's programs, maxes and reps
+    Read = 0,
+    /// Also edit programs, and log maxes and reps
+    Write = 1,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Grant {
+    pub id: Uuid,
+    pub profile_id: Uuid,
+    /// The user the profile is shared with
+    pub username: String,
+    pub access: Access,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub created_on: DateTime<Utc>,
+}
+
+impl Grant {
+    /// Select the grants of a profile, if it belongs to `owner_id`
+    pub async fn select_for_profile(
+        profile_id: Uuid,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} g.id, g.profile_id, u.username, g.access, g.created_on
+            FROM {TABLE} g
+            JOIN users u ON u.id = g.user_id
+            JOIN profiles p ON p.id = g.profile_id
+            WHERE g.profile_id = $1 AND p.owner_id = $2
+            ORDER BY u.username"
+        ))
+        .bind(profile_id)
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .with_context(|| format!("failed to select grants for profile_id={profile_id}"))
+        .map_err(into_log_server_error!())
+    }
+
+    /// Delete a grant, either as the owner of the profile, or as the user it was granted to.
+    ///
+    /// Returns [`None`] if the grant does not exist, or is not visible to `owner_id`.
+    pub async fn delete_one(
+        id: Uuid,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<()>> {
+        sqlx::query(formatcp!(
+            "{DELETE_FROM} {TABLE} g WHERE g.id = $1 AND (
+                EXISTS(SELECT 1 FROM profiles p WHERE p.id = g.profile_id AND p.owner_id = $2)
+                OR EXISTS(SELECT 1 FROM users u WHERE u.id = g.user_id AND u.owner_id = $2)
+            )"
+        ))
+        .bind(id)
+        .bind(owner_id)
+        .execute(executor.instrument_executor(db_span!(DELETE_FROM, TABLE)))
+        .await
+        .with_context(|| format!("failed to delete grant with id={id}"))
+        .map_err(into_log_server_error!())
+        .map(|res| (res.rows_affected() > 0).then_some(()))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateGrant {
+    pub profile_id: Uuid,
+    /// The user to share the profile with
+    #[validate(length(min = 1))]
+    pub username: String,
+    pub access: Access,
+}
+
+impl CreateGrant {
+    /// Share a profile with a user, or change the access of an existing grant.
+    ///
+    /// The caller must check that the profile belongs to the owner, and that the user is not the owner.
+    pub async fn upsert(
+        self,
+        user_id: Uuid,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Grant> {
+        sqlx::query_as::<_, (Uuid, DateTime<Utc>)>(formatcp!(
+            "{INSERT_INTO} {TABLE} (profile_id, user_id, access) VALUES ($1, $2, $3)
+            ON CONFLICT (profile_id, user_id) DO UPDATE SET access = EXCLUDED.access
+            RETURNING id, created_on"
+        ))
+        .bind(self.profile_id)
+        .bind(user_id)
+        .bind(self.access)
+        .fetch_one(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+        .await
+        .context("failed to share profile")
+        .map_err(into_log_server_error!())
+        .map(|(id, created_on)| Grant {
+            id,
+            profile_id: self.profile_id,
+            username: self.username,
+            access: self.access,
+            created_on,
+        })
+    }
+}
+
+/// A profile that was shared with the current user
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub access: Access,
+}
+
+impl SharedProfile {
+    pub async fn select_for_owner(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} p.id, p.name, g.access
+            FROM {TABLE} g
+            JOIN users u ON u.id = g.user_id
+            JOIN profiles p ON p.id = g.profile_id
+            WHERE u.owner_id = $1
+            ORDER BY p.name"
+        ))
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select shared profiles")
+        .map_err(into_log_server_error!())
+    }
+}
@@ -0,0 +1,111 @@
+use const_format::{concatcp, formatcp};
+use utoipa::{
+    openapi::{
+        path::{OperationBuilder, PathItemBuilder},
+        request_body::RequestBodyBuilder,
+        ComponentsBuilder, PathItemType, PathsBuilder, ResponseBuilder,
+    },
+    IntoParams, PartialSchema, ToSchema,
+};
+
+use crate::{
+    openapi::{
+        extensions::{id_path_param, no_content, ok, param_in_default, JsonContent},
+        Customizer,
+    },
+    router::GRANTS_PATH,
+};
+
+use super::{
+    handler::GrantsQuery,
+    model::{Access, CreateGrant, Grant, SharedProfile},
+};
+
+pub struct GrantsModule;
+
+const TAG: &str = "Grants";
+
+impl Customizer<ComponentsBuilder> for GrantsModule {
+    fn customize(builder: ComponentsBuilder) -> ComponentsBuilder {
+        builder
+            .schema_from::<Access>()
+            .schema_from::<Grant>()
+            .schema_from::<CreateGrant>()
+            .schema_from::<SharedProfile>()
+    }
+}
+
+impl Customizer<PathsBuilder> for GrantsModule {
+    fn customize(builder: PathsBuilder) -> PathsBuilder {
+        let get_op = OperationBuilder::new()
+            .description(Some("List the users a profile is shared with"))
+            .parameters(Some(GrantsQuery::into_params(param_in_default)))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<Grant>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let put_op = OperationBuilder::new()
+            .description(Some(
+                "Share a profile with a user, or change what an existing grant allows",
+            ))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(CreateGrant::schema().1)
+                    .build(),
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Grant::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let shared_op = OperationBuilder::new()
+            .description(Some("List the profiles shared with the current user"))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<SharedProfile>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let delete_op = OperationBuilder::new()
+            .description(Some(
+                "Stop sharing a profile. Either the owner of the profile or the user it is shared with can do this.",
+            ))
+            .parameters(id_path_param(Some("The id of the grant")))
+            .response(no_content(), ResponseBuilder::new().build())
+            .tag(TAG)
+            .build();
+
+        builder
+            .path(
+                GRANTS_PATH,
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, get_op)
+                    .operation(PathItemType::Put, put_op)
+                    .build(),
+            )
+            .path(
+                formatcp!("{GRANTS_PATH}/shared"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, shared_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(GRANTS_PATH, "/{id}"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Delete, delete_op)
+                    .build(),
+            )
+    }
+}
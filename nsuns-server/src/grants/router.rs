@@ -0,0 +1,19 @@
+use axum::{
+    extract::FromRef,
+    routing::{delete, get},
+    Router,
+};
+
+use crate::{db::Pool, router::State};
+
+use super::handler::{delete_grant, grants_index, share_profile, shared_profiles};
+
+pub fn router<S: State>() -> Router<S>
+where
+    Pool: FromRef<S>,
+{
+    Router::new()
+        .route("/", get(grants_index).put(share_profile))
+        .route("/shared", get(shared_profiles))
+        .route("/:id", delete(delete_grant))
+}
@@ -0,0 +1,167 @@
+//! Consistency checks over program sets, for problems that the schema cannot rule out by itself.
+//!
+//! The order of sets is stored on the set rows, so there are no id lists that could point at missing sets. What can
+//! still go wrong is sets in a week their program no longer has, sets of a training day from another program, gaps in
+//! the order of a day, and percentage of max references to movements of another owner. Each check can also repair
+//! what it finds.
+
+pub mod settings;
+pub mod task;
+
+use anyhow::Context;
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    db::{
+        tracing::{
+            statements::{DELETE_FROM, SELECT, UPDATE},
+            InstrumentExecutor,
+        },
+        DB,
+    },
+    db_span,
+    error::OperationResult,
+    into_log_server_error,
+};
+
+const TABLE: &str = "program_sets";
+
+/// The sets found by each check, which were also repaired if `repaired` is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// Sets in a week that their program does not have. Repaired by deleting them.
+    pub sets_without_week: Vec<Uuid>,
+    /// Sets of a training day that belongs to another program. Repaired by deleting them.
+    pub sets_in_foreign_training_day: Vec<Uuid>,
+    /// Sets whose position leaves a gap in the order of their day. Repaired by closing the gaps.
+    pub misordered_sets: Vec<Uuid>,
+    /// Sets whose percentage of max is a movement of another owner. Repaired by removing the percentage of max.
+    pub foreign_percentage_of_max: Vec<Uuid>,
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    /// How many sets had a problem
+    #[must_use]
+    pub fn issues(&self) -> usize {
+        self.sets_without_week.len()
+            + self.sets_in_foreign_training_day.len()
+            + self.misordered_sets.len()
+            + self.foreign_percentage_of_max.len()
+    }
+}
+
+/// A query that finds the ids of sets with a problem, and one that repairs them, returning the same ids
+struct Check {
+    description: &'static str,
+    find: &'static str,
+    repair: &'static str,
+}
+
+const SETS_WITHOUT_WEEK: Check = Check {
+    description: "sets without a week",
+    find: formatcp!(
+        "{SELECT} s.id FROM {TABLE} s
+        WHERE NOT EXISTS ({SELECT} 1 FROM program_weeks w WHERE w.program_id = s.program_id AND w.week = s.week)"
+    ),
+    repair: formatcp!(
+        "{DELETE_FROM} {TABLE} s
+        WHERE NOT EXISTS ({SELECT} 1 FROM program_weeks w WHERE w.program_id = s.program_id AND w.week = s.week)
+        RETURNING s.id"
+    ),
+};
+
+const SETS_IN_FOREIGN_TRAINING_DAY: Check = Check {
+    description: "sets in a training day of another program",
+    find: formatcp!(
+        "{SELECT} s.id FROM {TABLE} s
+        JOIN training_days d ON d.id = s.training_day_id
+        WHERE d.program_id <> s.program_id"
+    ),
+    repair: formatcp!(
+        "{DELETE_FROM} {TABLE} s USING training_days d
+        WHERE d.id = s.training_day_id AND d.program_id <> s.program_id
+        RETURNING s.id"
+    ),
+};
+
+/// The position each set should have, counting up from 0 within its day
+const EXPECTED_ORDERING: &str = formatcp!(
+    "({SELECT} id, ROW_NUMBER() OVER (
+        PARTITION BY program_id, week, day, training_day_id ORDER BY ordering
+    ) - 1 AS ordering FROM {TABLE})"
+);
+
+const MISORDERED_SETS: Check = Check {
+    description: "sets out of order",
+    find: formatcp!(
+        "{SELECT} s.id FROM {TABLE} s
+        JOIN {EXPECTED_ORDERING} o ON o.id = s.id
+        WHERE s.ordering <> o.ordering"
+    ),
+    repair: formatcp!(
+        "{UPDATE} {TABLE} s SET ordering = o.ordering
+        FROM {EXPECTED_ORDERING} o
+        WHERE o.id = s.id AND s.ordering <> o.ordering
+        RETURNING s.id"
+    ),
+};
+
+const FOREIGN_PERCENTAGE_OF_MAX: Check = Check {
+    description: "sets with a percentage of max from another owner",
+    find: formatcp!(
+        "{SELECT} s.id FROM {TABLE} s
+        JOIN movements m ON m.id = s.percentage_of_max
+        WHERE m.owner_id IS DISTINCT FROM s.owner_id"
+    ),
+    repair: formatcp!(
+        "{UPDATE} {TABLE} s SET percentage_of_max = NULL
+        FROM movements m
+        WHERE m.id = s.percentage_of_max AND m.owner_id IS DISTINCT FROM s.owner_id
+        RETURNING s.id"
+    ),
+};
+
+async fn run_check(
+    check: &Check,
+    repair: bool,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<Vec<Uuid>> {
+    let (sql, span) = if repair {
+        (check.repair, db_span!("repair program sets"))
+    } else {
+        (check.find, db_span!(SELECT, TABLE))
+    };
+
+    let mut ids: Vec<Uuid> = sqlx::query_as::<_, (Uuid,)>(sql)
+        .fetch_all(executor.instrument_executor(span))
+        .await
+        .with_context(|| format!("failed to check for {}", check.description))
+        .map_err(into_log_server_error!())?
+        .into_iter()
+        .map(|(id,)| id)
+        .collect();
+
+    ids.sort_unstable();
+
+    Ok(ids)
+}
+
+/// Check the sets of every program, repairing the problems found if `repair` is set.
+///
+/// Sets that are deleted by a repair are not checked for later problems.
+pub async fn check(repair: bool, tx: &mut Transaction<'_, DB>) -> OperationResult<IntegrityReport> {
+    Ok(IntegrityReport {
+        sets_without_week: run_check(&SETS_WITHOUT_WEEK, repair, &mut **tx).await?,
+        sets_in_foreign_training_day: run_check(&SETS_IN_FOREIGN_TRAINING_DAY, repair, &mut **tx)
+            .await?,
+        misordered_sets: run_check(&MISORDERED_SETS, repair, &mut **tx).await?,
+        foreign_percentage_of_max: run_check(&FOREIGN_PERCENTAGE_OF_MAX, repair, &mut **tx).await?,
+        repaired: repair,
+    })
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use serde::{de, Deserialize, Deserializer};
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_interval() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+/// Only accept intervals that are not zero
+fn deserialize_interval<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let interval = crate::serde_duration::deserialize(deserializer)?;
+
+    if interval.is_zero() {
+        Err(de::Error::custom("integrity.interval must not be zero"))
+    } else {
+        Ok(interval)
+    }
+}
+
+/// When the server checks program sets for problems on its own
+#[derive(Debug, Clone, Deserialize)]
+pub struct IntegritySettings {
+    /// Check when the server starts, and then on every interval
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(
+        default = "default_interval",
+        deserialize_with = "deserialize_interval"
+    )]
+    pub interval: Duration,
+    /// Repair the problems that are found, instead of only logging them
+    #[serde(default)]
+    pub auto_repair: bool,
+}
+
+impl Default for IntegritySettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            interval: default_interval(),
+            auto_repair: bool::default(),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::Context;
+use secrecy::ExposeSecret;
+use sqlx::Connection;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::{db_span, settings::Settings, shutdown::shutdown_signal};
+
+use super::check;
+
+/// Check program sets when the server starts, and then on every configured interval
+pub async fn run(settings: &Settings) -> anyhow::Result<()> {
+    if !settings.integrity.enabled {
+        return Ok(());
+    }
+
+    tracing::info!("starting background integrity check task");
+
+    // the first tick completes immediately, so the first check runs on startup
+    let mut interval = tokio::time::interval(settings.integrity.interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let cancellation_token = CancellationToken::new();
+    let child_cancellation_token = cancellation_token.clone();
+
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        child_cancellation_token.cancel();
+    });
+
+    let url = format!(
+        "postgres://{}:{}@{}:{}/{}",
+        settings.database.username,
+        settings.database.password.expose_secret(),
+        settings.database.host,
+        settings.database.port,
+        settings.database.database
+    );
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {},
+            _ = cancellation_token.cancelled() => {
+                break;
+            }
+        };
+
+        run_check(&url, settings.integrity.auto_repair).await;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn run_check(url: &str, repair: bool) {
+    tracing::info!("starting scheduled integrity check");
+
+    let mut conn = match sqlx::PgConnection::connect(url)
+        .instrument(db_span!("acquire connection"))
+        .await
+        .context("failed to establish a connection")
+    {
+        Ok(conn) => conn,
+        Err(e) => {
+            tracing::error!("{e:?}");
+            return;
+        }
+    };
+
+    let mut tx = match conn.begin().await.context("failed to start a transaction") {
+        Ok(tx) => tx,
+        Err(e) => {
+            tracing::error!("{e:?}");
+            return;
+        }
+    };
+
+    // errors are logged by the check itself
+    let Ok(report) = check(repair, &mut tx).await else {
+        return;
+    };
+
+    if let Err(e) = tx
+        .commit()
+        .await
+        .context("failed to commit integrity check")
+    {
+        tracing::error!("{e:?}");
+        return;
+    }
+
+    match (report.issues(), repair) {
+        (0, _) => tracing::info!("Found no problems with program sets"),
+        (issues, true) => tracing::warn!(?report, "Repaired {issues} program sets"),
+        (issues, false) => tracing::warn!(
+            ?report,
+            "Found {issues} program sets with problems, which can be repaired through the admin API"
+        ),
+    }
+}
@@ -1,14 +1,18 @@
+pub mod admin;
 pub mod auth;
 pub mod db;
 pub mod error;
 pub mod feature;
+pub mod grants;
 mod health;
+pub mod integrity;
 pub mod maxes;
 pub mod movements;
 pub mod observability;
 pub mod openapi;
 pub mod profiles;
 pub mod program;
+pub mod quotas;
 pub mod reps;
 pub mod response_transforms;
 pub mod router;
@@ -17,6 +21,8 @@ pub mod server;
 pub mod sets;
 pub mod settings;
 pub mod shutdown;
+pub mod templates;
+pub mod training_days;
 pub mod updates;
 pub mod validation;
 mod vec;
@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
 use nsuns_server::{
-    auth, log_error, observability::metrics::server as metrics_server,
-    observability::tracing::setup::setup_tracing, server, settings::Settings,
+    admin, auth, auth::user::Role, integrity, log_error,
+    observability::metrics::server as metrics_server, observability::tracing::setup::setup_tracing,
+    server, settings::Settings,
 };
 
 #[cfg(not(target_env = "msvc"))]
@@ -19,10 +20,43 @@ async fn main() -> Result<()> {
 
     let _guard = setup_tracing(&settings)?;
 
+    let mut args = std::env::args().skip(1);
+
+    if let Some(command) = args.next() {
+        return match command.as_str() {
+            "mint-reset-token" => {
+                let username = args
+                    .next()
+                    .context("usage: nsuns-server mint-reset-token <username>")?;
+                auth::reset::mint_from_cli(&settings, &username)
+                    .await
+                    .map_err(log_error!())
+            }
+            "grant-admin" | "revoke-admin" => {
+                let username = args
+                    .next()
+                    .with_context(|| format!("usage: nsuns-server {command} <username>"))?;
+                let role = if command == "grant-admin" {
+                    Role::Admin
+                } else {
+                    Role::User
+                };
+                admin::cli::update_role_from_cli(&settings, &username, role)
+                    .await
+                    .map_err(log_error!())
+            }
+            _ => Err(anyhow::anyhow!("unknown command: {command}")).map_err(log_error!()),
+        };
+    }
+
+    // migrations run while initializing, so they finish before the integrity check reads the schema
+    let app = server::initialize(&settings).await?;
+
     tokio::try_join!(
-        server::run(&settings),
+        server::run(&settings, app),
         metrics_server::run(&settings.metrics),
-        auth::cleanup::run(&settings)
+        auth::cleanup::run(&settings),
+        integrity::task::run(&settings)
     )
     .map_err(log_error!())?;
 
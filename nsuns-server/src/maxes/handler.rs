@@ -12,6 +12,7 @@ use crate::{
     auth::token::OwnerId,
     db::{transaction::commit_ok, Pool},
     error::extract::WithErrorRejection,
+    quotas::settings::QuotaSettings,
     response_transforms::{created, or_404},
     transaction,
     validation::ValidatedJson,
@@ -42,12 +43,13 @@ pub async fn maxes_index(
 #[tracing::instrument(skip_all)]
 pub async fn create_max(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     ValidatedJson(max): ValidatedJson<CreateMax>,
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
     let res = max
-        .insert_one(owner_id, &mut tx)
+        .insert_one(owner_id, &quotas, &mut tx)
         .await
         .map(Json)
         .map(created);
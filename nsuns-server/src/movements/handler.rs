@@ -1,33 +1,65 @@
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
 
 use crate::{
     acquire,
     auth::token::OwnerId,
-    db::Pool,
+    db::{commit_ok, Pool},
+    error::extract::WithErrorRejection,
+    quotas::settings::QuotaSettings,
     response_transforms::{created, or_404},
+    transaction,
     validation::ValidatedJson,
 };
 
 use super::model::{CreateMovement, Movement};
 
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct MovementsQuery {
+    /// List the movements of the owner of this profile instead, which can be a profile shared with the current user
+    pub profile_id: Option<Uuid>,
+}
+
 #[tracing::instrument(skip_all)]
-pub async fn movements_index(State(pool): State<Pool>, owner_id: OwnerId) -> impl IntoResponse {
+pub async fn movements_index(
+    State(pool): State<Pool>,
+    WithErrorRejection(Query(query)): WithErrorRejection<Query<MovementsQuery>>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
     let mut conn = acquire!(&pool).await?;
-    Movement::select_all(owner_id, &mut *conn).await.map(Json)
+
+    match query.profile_id {
+        Some(profile_id) => {
+            Movement::select_all_for_profile(profile_id, owner_id, &mut *conn).await
+        }
+        None => Movement::select_all(owner_id, &mut *conn).await,
+    }
+    .map(Json)
 }
 
 #[tracing::instrument(skip_all)]
 pub async fn create_movement(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     ValidatedJson(movement): ValidatedJson<CreateMovement>,
 ) -> impl IntoResponse {
-    let mut conn = acquire!(&pool).await?;
-    movement
-        .insert_one(owner_id, &mut *conn)
+    let mut tx = transaction!(&pool).await?;
+    let res = movement
+        .insert_one(owner_id, &quotas, &mut tx)
         .await
         .map(Json)
-        .map(created)
+        .map(created);
+
+    commit_ok(res, tx).await
 }
 
 #[tracing::instrument(skip_all)]
@@ -4,7 +4,7 @@ use anyhow::{anyhow, Context};
 use axum::http::StatusCode;
 use const_format::formatcp;
 use serde::{Deserialize, Serialize};
-use sqlx::Executor;
+use sqlx::{Executor, Transaction};
 use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
@@ -21,7 +21,8 @@ use crate::{
     },
     db_span,
     error::{ErrorWithStatus, OperationResult},
-    into_log_server_error, log_server_error,
+    has_access, into_log_server_error, log_server_error,
+    quotas::{self, settings::QuotaSettings, Resource},
 };
 
 const TABLE: &str = "movements";
@@ -62,6 +63,24 @@ impl Movement {
             .map_err(into_log_server_error!())
     }
 
+    /// Select the movements of the owner of a profile, if the profile belongs to `owner_id` or was shared with them
+    pub async fn select_all_for_profile(
+        profile_id: Uuid,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} * FROM {TABLE} WHERE owner_id = (SELECT owner_id FROM profiles WHERE id = $1 AND {})",
+            has_access!(Read, "id", "$2")
+        ))
+        .bind(profile_id)
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .with_context(|| format!("failed to select movements for profile with id={profile_id}"))
+        .map_err(into_log_server_error!())
+    }
+
     pub async fn update_one(
         self,
         owner_id: OwnerId,
@@ -121,15 +140,18 @@ impl CreateMovement {
     pub async fn insert_one(
         self,
         owner_id: OwnerId,
-        executor: impl Executor<'_, Database = DB>,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<Movement> {
+        quotas::enforce(Resource::Movements, 1, owner_id, quotas, tx).await?;
+
         sqlx::query_as::<_, Movement>(formatcp!(
             "{INSERT_INTO} {TABLE} (name, description, owner_id) VALUES ($1, $2, $3) RETURNING *",
         ))
         .bind(&self.name)
         .bind(self.description.as_ref())
         .bind(owner_id)
-        .fetch_one(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+        .fetch_one((&mut **tx).instrument_executor(db_span!(INSERT_INTO, TABLE)))
         .await
         .map_err(|e| handle_error(e, || "failed to insert new movement"))
         .map_err(log_server_error!())
@@ -138,8 +160,11 @@ impl CreateMovement {
     pub async fn insert_many(
         movements: &[Self],
         owner_id: OwnerId,
-        executor: impl Executor<'_, Database = DB>,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<Vec<Movement>> {
+        quotas::enforce(Resource::Movements, movements.len(), owner_id, quotas, tx).await?;
+
         // sqlx does not yet support providing iterators for bound data.
         let names: Vec<_> = movements.iter().map(|m| m.name.as_str()).collect();
         let descriptions: Vec<_> = movements.iter().map(|m| m.description.as_deref()).collect();
@@ -156,7 +181,7 @@ impl CreateMovement {
         .bind(&names)
         .bind(&descriptions)
         .bind(owner_id)
-        .fetch_all(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+        .fetch_all((&mut **tx).instrument_executor(db_span!(INSERT_INTO, TABLE)))
         .await
         .map_err(|e| handle_error(e, || "failed to insert new movements"))
         .map_err(log_server_error!())
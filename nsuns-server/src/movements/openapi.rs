@@ -5,18 +5,21 @@ use utoipa::{
         response::Response,
         ComponentsBuilder, PathItemType, PathsBuilder, ResponseBuilder,
     },
-    PartialSchema, ToSchema,
+    IntoParams, PartialSchema, ToSchema,
 };
 
 use crate::{
     openapi::{
-        extensions::{created, ok, JsonContent},
+        extensions::{created, ok, param_in_default, JsonContent},
         Customizer,
     },
     router::MOVEMENTS_PATH,
 };
 
-use super::model::{CreateMovement, Movement};
+use super::{
+    handler::MovementsQuery,
+    model::{CreateMovement, Movement},
+};
 
 pub struct MovementsModule;
 
@@ -39,6 +42,7 @@ impl Customizer<ComponentsBuilder> for MovementsModule {
 impl Customizer<PathsBuilder> for MovementsModule {
     fn customize(builder: PathsBuilder) -> PathsBuilder {
         let get_op = OperationBuilder::new()
+            .parameters(Some(MovementsQuery::into_params(param_in_default)))
             .response(
                 ok(),
                 ResponseBuilder::new()
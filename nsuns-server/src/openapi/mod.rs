@@ -5,9 +5,11 @@ use utoipa::{
 };
 
 use crate::{
-    auth::openapi::AuthModule, maxes::openapi::MaxesModule, movements::openapi::MovementsModule,
+    admin::openapi::AdminModule, auth::openapi::AuthModule, grants::openapi::GrantsModule,
+    maxes::openapi::MaxesModule, movements::openapi::MovementsModule,
     profiles::openapi::ProfilesModule, program::openapi::ProgramModule, reps::openapi::RepsModule,
-    sets::openapi::SetsModule, updates::openapi::UpdatesModule,
+    sets::openapi::SetsModule, templates::openapi::TemplatesModule,
+    training_days::openapi::TrainingDaysModule, updates::openapi::UpdatesModule,
 };
 
 use self::settings::OpenApiFeature;
@@ -34,24 +36,32 @@ impl<B> WithModule<B> for B {
 impl OpenApi for ApiDoc {
     fn openapi() -> utoipa::openapi::OpenApi {
         let paths = PathsBuilder::new()
+            .with_module::<AdminModule>()
             .with_module::<AuthModule>()
+            .with_module::<GrantsModule>()
             .with_module::<MaxesModule>()
             .with_module::<MovementsModule>()
             .with_module::<ProfilesModule>()
             .with_module::<ProgramModule>()
             .with_module::<RepsModule>()
             .with_module::<SetsModule>()
+            .with_module::<TemplatesModule>()
+            .with_module::<TrainingDaysModule>()
             .with_module::<UpdatesModule>()
             .build();
 
         let components = ComponentsBuilder::new()
+            .with_module::<AdminModule>()
             .with_module::<AuthModule>()
+            .with_module::<GrantsModule>()
             .with_module::<MaxesModule>()
             .with_module::<MovementsModule>()
             .with_module::<ProfilesModule>()
             .with_module::<ProgramModule>()
             .with_module::<RepsModule>()
             .with_module::<SetsModule>()
+            .with_module::<TemplatesModule>()
+            .with_module::<TrainingDaysModule>()
             .with_module::<UpdatesModule>()
             .build();
 
@@ -9,6 +9,7 @@ use crate::{
     acquire,
     auth::token::OwnerId,
     db::{commit_ok, Pool},
+    quotas::settings::QuotaSettings,
     response_transforms::{created, or_404},
     transaction,
     validation::ValidatedJson,
@@ -37,12 +38,13 @@ pub async fn get_profile(
 #[tracing::instrument(skip_all)]
 pub async fn create_profile(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     ValidatedJson(profile): ValidatedJson<CreateProfile>,
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
     let res = profile
-        .create_one(owner_id, &mut tx)
+        .create_one(owner_id, &quotas, &mut tx)
         .await
         .map(Json)
         .map(created);
@@ -18,7 +18,9 @@ use crate::{
     },
     db_span,
     error::OperationResult,
+    grants::model::Access,
     into_log_server_error,
+    quotas::{self, settings::QuotaSettings, Resource},
 };
 
 const TABLE: &str = "profiles";
@@ -100,20 +102,35 @@ impl Profile {
         .map_err(into_log_server_error!())
     }
 
+    /// Check that the profile belongs to `owner_id`, or was shared with them with at least `access`.
+    ///
+    /// Returns the owner of the profile.
     pub async fn assert_owner(
         id: Uuid,
         owner_id: OwnerId,
+        access: Access,
         executor: impl Executor<'_, Database = DB>,
-    ) -> OperationResult<()> {
-        assert_owner!(TABLE, "profile", id, owner_id, executor)
+    ) -> OperationResult<OwnerId> {
+        match access {
+            Access::Read => assert_owner!(TABLE, "profile", id, owner_id, executor, Read, "id"),
+            Access::Write => assert_owner!(TABLE, "profile", id, owner_id, executor, Write, "id"),
+        }
     }
 
     pub async fn assert_all_owner(
         ids: &[Uuid],
         owner_id: OwnerId,
+        access: Access,
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<()> {
-        assert_all_owner!(TABLE, "profile", ids, owner_id, executor)
+        match access {
+            Access::Read => {
+                assert_all_owner!(TABLE, "profile", ids, owner_id, executor, Read, "id")
+            }
+            Access::Write => {
+                assert_all_owner!(TABLE, "profile", ids, owner_id, executor, Write, "id")
+            }
+        }
     }
 }
 
@@ -128,8 +145,11 @@ impl CreateProfile {
     pub async fn create_one(
         self,
         owner_id: OwnerId,
+        quotas: &QuotaSettings,
         tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<Profile> {
+        quotas::enforce(Resource::Profiles, 1, owner_id, quotas, tx).await?;
+
         sqlx::query_as::<_, Profile>(formatcp!(
             "{INSERT_INTO} {TABLE} (name, owner_id) VALUES ($1, $2) RETURNING *"
         ))
@@ -1,12 +1,13 @@
 use axum::{extract::FromRef, routing::get, Router};
 
-use crate::{db::Pool, router::State};
+use crate::{db::Pool, quotas::settings::QuotaSettings, router::State};
 
 use super::handler::{create_profile, delete_profile, get_profile, profiles_index, update_profile};
 
 pub fn router<S: State>() -> Router<S>
 where
     Pool: FromRef<S>,
+    QuotaSettings: FromRef<S>,
 {
     Router::new()
         .route(
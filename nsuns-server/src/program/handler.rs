@@ -3,6 +3,7 @@ use axum::{
     response::IntoResponse,
     Json,
 };
+use http::StatusCode;
 use serde::Deserialize;
 use utoipa::IntoParams;
 use uuid::Uuid;
@@ -12,13 +13,16 @@ use crate::{
     auth::token::OwnerId,
     db::{commit_ok, Pool},
     error::extract::WithErrorRejection,
+    quotas::settings::QuotaSettings,
     response_transforms::{created, or_404},
+    sets::model::Day,
     transaction,
     validation::ValidatedJson,
 };
 
 use super::model::{
-    delete_one, gather_program_summary, CreateProgram, ProgramMeta, ReorderSets, UpdateProgram,
+    delete_one, delete_week, gather_program_summary, AddWeek, CloneProgram, CreateProgram,
+    ProgramMeta, ReorderSets, ReplaceDay, UpdateProgram,
 };
 
 #[derive(Debug, Deserialize, IntoParams)]
@@ -28,6 +32,41 @@ pub struct ProgramQuery {
     pub profile_id: Uuid,
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SummaryQuery {
+    /// The week to fetch sets for, starting at 0
+    #[serde(default)]
+    pub week: i16,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DayQuery {
+    /// The week of the day, starting at 0
+    #[serde(default)]
+    pub week: i16,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct DayPath {
+    /// The id of the program
+    pub id: Uuid,
+    /// The day of the week, starting at 0 for Sunday
+    #[param(value_type = i16)]
+    pub day: Day,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct WeekPath {
+    /// The id of the program
+    pub id: Uuid,
+    /// The number of the week, starting at 0
+    pub week: i16,
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn profile_programs(
     State(pool): State<Pool>,
@@ -43,13 +82,14 @@ pub async fn profile_programs(
 #[tracing::instrument(skip_all)]
 pub async fn create_program(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     ValidatedJson(program): ValidatedJson<CreateProgram>,
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
 
     let res = program
-        .insert_one(owner_id, &mut tx)
+        .insert_one(owner_id, &quotas, &mut tx)
         .await
         .map(Json)
         .map(created);
@@ -57,6 +97,23 @@ pub async fn create_program(
     commit_ok(res, tx).await
 }
 
+#[tracing::instrument(skip_all)]
+pub async fn clone_program(
+    State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
+    Path(id): Path<Uuid>,
+    owner_id: OwnerId,
+    ValidatedJson(clone): ValidatedJson<CloneProgram>,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = clone
+        .clone_program(id, owner_id, &quotas, &mut tx)
+        .await
+        .map(|program| program.map(Json).map(created))
+        .map(or_404::<_, (StatusCode, Json<_>)>);
+    commit_ok(res, tx).await
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn update_program(
     State(pool): State<Pool>,
@@ -100,10 +157,58 @@ pub async fn delete_program(
 pub async fn program_summary(
     State(pool): State<Pool>,
     Path(id): Path<Uuid>,
+    WithErrorRejection(Query(params)): WithErrorRejection<Query<SummaryQuery>>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = gather_program_summary(id, params.week, owner_id, &mut tx)
+        .await
+        .map(or_404::<_, Json<_>>);
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn add_week(
+    State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
+    Path(id): Path<Uuid>,
+    owner_id: OwnerId,
+    ValidatedJson(add_week): ValidatedJson<AddWeek>,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = add_week
+        .add_to(id, owner_id, &quotas, &mut tx)
+        .await
+        .map(|week| week.map(Json).map(created))
+        .map(or_404::<_, (StatusCode, Json<_>)>);
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn remove_week(
+    State(pool): State<Pool>,
+    WithErrorRejection(Path(path)): WithErrorRejection<Path<WeekPath>>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = delete_week(path.id, path.week, owner_id, &mut tx)
+        .await
+        .map(or_404::<_, Json<_>>);
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn replace_day(
+    State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
+    WithErrorRejection(Path(path)): WithErrorRejection<Path<DayPath>>,
+    WithErrorRejection(Query(params)): WithErrorRejection<Query<DayQuery>>,
     owner_id: OwnerId,
+    ValidatedJson(replace): ValidatedJson<ReplaceDay>,
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
-    let res = gather_program_summary(id, owner_id, &mut tx)
+    let res = replace
+        .replace(path.id, params.week, path.day, owner_id, &quotas, &mut tx)
         .await
         .map(or_404::<_, Json<_>>);
     commit_ok(res, tx).await
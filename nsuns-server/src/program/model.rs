@@ -1,4 +1,7 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 use anyhow::{anyhow, Context};
 use axum::http::StatusCode;
@@ -9,7 +12,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgQueryResult, Executor, Transaction};
 use utoipa::ToSchema;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 use crate::{
     assert_all_owner, assert_owner,
@@ -23,9 +26,13 @@ use crate::{
     },
     db_span,
     error::{ErrorWithStatus, OperationResult},
-    into_log_server_error, log_server_error,
+    grants::model::Access,
+    has_access, into_log_server_error, log_server_error,
+    movements::model::{CreateMovement, Movement},
     profiles::model::Profile,
-    sets::model::{Day, Set},
+    quotas::{self, settings::QuotaSettings, Resource},
+    sets::model::{remove_week_sets, validate_slot, CreateSet, Day, Set, Slot, UpdateSet},
+    training_days::model::TrainingDay,
     vec::MoveWithin,
 };
 
@@ -51,19 +58,28 @@ pub struct Program {
     pub name: String,
     pub description: Option<String>,
     pub owner: Uuid,
+    /// The owner of the profile, which may differ from the requester if the profile was shared with them
+    pub owner_id: OwnerId,
     pub created_on: NaiveDateTime,
-    pub set_ids_sunday: Vec<Uuid>,
-    pub set_ids_monday: Vec<Uuid>,
-    pub set_ids_tuesday: Vec<Uuid>,
-    pub set_ids_wednesday: Vec<Uuid>,
-    pub set_ids_thursday: Vec<Uuid>,
-    pub set_ids_friday: Vec<Uuid>,
-    pub set_ids_saturday: Vec<Uuid>,
 }
 
+const WEEKS_TABLE: &str = "program_weeks";
+
+const TRAINING_DAYS_TABLE: &str = "training_days";
+
+const SETS_TABLE: &str = "program_sets";
+
+/// The profile a week belongs to, through its program
+const WEEK_PROFILE_ID: &str = "(SELECT p.owner FROM programs p WHERE p.id = program_id)";
+
+/// Programs cannot be longer than a year
+pub const MAX_WEEKS: i16 = 52;
+
+/// The ordered set ids of each day in one week of a program
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct ProgramSetIds {
-    pub id: Uuid,
+    pub program_id: Uuid,
+    pub week: i16,
     pub set_ids_sunday: Vec<Uuid>,
     pub set_ids_monday: Vec<Uuid>,
     pub set_ids_tuesday: Vec<Uuid>,
@@ -73,39 +89,68 @@ pub struct ProgramSetIds {
     pub set_ids_saturday: Vec<Uuid>,
 }
 
+/// The set ids of each day of a week `w`, gathered from the ordering of the sets
+const SET_IDS_COLS: &str = "w.program_id,
+    w.week,
+    array(SELECT s.id FROM program_sets s WHERE s.program_id = w.program_id AND s.week = w.week AND s.day = 0 ORDER BY s.ordering) AS set_ids_sunday,
+    array(SELECT s.id FROM program_sets s WHERE s.program_id = w.program_id AND s.week = w.week AND s.day = 1 ORDER BY s.ordering) AS set_ids_monday,
+    array(SELECT s.id FROM program_sets s WHERE s.program_id = w.program_id AND s.week = w.week AND s.day = 2 ORDER BY s.ordering) AS set_ids_tuesday,
+    array(SELECT s.id FROM program_sets s WHERE s.program_id = w.program_id AND s.week = w.week AND s.day = 3 ORDER BY s.ordering) AS set_ids_wednesday,
+    array(SELECT s.id FROM program_sets s WHERE s.program_id = w.program_id AND s.week = w.week AND s.day = 4 ORDER BY s.ordering) AS set_ids_thursday,
+    array(SELECT s.id FROM program_sets s WHERE s.program_id = w.program_id AND s.week = w.week AND s.day = 5 ORDER BY s.ordering) AS set_ids_friday,
+    array(SELECT s.id FROM program_sets s WHERE s.program_id = w.program_id AND s.week = w.week AND s.day = 6 ORDER BY s.ordering) AS set_ids_saturday";
+
 impl ProgramSetIds {
     pub async fn select_one(
-        id: Uuid,
-        for_update: bool,
+        program_id: Uuid,
+        week: i16,
         owner_id: OwnerId,
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Option<Self>> {
-        let lock_clause = if for_update { "FOR UPDATE" } else { "" };
-
-        sqlx::query_as::<_, Self>(&format!(
-            "{SELECT}
-            id,
-            set_ids_sunday,
-            set_ids_monday,
-            set_ids_tuesday,
-            set_ids_wednesday,
-            set_ids_thursday,
-            set_ids_friday,
-            set_ids_saturday
-            FROM {TABLE}
-            WHERE id = $1 AND owner_id = $2 {lock_clause}"
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} {SET_IDS_COLS} FROM {WEEKS_TABLE} w
+            WHERE w.program_id = $1 AND w.week = $2 AND {}",
+            has_access!(Read, WEEK_PROFILE_ID, "$3")
         ))
-        .bind(id)
+        .bind(program_id)
+        .bind(week)
         .bind(owner_id)
-        .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, WEEKS_TABLE)))
         .await
-        .with_context(|| format!("failed to fetch program with id={id}"))
+        .with_context(|| format!("failed to fetch week={week} of program with id={program_id}"))
+        .map_err(into_log_server_error!())
+    }
+
+    pub async fn select_all(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} {SET_IDS_COLS} FROM {WEEKS_TABLE} w WHERE w.owner_id = $1 ORDER BY w.program_id, w.week"
+        ))
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, WEEKS_TABLE)))
+        .await
+        .context("failed to select all program weeks")
         .map_err(into_log_server_error!())
     }
 
-    /// Blindly update set ids on a program.
+    #[must_use]
+    pub fn days(&self) -> [(Day, &[Uuid]); 7] {
+        [
+            (Day::Sunday, &self.set_ids_sunday),
+            (Day::Monday, &self.set_ids_monday),
+            (Day::Tuesday, &self.set_ids_tuesday),
+            (Day::Wednesday, &self.set_ids_wednesday),
+            (Day::Thursday, &self.set_ids_thursday),
+            (Day::Friday, &self.set_ids_friday),
+            (Day::Saturday, &self.set_ids_saturday),
+        ]
+    }
+
+    /// Blindly reorder the sets of each day of a week to match these set ids.
     ///
-    /// This does _NOT_ check that the sets pointed to are valid, nor remove any dropped sets.
+    /// This does _NOT_ check that every set of a day is included, nor remove any dropped sets.
     ///
     /// This is primarily intended for bootstrapping a new program from a template.
     pub async fn update_one(
@@ -114,15 +159,18 @@ impl ProgramSetIds {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<()> {
         sqlx::query(formatcp!(
-            "{UPDATE} {TABLE} SET
-            set_ids_sunday = $1,
-            set_ids_monday = $2,
-            set_ids_tuesday = $3,
-            set_ids_wednesday = $4,
-            set_ids_thursday = $5,
-            set_ids_friday = $6,
-            set_ids_saturday = $7
-            WHERE id = $8 AND owner_id = $9"
+            "{UPDATE} {SETS_TABLE} s SET ordering = o.index - 1
+            FROM (
+                {SELECT} 0 AS day, a.id, a.index FROM unnest($1::UUID[]) WITH ORDINALITY a(id, index)
+                UNION ALL {SELECT} 1, a.id, a.index FROM unnest($2::UUID[]) WITH ORDINALITY a(id, index)
+                UNION ALL {SELECT} 2, a.id, a.index FROM unnest($3::UUID[]) WITH ORDINALITY a(id, index)
+                UNION ALL {SELECT} 3, a.id, a.index FROM unnest($4::UUID[]) WITH ORDINALITY a(id, index)
+                UNION ALL {SELECT} 4, a.id, a.index FROM unnest($5::UUID[]) WITH ORDINALITY a(id, index)
+                UNION ALL {SELECT} 5, a.id, a.index FROM unnest($6::UUID[]) WITH ORDINALITY a(id, index)
+                UNION ALL {SELECT} 6, a.id, a.index FROM unnest($7::UUID[]) WITH ORDINALITY a(id, index)
+            ) o
+            WHERE s.id = o.id AND s.day = o.day AND s.program_id = $8 AND s.week = $9 AND {}",
+            has_access!(Write, WEEK_PROFILE_ID, "$10")
         ))
         .bind(&self.set_ids_sunday)
         .bind(&self.set_ids_monday)
@@ -131,11 +179,17 @@ impl ProgramSetIds {
         .bind(&self.set_ids_thursday)
         .bind(&self.set_ids_friday)
         .bind(&self.set_ids_saturday)
-        .bind(self.id)
+        .bind(self.program_id)
+        .bind(self.week)
         .bind(owner_id)
-        .execute(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+        .execute(executor.instrument_executor(db_span!(UPDATE, SETS_TABLE)))
         .await
-        .with_context(|| format!("failed to fetch program with id={}", self.id))
+        .with_context(|| {
+            format!(
+                "failed to update set order of week={} of program with id={}",
+                self.week, self.program_id
+            )
+        })
         .map_err(into_log_server_error!())
         .map(|_| ())
     }
@@ -148,7 +202,8 @@ impl Program {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Option<Self>> {
         sqlx::query_as::<_, Self>(formatcp!(
-            "{SELECT} * from {TABLE} WHERE id = $1 AND owner_id = $2"
+            "{SELECT} * from {TABLE} WHERE id = $1 AND {}",
+            has_access!(Read, "owner", "$2")
         ))
         .bind(id)
         .bind(owner_id)
@@ -158,20 +213,51 @@ impl Program {
         .map_err(into_log_server_error!())
     }
 
+    pub async fn select_all(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} * FROM {TABLE} WHERE owner_id = $1 ORDER BY created_on"
+        ))
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select all programs")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Check that the program belongs to `owner_id`, or its profile was shared with them with at least `access`.
+    ///
+    /// Returns the owner of the program.
     pub async fn assert_owner(
         id: Uuid,
         owner_id: OwnerId,
+        access: Access,
         executor: impl Executor<'_, Database = DB>,
-    ) -> OperationResult<()> {
-        assert_owner!(TABLE, "program", id, owner_id, executor)
+    ) -> OperationResult<OwnerId> {
+        match access {
+            Access::Read => assert_owner!(TABLE, "program", id, owner_id, executor, Read, "owner"),
+            Access::Write => {
+                assert_owner!(TABLE, "program", id, owner_id, executor, Write, "owner")
+            }
+        }
     }
 
     pub async fn assert_all_owner(
         ids: &[Uuid],
         owner_id: OwnerId,
+        access: Access,
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<()> {
-        assert_all_owner!(TABLE, "program", ids, owner_id, executor)
+        match access {
+            Access::Read => {
+                assert_all_owner!(TABLE, "program", ids, owner_id, executor, Read, "owner")
+            }
+            Access::Write => {
+                assert_all_owner!(TABLE, "program", ids, owner_id, executor, Write, "owner")
+            }
+        }
     }
 }
 
@@ -196,7 +282,8 @@ impl ProgramMeta {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Vec<Self>> {
         sqlx::query_as::<_, Self>(formatcp!(
-            "{SELECT} {PROGRAM_META_COLS} FROM {TABLE} WHERE owner = $1 AND owner_id = $2 ORDER BY created_on",
+            "{SELECT} {PROGRAM_META_COLS} FROM {TABLE} WHERE owner = $1 AND {} ORDER BY created_on",
+            has_access!(Read, "owner", "$2")
         ))
         .bind(profile_id)
         .bind(owner_id)
@@ -212,7 +299,8 @@ impl ProgramMeta {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Option<Self>> {
         sqlx::query_as::<_, Self>(formatcp!(
-            "{SELECT} {PROGRAM_META_COLS} FROM {TABLE} WHERE id = $1 AND owner_id = $2",
+            "{SELECT} {PROGRAM_META_COLS} FROM {TABLE} WHERE id = $1 AND {}",
+            has_access!(Read, "owner", "$2")
         ))
         .bind(id)
         .bind(owner_id)
@@ -249,10 +337,14 @@ impl CreateProgram {
     pub async fn insert_one(
         self,
         owner_id: OwnerId,
+        quotas: &QuotaSettings,
         tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<ProgramMeta> {
-        Profile::assert_owner(self.owner, owner_id, &mut **tx).await?;
-        sqlx::query_as::<_, ProgramMeta>(formatcp!(
+        let owner_id =
+            Profile::assert_owner(self.owner, owner_id, Access::Write, &mut **tx).await?;
+        quotas::enforce(Resource::Programs, 1, owner_id, quotas, tx).await?;
+
+        let program = sqlx::query_as::<_, ProgramMeta>(formatcp!(
             "{INSERT_INTO} {TABLE} (name, description, owner, owner_id) VALUES ($1, $2, $3, $4) RETURNING {PROGRAM_META_COLS}",
         ))
         .bind(self.name)
@@ -262,7 +354,11 @@ impl CreateProgram {
         .fetch_one((&mut **tx).instrument_executor(db_span!(INSERT_INTO, TABLE)))
         .await
         .map_err(|e| handle_error(e, || "failed to create program"))
-        .map_err(log_server_error!())
+        .map_err(log_server_error!())?;
+
+        append_weeks(program.id, 1, owner_id, &mut **tx).await?;
+
+        Ok(program)
     }
 }
 
@@ -283,7 +379,8 @@ impl UpdateProgram {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Option<ProgramMeta>> {
         sqlx::query_as::<_, ProgramMeta>(formatcp!(
-            "{UPDATE} {TABLE} SET name = $1, description = $2 WHERE id = $3 AND owner_id = $4 RETURNING {PROGRAM_META_COLS}",
+            "{UPDATE} {TABLE} SET name = $1, description = $2 WHERE id = $3 AND {} RETURNING {PROGRAM_META_COLS}",
+            has_access!(Write, "owner", "$4")
         ))
         .bind(self.name)
         .bind(self.description)
@@ -306,7 +403,8 @@ pub async fn delete_one(
     executor: impl Executor<'_, Database = DB>,
 ) -> OperationResult<Option<ProgramMeta>> {
     sqlx::query_as::<_, ProgramMeta>(formatcp!(
-        "{DELETE_FROM} {TABLE} WHERE id = $1 AND owner_id = $2 RETURNING {PROGRAM_META_COLS}"
+        "{DELETE_FROM} {TABLE} WHERE id = $1 AND {} RETURNING {PROGRAM_META_COLS}",
+        has_access!(Write, "owner", "$2")
     ))
     .bind(id)
     .bind(owner_id)
@@ -320,6 +418,10 @@ pub async fn delete_one(
 #[serde(rename_all = "camelCase")]
 pub struct ProgramSummary {
     pub program: ProgramMeta,
+    /// The week the sets are from
+    pub week: i16,
+    /// How many weeks the program has
+    pub weeks: i16,
     pub sets_sunday: Vec<Set>,
     pub sets_monday: Vec<Set>,
     pub sets_tuesday: Vec<Set>,
@@ -327,163 +429,215 @@ pub struct ProgramSummary {
     pub sets_thursday: Vec<Set>,
     pub sets_friday: Vec<Set>,
     pub sets_saturday: Vec<Set>,
+    /// The sets of each of the program's training days, in the order of the rotation
+    pub training_days: Vec<TrainingDaySets>,
+}
+
+/// The ordered sets of a training day in one week of a program
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingDaySets {
+    pub training_day: TrainingDay,
+    pub sets: Vec<Set>,
 }
 
 pub async fn gather_program_summary(
     id: Uuid,
+    week: i16,
     owner_id: OwnerId,
     tx: &mut Transaction<'_, DB>,
 ) -> OperationResult<Option<ProgramSummary>> {
-    let program_opt = Program::select_one(id, owner_id, &mut **tx).await?;
-
-    let get_ctx = || format!("failed to fetch sets for program with id={id}");
-
-    if let Some(program) = program_opt {
-        let all_ids: Vec<_> = [
-            program.set_ids_sunday.clone(),
-            program.set_ids_monday.clone(),
-            program.set_ids_tuesday.clone(),
-            program.set_ids_wednesday.clone(),
-            program.set_ids_thursday.clone(),
-            program.set_ids_friday.clone(),
-            program.set_ids_saturday.clone(),
-        ]
-        .concat();
+    let Some(program) = Program::select_one(id, owner_id, &mut **tx).await? else {
+        return Ok(None);
+    };
 
-        let all_sets = Set::select_where_id_in(&all_ids, owner_id, &mut **tx)
-            .await
-            .with_context(get_ctx)
-            .map_err(into_log_server_error!())?;
+    let weeks = count_weeks(id, &mut **tx).await?;
 
-        let mut set_map: HashMap<Uuid, Set> = HashMap::with_capacity(all_sets.len());
+    if !(0..weeks).contains(&week) {
+        return Ok(None);
+    }
 
-        for set in all_sets {
-            set_map.insert(set.id, set);
-        }
+    let mut days: [Vec<Set>; 7] = Default::default();
+    let mut training_day_sets: HashMap<Uuid, Vec<Set>> = HashMap::new();
 
-        let sets_sunday = program
-            .set_ids_sunday
-            .iter()
-            .filter_map(|set_id| set_map.remove(set_id))
-            .collect();
+    // sets arrive in order within each day, so they only need to be grouped
+    for set in Set::select_for_week(id, week, program.owner_id, &mut **tx).await? {
+        match set.slot() {
+            Some(Slot::Day(day)) => days[day as usize].push(set),
+            Some(Slot::TrainingDay(training_day_id)) => {
+                training_day_sets
+                    .entry(training_day_id)
+                    .or_default()
+                    .push(set);
+            }
+            None => {}
+        }
+    }
 
-        let sets_monday = program
-            .set_ids_monday
-            .iter()
-            .filter_map(|set_id| set_map.remove(set_id))
-            .collect();
+    let training_days = TrainingDay::select_for_program(id, owner_id, &mut **tx)
+        .await?
+        .into_iter()
+        .map(|training_day| TrainingDaySets {
+            sets: training_day_sets
+                .remove(&training_day.id)
+                .unwrap_or_default(),
+            training_day,
+        })
+        .collect();
 
-        let sets_tuesday = program
-            .set_ids_tuesday
-            .iter()
-            .filter_map(|set_id| set_map.remove(set_id))
-            .collect();
+    let [sets_sunday, sets_monday, sets_tuesday, sets_wednesday, sets_thursday, sets_friday, sets_saturday] =
+        days;
 
-        let sets_wednesday = program
-            .set_ids_wednesday
-            .iter()
-            .filter_map(|set_id| set_map.remove(set_id))
-            .collect();
+    Ok(Some(ProgramSummary {
+        program: program.into(),
+        week,
+        weeks,
+        sets_sunday,
+        sets_monday,
+        sets_tuesday,
+        sets_wednesday,
+        sets_thursday,
+        sets_friday,
+        sets_saturday,
+        training_days,
+    }))
+}
 
-        let sets_thursday = program
-            .set_ids_thursday
-            .iter()
-            .filter_map(|set_id| set_map.remove(set_id))
-            .collect();
+/// Check that a weekday or training day exists in one week of a program, optionally locking it
+/// so the order of its sets can be changed.
+async fn select_slot(
+    program_id: Uuid,
+    week: i16,
+    slot: Slot,
+    for_update: bool,
+    owner_id: OwnerId,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<bool> {
+    let (access_clause, lock_clause) = if for_update {
+        (has_access!(Write, WEEK_PROFILE_ID, "$3"), "FOR UPDATE")
+    } else {
+        (has_access!(Read, WEEK_PROFILE_ID, "$3"), "")
+    };
 
-        let sets_friday = program
-            .set_ids_friday
-            .iter()
-            .filter_map(|set_id| set_map.remove(set_id))
-            .collect();
+    let sql = match slot {
+        // the week is locked for a weekday, since all weekdays of a week share it
+        Slot::Day(_) => format!(
+            "{SELECT} 1 FROM {WEEKS_TABLE} WHERE program_id = $1 AND week = $2 AND {access_clause} {lock_clause}",
+        ),
+        Slot::TrainingDay(_) => format!(
+            "{SELECT} 1 FROM {TRAINING_DAYS_TABLE}
+            WHERE id = $4 AND program_id = $1
+            AND EXISTS ({SELECT} 1 FROM {WEEKS_TABLE} w WHERE w.program_id = $1 AND w.week = $2)
+            AND {access_clause} {lock_clause}",
+        ),
+    };
 
-        let sets_saturday = program
-            .set_ids_saturday
-            .iter()
-            .filter_map(|set_id| set_map.remove(set_id))
-            .collect();
+    let mut query = sqlx::query(&sql).bind(program_id).bind(week).bind(owner_id);
 
-        Ok(Some(ProgramSummary {
-            program: program.into(),
-            sets_sunday,
-            sets_monday,
-            sets_tuesday,
-            sets_wednesday,
-            sets_thursday,
-            sets_friday,
-            sets_saturday,
-        }))
-    } else {
-        Ok(None)
+    if let Some(training_day_id) = slot.training_day_id() {
+        query = query.bind(training_day_id);
     }
-}
 
-fn get_day_column(day: Day) -> &'static str {
-    match day {
-        Day::Sunday => "set_ids_sunday",
-        Day::Monday => "set_ids_monday",
-        Day::Tuesday => "set_ids_tuesday",
-        Day::Wednesday => "set_ids_wednesday",
-        Day::Thursday => "set_ids_thursday",
-        Day::Friday => "set_ids_friday",
-        Day::Saturday => "set_ids_saturday",
-    }
+    query
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, WEEKS_TABLE)))
+        .await
+        .with_context(|| {
+            format!("failed to fetch week={week}, slot={slot:?} of program_id={program_id}")
+        })
+        .map_err(into_log_server_error!())
+        .map(|row| row.is_some())
 }
 
+/// Get the ordered set ids of a weekday or training day in one week of a program.
+///
+/// Returns [`None`] if the week, or the training day, does not exist in the program.
 pub async fn get_set_ids(
     program_id: Uuid,
-    day: Day,
+    week: i16,
+    slot: Slot,
     for_update: bool,
     owner_id: OwnerId,
-    executor: impl Executor<'_, Database = DB>,
+    tx: &mut Transaction<'_, DB>,
 ) -> OperationResult<Option<Vec<Uuid>>> {
-    let day_col = get_day_column(day);
-    let lock_clause = if for_update { "FOR UPDATE" } else { "" };
+    if !select_slot(program_id, week, slot, for_update, owner_id, &mut **tx).await? {
+        return Ok(None);
+    }
 
-    let set_ids = sqlx::query_as::<_, (Vec<Uuid>,)>(&format!(
-        "{SELECT} {day_col} FROM {TABLE} WHERE id = $1 AND owner_id = $2 {lock_clause}",
+    // selected after taking the lock, so the order includes changes made by whoever held it before
+    let set_ids = sqlx::query_as::<_, (Uuid,)>(formatcp!(
+        "{SELECT} id FROM {SETS_TABLE}
+        WHERE program_id = $1 AND week = $2 AND (day = $3 OR training_day_id = $4)
+        ORDER BY ordering"
     ))
     .bind(program_id)
-    .bind(owner_id)
-    .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+    .bind(week)
+    .bind(slot.day())
+    .bind(slot.training_day_id())
+    .fetch_all((&mut **tx).instrument_executor(db_span!(SELECT, SETS_TABLE)))
     .await
     .with_context(|| {
-        format!("failed to fetch existing set ids for day={day:?} and program_id={program_id}",)
+        format!("failed to fetch existing set ids for week={week}, slot={slot:?} and program_id={program_id}",)
     })
     .map_err(into_log_server_error!())?
-    .map(|id| id.0);
+    .into_iter()
+    .map(|(id,)| id)
+    .collect();
 
-    Ok(set_ids)
+    Ok(Some(set_ids))
 }
 
+/// Set the order of the sets of a weekday or training day in one week of a program.
+///
+/// Sets that are not in `set_ids` keep their position, so they must all be included.
 pub async fn update_set_ids(
     program_id: Uuid,
-    day: Day,
+    week: i16,
+    slot: Slot,
     set_ids: &[Uuid],
     owner_id: OwnerId,
     executor: impl Executor<'_, Database = DB>,
 ) -> OperationResult<PgQueryResult> {
-    let day_col = get_day_column(day);
-    sqlx::query(&format!(
-        "{UPDATE} {TABLE} SET {day_col} = $1 WHERE id = $2 AND owner_id = $3"
+    sqlx::query(formatcp!(
+        "{UPDATE} {SETS_TABLE} s SET ordering = o.index - 1
+        FROM unnest($1::UUID[]) WITH ORDINALITY o(id, index)
+        WHERE s.id = o.id AND s.program_id = $2 AND s.week = $3
+        AND (s.day = $5 OR s.training_day_id = $6) AND {}",
+        has_access!(Write, WEEK_PROFILE_ID, "$4")
     ))
     .bind(set_ids)
     .bind(program_id)
+    .bind(week)
     .bind(owner_id)
-    .execute(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+    .bind(slot.day())
+    .bind(slot.training_day_id())
+    .execute(executor.instrument_executor(db_span!(UPDATE, SETS_TABLE)))
     .await
     .with_context(|| {
-        format!("failed to update set ids for day={day:?} and program_id={program_id}",)
+        format!(
+            "failed to update set ids for week={week}, slot={slot:?} and program_id={program_id}",
+        )
     })
     .map_err(into_log_server_error!())
 }
 
+fn validate_reorder_slot(reorder: &ReorderSets) -> Result<(), ValidationError> {
+    validate_slot(reorder.day, reorder.training_day_id).map(|_| ())
+}
+
+/// Move a set within a weekday or training day, given by exactly one of `day` or `trainingDayId`
 #[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "validate_reorder_slot", skip_on_field_errors = false))]
 #[serde(rename_all = "camelCase")]
 pub struct ReorderSets {
     pub program_id: Uuid,
-    #[schema(value_type = i16)]
-    pub day: Day,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub week: i16,
+    #[serde(default)]
+    #[schema(value_type = Option<i16>)]
+    pub day: Option<Day>,
+    #[serde(default)]
+    pub training_day_id: Option<Uuid>,
     #[validate(range(min = 0))]
     pub from: usize,
     #[validate(range(min = 0))]
@@ -509,8 +663,11 @@ impl ReorderSets {
         owner_id: OwnerId,
         tx: &mut Transaction<'a, DB>,
     ) -> OperationResult<Option<Vec<SetId>>> {
+        let slot = validate_slot(self.day, self.training_day_id)
+            .map_err(|e| ErrorWithStatus::new(StatusCode::BAD_REQUEST, anyhow!(e)))?;
+
         if let Some(mut set_ids) =
-            get_set_ids(self.program_id, self.day, true, owner_id, &mut **tx).await?
+            get_set_ids(self.program_id, self.week, slot, true, owner_id, tx).await?
         {
             if self.from >= set_ids.len() || self.to >= set_ids.len() {
                 return Err(ErrorWithStatus::new(
@@ -520,7 +677,15 @@ impl ReorderSets {
             }
 
             if set_ids.move_within(self.from, self.to) {
-                update_set_ids(self.program_id, self.day, &set_ids, owner_id, &mut **tx).await?;
+                update_set_ids(
+                    self.program_id,
+                    self.week,
+                    slot,
+                    &set_ids,
+                    owner_id,
+                    &mut **tx,
+                )
+                .await?;
             }
 
             return Ok(Some(set_ids.into_iter().map(SetId).collect()));
@@ -529,3 +694,517 @@ impl ReorderSets {
         Ok(None)
     }
 }
+
+/// A set of a day that is replaced as a whole. Sets without an id are created.
+#[derive(Debug, Serialize, Deserialize, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DaySet {
+    /// The id of an existing set on the day, or nothing to create a new set
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    pub movement_id: Uuid,
+    #[validate(range(min = 0))]
+    pub reps: Option<i32>,
+    #[serde(default)]
+    pub reps_is_minimum: bool,
+    #[validate(length(min = 1))]
+    pub description: Option<String>,
+    #[validate(range(min = 0))]
+    pub amount: f64,
+    pub percentage_of_max: Option<Uuid>,
+}
+
+/// The full, ordered list of sets a day should have
+#[derive(Debug, Serialize, Deserialize, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceDay {
+    #[validate]
+    pub sets: Vec<DaySet>,
+}
+
+impl ReplaceDay {
+    /// Make the sets of a day match this list, deleting the sets that are left out.
+    ///
+    /// Returns the sets of the day in order, or [`None`] if the program or week was not found.
+    pub async fn replace(
+        self,
+        program_id: Uuid,
+        week: i16,
+        day: Day,
+        owner_id: OwnerId,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Option<Vec<Set>>> {
+        let slot = Slot::Day(day);
+
+        let Some(existing_ids) = get_set_ids(program_id, week, slot, true, owner_id, tx).await?
+        else {
+            return Ok(None);
+        };
+
+        let owner_id =
+            Program::assert_owner(program_id, owner_id, Access::Write, &mut **tx).await?;
+
+        let mut kept_ids: Vec<Uuid> = Vec::with_capacity(self.sets.len());
+
+        for id in self.sets.iter().filter_map(|set| set.id) {
+            if !existing_ids.contains(&id) {
+                return Err(ErrorWithStatus::new(
+                    StatusCode::CONFLICT,
+                    anyhow!("set with id={id} is not on this day"),
+                ));
+            }
+            if kept_ids.contains(&id) {
+                return Err(ErrorWithStatus::new(
+                    StatusCode::CONFLICT,
+                    anyhow!("set with id={id} is listed more than once"),
+                ));
+            }
+            kept_ids.push(id);
+        }
+
+        let deleted_ids: Vec<_> = existing_ids
+            .into_iter()
+            .filter(|id| !kept_ids.contains(id))
+            .collect();
+
+        if !deleted_ids.is_empty() {
+            sqlx::query(formatcp!(
+                "{DELETE_FROM} {SETS_TABLE} WHERE id = any($1) AND program_id = $2"
+            ))
+            .bind(&deleted_ids)
+            .bind(program_id)
+            .execute((&mut **tx).instrument_executor(db_span!(DELETE_FROM, SETS_TABLE)))
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to delete sets of week={week}, day={day:?} and program_id={program_id}"
+                )
+            })
+            .map_err(into_log_server_error!())?;
+        }
+
+        // close the gaps left by the deleted sets, so new sets can go after the kept ones
+        update_set_ids(program_id, week, slot, &kept_ids, owner_id, &mut **tx).await?;
+
+        let mut new_sets = vec![];
+
+        for set in self.sets.iter().cloned() {
+            match set.id {
+                Some(id) => {
+                    UpdateSet {
+                        id,
+                        movement_id: set.movement_id,
+                        reps: set.reps,
+                        reps_is_minimum: set.reps_is_minimum,
+                        description: set.description,
+                        amount: set.amount,
+                        percentage_of_max: set.percentage_of_max,
+                    }
+                    .update_one(owner_id, tx)
+                    .await?;
+                }
+                None => new_sets.push(CreateSet {
+                    program_id,
+                    movement_id: set.movement_id,
+                    week,
+                    day: Some(day),
+                    training_day_id: None,
+                    reps: set.reps,
+                    reps_is_minimum: set.reps_is_minimum,
+                    description: set.description,
+                    amount: set.amount,
+                    percentage_of_max: set.percentage_of_max,
+                }),
+            }
+        }
+
+        if !new_sets.is_empty() {
+            CreateSet::insert_many(&new_sets, program_id, owner_id, quotas, tx).await?;
+        }
+
+        // new sets were added after the kept ones, in the order they were given
+        let mut created_ids = get_set_ids(program_id, week, slot, false, owner_id, tx)
+            .await?
+            .unwrap_or_default()
+            .into_iter()
+            .skip(kept_ids.len());
+
+        let set_ids: Vec<_> = self
+            .sets
+            .iter()
+            .filter_map(|set| set.id.or_else(|| created_ids.next()))
+            .collect();
+
+        update_set_ids(program_id, week, slot, &set_ids, owner_id, &mut **tx).await?;
+
+        let mut sets: HashMap<Uuid, Set> =
+            Set::select_for_week(program_id, week, owner_id, &mut **tx)
+                .await?
+                .into_iter()
+                .map(|set| (set.id, set))
+                .collect();
+
+        Ok(Some(
+            set_ids.iter().filter_map(|id| sets.remove(id)).collect(),
+        ))
+    }
+}
+
+async fn count_weeks(
+    program_id: Uuid,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<i16> {
+    sqlx::query_as::<_, (i16,)>(formatcp!(
+        "{SELECT} count(*)::SMALLINT FROM {WEEKS_TABLE} WHERE program_id = $1"
+    ))
+    .bind(program_id)
+    .fetch_one(executor.instrument_executor(db_span!(SELECT, WEEKS_TABLE)))
+    .await
+    .with_context(|| format!("failed to count weeks of program with id={program_id}"))
+    .map_err(into_log_server_error!())
+    .map(|(weeks,)| weeks)
+}
+
+/// Lock the weeks of a program that `owner_id` can change, so weeks can be added or renumbered.
+///
+/// Returns the owner of the program and how many weeks it has, or [`None`] if the program was not found.
+async fn lock_weeks(
+    program_id: Uuid,
+    owner_id: OwnerId,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<Option<(OwnerId, i16)>> {
+    let rows = sqlx::query_as::<_, (OwnerId,)>(formatcp!(
+        "{SELECT} owner_id FROM {WEEKS_TABLE} WHERE program_id = $1 AND {} FOR UPDATE",
+        has_access!(Write, WEEK_PROFILE_ID, "$2")
+    ))
+    .bind(program_id)
+    .bind(owner_id)
+    .fetch_all(executor.instrument_executor(db_span!(SELECT, WEEKS_TABLE)))
+    .await
+    .with_context(|| format!("failed to lock weeks of program with id={program_id}"))
+    .map_err(into_log_server_error!())?;
+
+    // every program has at least one week, so no rows means the program is not accessible
+    Ok(rows
+        .first()
+        .map(|(owner_id,)| (*owner_id, rows.len() as i16)))
+}
+
+/// Add `count` empty weeks to the end of a program, returning the number of the first new week.
+///
+/// This does _NOT_ check access to the program.
+pub async fn append_weeks(
+    program_id: Uuid,
+    count: i16,
+    owner_id: OwnerId,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<i16> {
+    let weeks = sqlx::query_as::<_, (i16,)>(formatcp!(
+        "WITH next AS (
+            {SELECT} COALESCE(MAX(week) + 1, 0) AS week FROM {WEEKS_TABLE} WHERE program_id = $1
+        )
+        {INSERT_INTO} {WEEKS_TABLE} (program_id, week, owner_id)
+        {SELECT} $1, next.week + n, $3 FROM next, generate_series(0, $2 - 1) n
+        RETURNING week"
+    ))
+    .bind(program_id)
+    .bind(count)
+    .bind(owner_id)
+    .fetch_all(executor.instrument_executor(db_span!(INSERT_INTO, WEEKS_TABLE)))
+    .await
+    .with_context(|| format!("failed to add weeks to program with id={program_id}"))
+    .map_err(into_log_server_error!())?;
+
+    weeks
+        .into_iter()
+        .map(|(week,)| week)
+        .min()
+        .ok_or_else(|| anyhow!("no weeks were added to program with id={program_id}").into())
+        .map_err(log_server_error!())
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramWeek {
+    pub program_id: Uuid,
+    pub week: i16,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddWeek {
+    /// Copy the sets of this week into the new week, instead of leaving it empty
+    #[validate(range(min = 0))]
+    pub copy_from: Option<i16>,
+}
+
+impl AddWeek {
+    /// Add a week to the end of a program
+    pub async fn add_to(
+        self,
+        program_id: Uuid,
+        owner_id: OwnerId,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Option<ProgramWeek>> {
+        let Some((owner_id, weeks)) = lock_weeks(program_id, owner_id, &mut **tx).await? else {
+            return Ok(None);
+        };
+
+        if weeks >= MAX_WEEKS {
+            return Err(ErrorWithStatus::new(
+                StatusCode::CONFLICT,
+                anyhow!("programs can have at most {MAX_WEEKS} weeks"),
+            ));
+        }
+
+        if let Some(copy_from) = self.copy_from {
+            if copy_from >= weeks {
+                return Err(ErrorWithStatus::new(
+                    StatusCode::CONFLICT,
+                    anyhow!("referenced week does not exist"),
+                ));
+            }
+        }
+
+        let week = append_weeks(program_id, 1, owner_id, &mut **tx).await?;
+
+        if let Some(copy_from) = self.copy_from {
+            copy_sets(program_id, copy_from, week, owner_id, quotas, tx).await?;
+        }
+
+        Ok(Some(ProgramWeek { program_id, week }))
+    }
+}
+
+/// Create copies of the sets of a week in another week of the same program, in the same order.
+///
+/// This includes the sets of every training day.
+async fn copy_sets(
+    program_id: Uuid,
+    from: i16,
+    week: i16,
+    owner_id: OwnerId,
+    quotas: &QuotaSettings,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<()> {
+    let copies: Vec<_> = Set::select_for_week(program_id, from, owner_id, &mut **tx)
+        .await?
+        .into_iter()
+        .map(|set| CreateSet {
+            program_id,
+            movement_id: set.movement_id,
+            week,
+            day: set.day,
+            training_day_id: set.training_day_id,
+            reps: set.reps,
+            reps_is_minimum: set.reps_is_minimum,
+            description: set.description,
+            amount: set.amount,
+            percentage_of_max: set.percentage_of_max,
+        })
+        .collect();
+
+    if !copies.is_empty() {
+        CreateSet::insert_many(&copies, program_id, owner_id, quotas, tx).await?;
+    }
+
+    Ok(())
+}
+
+/// Delete a week of a program along with its sets. Later weeks move up to fill the gap.
+pub async fn delete_week(
+    program_id: Uuid,
+    week: i16,
+    owner_id: OwnerId,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<Option<ProgramWeek>> {
+    let Some((owner_id, weeks)) = lock_weeks(program_id, owner_id, &mut **tx).await? else {
+        return Ok(None);
+    };
+
+    if !(0..weeks).contains(&week) {
+        return Ok(None);
+    }
+
+    if weeks == 1 {
+        return Err(ErrorWithStatus::new(
+            StatusCode::CONFLICT,
+            anyhow!("programs must have at least one week"),
+        ));
+    }
+
+    remove_week_sets(program_id, week, owner_id, &mut **tx).await?;
+
+    sqlx::query(formatcp!(
+        "WITH deleted AS (
+            {DELETE_FROM} {WEEKS_TABLE} WHERE program_id = $1 AND week = $2 AND owner_id = $3
+        )
+        {UPDATE} {WEEKS_TABLE} SET week = week - 1 WHERE program_id = $1 AND week > $2 AND owner_id = $3"
+    ))
+    .bind(program_id)
+    .bind(week)
+    .bind(owner_id)
+    .execute((&mut **tx).instrument_executor(db_span!(DELETE_FROM, WEEKS_TABLE)))
+    .await
+    .with_context(|| format!("failed to delete week={week} of program with id={program_id}"))
+    .map_err(into_log_server_error!())?;
+
+    Ok(Some(ProgramWeek { program_id, week }))
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneProgram {
+    /// The profile to create the copy in, instead of the profile of the program
+    pub owner: Option<Uuid>,
+    /// The name of the copy, instead of the name of the program
+    #[validate(length(min = 1))]
+    pub name: Option<String>,
+}
+
+impl CloneProgram {
+    /// Copy a program with all of its weeks, training days and sets, returning the copy.
+    ///
+    /// Sets keep referring to the same movements if the copy has the same owner as the program. Otherwise, they refer
+    /// to the movements of the new owner with the same names, which are created if the new owner does not have them.
+    pub async fn clone_program(
+        self,
+        program_id: Uuid,
+        owner_id: OwnerId,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Option<ProgramMeta>> {
+        let Some(program) = Program::select_one(program_id, owner_id, &mut **tx).await? else {
+            return Ok(None);
+        };
+
+        let profile_id = self.owner.unwrap_or(program.owner);
+        let copy_owner_id =
+            Profile::assert_owner(profile_id, owner_id, Access::Write, &mut **tx).await?;
+
+        let weeks = count_weeks(program.id, &mut **tx).await?;
+
+        let mut sets = Vec::new();
+        for week in 0..weeks {
+            sets.extend(Set::select_for_week(program.id, week, program.owner_id, &mut **tx).await?);
+        }
+
+        let movement_ids = if copy_owner_id.as_uuid() == program.owner_id.as_uuid() {
+            None
+        } else {
+            Some(copy_movements(&sets, program.owner_id, copy_owner_id, quotas, tx).await?)
+        };
+
+        let copy = CreateProgram {
+            name: self.name.unwrap_or(program.name),
+            description: program.description,
+            owner: profile_id,
+        }
+        .insert_one(owner_id, quotas, tx)
+        .await?;
+
+        // the copy starts with one week
+        if weeks > 1 {
+            append_weeks(copy.id, weeks - 1, copy_owner_id, &mut **tx).await?;
+        }
+
+        let training_days =
+            TrainingDay::select_for_program(program.id, owner_id, &mut **tx).await?;
+
+        let training_day_ids: HashMap<Uuid, Uuid> = if training_days.is_empty() {
+            HashMap::new()
+        } else {
+            let names: Vec<_> = training_days.iter().map(|day| day.name.clone()).collect();
+
+            TrainingDay::insert_many(copy.id, &names, copy_owner_id, &mut **tx)
+                .await?
+                .into_iter()
+                .zip(&training_days)
+                .map(|(copy, training_day)| (training_day.id, copy.id))
+                .collect()
+        };
+
+        let movement_id = |id: Uuid| match &movement_ids {
+            Some(movement_ids) => movement_ids.get(&id).copied(),
+            None => Some(id),
+        };
+
+        // sets are selected in order, and keep that order when they are inserted
+        let copies: Vec<_> = sets
+            .into_iter()
+            .map(|set| CreateSet {
+                program_id: copy.id,
+                movement_id: movement_id(set.movement_id).unwrap_or(set.movement_id),
+                week: set.week,
+                day: set.day,
+                training_day_id: set
+                    .training_day_id
+                    .and_then(|id| training_day_ids.get(&id).copied()),
+                reps: set.reps,
+                reps_is_minimum: set.reps_is_minimum,
+                description: set.description,
+                amount: set.amount,
+                percentage_of_max: set.percentage_of_max.and_then(movement_id),
+            })
+            .collect();
+
+        if !copies.is_empty() {
+            CreateSet::insert_many(&copies, copy.id, copy_owner_id, quotas, tx).await?;
+        }
+
+        Ok(Some(copy))
+    }
+}
+
+/// Find or create the movements of `to` that have the names of the movements of `from` which `sets` refer to.
+///
+/// Returns the id of the movement of `to` for each of those movements.
+async fn copy_movements(
+    sets: &[Set],
+    from: OwnerId,
+    to: OwnerId,
+    quotas: &QuotaSettings,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<HashMap<Uuid, Uuid>> {
+    let referenced: HashSet<Uuid> = sets
+        .iter()
+        .flat_map(|set| std::iter::once(set.movement_id).chain(set.percentage_of_max))
+        .collect();
+
+    let mut existing: HashMap<String, Uuid> = Movement::select_all(to, &mut **tx)
+        .await?
+        .into_iter()
+        .map(|movement| (movement.name, movement.id))
+        .collect();
+
+    let movements: Vec<_> = Movement::select_all(from, &mut **tx)
+        .await?
+        .into_iter()
+        .filter(|movement| referenced.contains(&movement.id))
+        .collect();
+
+    let missing: Vec<_> = movements
+        .iter()
+        .filter(|movement| !existing.contains_key(&movement.name))
+        .map(|movement| CreateMovement {
+            name: movement.name.clone(),
+            description: movement.description.clone(),
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        existing.extend(
+            CreateMovement::insert_many(&missing, to, quotas, tx)
+                .await?
+                .into_iter()
+                .map(|movement| (movement.name, movement.id)),
+        );
+    }
+
+    Ok(movements
+        .into_iter()
+        .filter_map(|movement| existing.get(&movement.name).map(|id| (movement.id, *id)))
+        .collect())
+}
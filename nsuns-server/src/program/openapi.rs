@@ -15,12 +15,19 @@ use crate::{
         Customizer,
     },
     router::PROGRAMS_PATH,
+    sets::model::Set,
 };
 
 use super::{
-    handler::ProgramQuery,
-    model::{CreateProgram, ProgramMeta, ProgramSummary, ReorderSets, SetId, UpdateProgram},
+    handler::{DayPath, DayQuery, ProgramQuery, SummaryQuery, WeekPath},
+    model::{
+        AddWeek, CloneProgram, CreateProgram, DaySet, ProgramMeta, ProgramSummary, ProgramWeek,
+        ReorderSets, ReplaceDay, SetId, UpdateProgram,
+    },
     router::REORDER_SETS_PATH,
+    templated::model::{
+        DayTemplate, MovementRef, MovementTemplate, SetTemplate, TemplatedProgram, WeekTemplate,
+    },
 };
 
 pub struct ProgramModule;
@@ -31,6 +38,12 @@ fn program_response() -> Response {
         .build()
 }
 
+fn week_response() -> Response {
+    ResponseBuilder::new()
+        .json_content(ProgramWeek::schema().1)
+        .build()
+}
+
 const TAG: &str = "Programs";
 
 impl Customizer<ComponentsBuilder> for ProgramModule {
@@ -40,6 +53,17 @@ impl Customizer<ComponentsBuilder> for ProgramModule {
             .schema_from::<CreateProgram>()
             .schema_from::<UpdateProgram>()
             .schema_from::<ProgramSummary>()
+            .schema_from::<CloneProgram>()
+            .schema_from::<AddWeek>()
+            .schema_from::<ProgramWeek>()
+            .schema_from::<DaySet>()
+            .schema_from::<ReplaceDay>()
+            .schema_from::<TemplatedProgram>()
+            .schema_from::<WeekTemplate>()
+            .schema_from::<DayTemplate>()
+            .schema_from::<SetTemplate>()
+            .schema_from::<MovementTemplate>()
+            .schema_from::<MovementRef>()
     }
 }
 
@@ -86,6 +110,7 @@ impl Customizer<PathsBuilder> for ProgramModule {
             .parameters(id_path_param(Some(
                 "The id of the program to fetch a summary for",
             )))
+            .parameters(Some(SummaryQuery::into_params(param_in_default)))
             .response(
                 ok(),
                 ResponseBuilder::new()
@@ -110,6 +135,73 @@ impl Customizer<PathsBuilder> for ProgramModule {
             .tag(TAG)
             .build();
 
+        let clone_op = OperationBuilder::new()
+            .description(Some(
+                "Copy a program with its weeks, training days and sets, optionally into another profile.",
+            ))
+            .parameters(id_path_param(Some("The id of the program to copy")))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(CloneProgram::schema().1)
+                    .build(),
+            ))
+            .response(created(), program_response())
+            .tag(TAG)
+            .build();
+
+        let export_op = OperationBuilder::new()
+            .description(Some(
+                "Export every week of a program as a template, which can be imported with `/from-template`.",
+            ))
+            .parameters(id_path_param(Some("The id of the program to export")))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(TemplatedProgram::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let add_week_op = OperationBuilder::new()
+            .parameters(id_path_param(Some(
+                "The id of the program to add a week to",
+            )))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(AddWeek::schema().1)
+                    .build(),
+            ))
+            .response(created(), week_response())
+            .tag(TAG)
+            .build();
+
+        let delete_week_op = OperationBuilder::new()
+            .parameters(Some(WeekPath::into_params(param_in_default)))
+            .response(ok(), week_response())
+            .tag(TAG)
+            .build();
+
+        let replace_day_op = OperationBuilder::new()
+            .description(Some(
+                "Replace the sets of a day with an ordered list, in one step. Sets without an id are created, and sets that are left out are deleted.",
+            ))
+            .parameters(Some(DayPath::into_params(param_in_default)))
+            .parameters(Some(DayQuery::into_params(param_in_default)))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(ReplaceDay::schema().1)
+                    .build(),
+            ))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<Set>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
         builder
             .path(
                 PROGRAMS_PATH,
@@ -132,5 +224,35 @@ impl Customizer<PathsBuilder> for ProgramModule {
                     .operation(PathItemType::Delete, delete_op)
                     .build(),
             )
+            .path(
+                concatcp!(PROGRAMS_PATH, "/{id}/clone"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, clone_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(PROGRAMS_PATH, "/{id}/export"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, export_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(PROGRAMS_PATH, "/{id}/weeks"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, add_week_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(PROGRAMS_PATH, "/{id}/weeks/{week}"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Delete, delete_week_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(PROGRAMS_PATH, "/{id}/days/{day}"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Put, replace_day_op)
+                    .build(),
+            )
     }
 }
@@ -1,17 +1,17 @@
 use axum::{
     extract::FromRef,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
-use crate::{db::Pool, router::State};
+use crate::{db::Pool, quotas::settings::QuotaSettings, router::State};
 
 use super::{
     handler::{
-        create_program, delete_program, profile_programs, program_summary, reorder_sets,
-        update_program,
+        add_week, clone_program, create_program, delete_program, profile_programs, program_summary,
+        remove_week, reorder_sets, replace_day, update_program,
     },
-    templated::handler::create_from_template,
+    templated::handler::{create_from_template, export_program},
 };
 
 pub const REORDER_SETS_PATH: &str = "/reorder-sets";
@@ -19,6 +19,7 @@ pub const REORDER_SETS_PATH: &str = "/reorder-sets";
 pub fn router<S: State>() -> Router<S>
 where
     Pool: FromRef<S>,
+    QuotaSettings: FromRef<S>,
 {
     Router::new()
         .route(
@@ -30,4 +31,9 @@ where
         .route(REORDER_SETS_PATH, post(reorder_sets))
         .route("/from-template", post(create_from_template))
         .route("/:id", get(program_summary).delete(delete_program))
+        .route("/:id/clone", post(clone_program))
+        .route("/:id/export", get(export_program))
+        .route("/:id/weeks", post(add_week))
+        .route("/:id/weeks/:week", delete(remove_week))
+        .route("/:id/days/:day", put(replace_day))
 }
@@ -1,19 +1,26 @@
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use uuid::Uuid;
 
 use crate::{
     auth::token::OwnerId,
     db::{commit_ok, Pool},
     error::extract::WithErrorRejection,
-    response_transforms::created,
+    quotas::settings::QuotaSettings,
+    response_transforms::{created, or_404},
     transaction,
     validation::Validated,
 };
 
-use super::model::TemplatedProgram;
+use super::model::{export, TemplatedProgram};
 
 #[tracing::instrument(skip_all)]
 pub async fn create_from_template(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     WithErrorRejection(Json(validated_template)): WithErrorRejection<
         Json<Validated<TemplatedProgram>>,
@@ -21,10 +28,23 @@ pub async fn create_from_template(
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
     let res = validated_template
-        .insert(owner_id, &mut tx)
+        .insert(owner_id, &quotas, &mut tx)
         .await
         .map(Json)
         .map(created);
 
     commit_ok(res, tx).await
 }
+
+#[tracing::instrument(skip_all)]
+pub async fn export_program(
+    State(pool): State<Pool>,
+    Path(id): Path<Uuid>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = export(id, owner_id, &mut tx)
+        .await
+        .map(or_404::<_, Json<_>>);
+    commit_ok(res, tx).await
+}
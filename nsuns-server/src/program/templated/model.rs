@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use const_format::formatcp;
 use serde::{Deserialize, Serialize};
 use sqlx::Transaction;
 use utoipa::ToSchema;
@@ -8,9 +11,16 @@ use crate::{
     auth::token::OwnerId,
     db::DB,
     error::OperationResult,
-    movements::model::CreateMovement,
-    program::model::{CreateProgram, ProgramMeta},
-    sets::model::{CreateSet, Day},
+    grants::model::Access,
+    movements::model::{CreateMovement, Movement},
+    profiles::model::Profile,
+    program::model::{
+        append_weeks, gather_program_summary, CreateProgram, Program, ProgramMeta, ProgramSummary,
+        MAX_WEEKS,
+    },
+    quotas::settings::QuotaSettings,
+    sets::model::{CreateSet, Day, Set},
+    training_days::model::TrainingDay,
     validation::Validated,
 };
 
@@ -28,11 +38,21 @@ pub struct SetTemplate {
     pub amount: f64,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default, ToSchema)]
 pub struct DayTemplate {
     pub sets: Vec<SetTemplate>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekTemplate {
+    #[serde(default)]
+    pub days: [DayTemplate; 7],
+    /// The sets of each of the program's training days, in the same order as the training days
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub training_days: Vec<DayTemplate>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, ToSchema)]
 pub struct MovementRef {
     pub id: Uuid,
@@ -47,14 +67,52 @@ pub enum MovementTemplate {
     New(CreateMovement),
 }
 
+/// The newest version of the template format, which is the one that exported programs are written in
+pub const TEMPLATE_VERSION: u32 = 1;
+
+fn first_version() -> u32 {
+    1
+}
+
+/// A program to create, with either `days` for a single-week program, or `weeks`.
+///
+/// Programs that rotate through training days instead of weekdays name them in `trainingDays`, and give their sets
+/// in each week.
 #[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct TemplatedProgram {
+    /// The version of the format the template is written in. Templates without one are version 1.
+    #[serde(default = "first_version")]
+    version: u32,
     name: String,
-    owner: Uuid,
-    days: [DayTemplate; 7],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// The profile to create the program in. Required when creating a program.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    training_days: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    days: Option<[DayTemplate; 7]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weeks: Option<Vec<WeekTemplate>>,
     movements: Vec<MovementTemplate>,
 }
 
+fn validate_days(days: &[DayTemplate], movements_len: usize) -> Vec<Result<(), ValidationErrors>> {
+    days.iter()
+        .map(|day| {
+            let sets_errs: Vec<_> = day
+                .sets
+                .iter()
+                .map(|set| validate_set_template(set, movements_len))
+                .collect();
+
+            ValidationErrors::merge_all(Ok(()), "sets", sets_errs)
+        })
+        .collect()
+}
+
 fn validate_set_template(set: &SetTemplate, movements_len: usize) -> Result<(), ValidationErrors> {
     let mut set_errs = set.validate().err().unwrap_or_default();
 
@@ -86,20 +144,99 @@ impl Validate for TemplatedProgram {
     fn validate(&self) -> Result<(), validator::ValidationErrors> {
         let movements_len = self.movements.len();
 
-        let days_errs: Vec<_> = self
+        let mut errs = ValidationErrors::new();
+
+        if self.version > TEMPLATE_VERSION {
+            errs.add(
+                "version",
+                ValidationError::new(formatcp!(
+                    "templates newer than version {TEMPLATE_VERSION} are not supported"
+                )),
+            );
+        }
+
+        if self.owner.is_none() {
+            errs.add("owner", ValidationError::new("owner must be provided"));
+        }
+
+        if self.description.as_ref().is_some_and(String::is_empty) {
+            errs.add(
+                "description",
+                ValidationError::new("description must not be empty"),
+            );
+        }
+
+        match (&self.days, &self.weeks) {
+            (Some(_), Some(_)) => errs.add(
+                "weeks",
+                ValidationError::new("only one of days or weeks can be provided"),
+            ),
+            (None, None) => errs.add(
+                "weeks",
+                ValidationError::new("one of days or weeks must be provided"),
+            ),
+            (None, Some(weeks)) if weeks.is_empty() || weeks.len() > MAX_WEEKS as usize => errs
+                .add(
+                    "weeks",
+                    ValidationError::new(formatcp!(
+                        "weeks must have between 1 and {MAX_WEEKS} items"
+                    )),
+                ),
+            _ => {}
+        }
+
+        if self.training_days.iter().any(String::is_empty) {
+            errs.add(
+                "trainingDays",
+                ValidationError::new("training day names must not be empty"),
+            );
+        }
+
+        if self
+            .weeks
+            .iter()
+            .flatten()
+            .any(|week| week.training_days.len() > self.training_days.len())
+        {
+            errs.add(
+                "weeks",
+                ValidationError::new(
+                    "weeks cannot have sets for more training days than the program has",
+                ),
+            );
+        }
+
+        let base = if errs.is_empty() { Ok(()) } else { Err(errs) };
+
+        let days_errs = self
             .days
+            .as_ref()
+            .map(|days| validate_days(days, movements_len))
+            .unwrap_or_default();
+
+        let base = ValidationErrors::merge_all(base, "days", days_errs);
+
+        let weeks_errs: Vec<_> = self
+            .weeks
             .iter()
-            .map(|day| {
-                let sets_errs: Vec<_> = day
-                    .sets
-                    .iter()
-                    .map(|set| validate_set_template(set, movements_len))
-                    .collect();
+            .flatten()
+            .map(|week| {
+                let days = ValidationErrors::merge_all(
+                    Ok(()),
+                    "days",
+                    validate_days(&week.days, movements_len),
+                );
 
-                ValidationErrors::merge_all(Ok(()), "sets", sets_errs)
+                ValidationErrors::merge_all(
+                    days,
+                    "trainingDays",
+                    validate_days(&week.training_days, movements_len),
+                )
             })
             .collect();
 
+        let base = ValidationErrors::merge_all(base, "weeks", weeks_errs);
+
         let movements_errs: Vec<_> = self
             .movements
             .iter()
@@ -109,9 +246,7 @@ impl Validate for TemplatedProgram {
             })
             .collect();
 
-        let base = ValidationErrors::merge_all(Ok(()), "movements", movements_errs);
-
-        ValidationErrors::merge_all(base, "days", days_errs)
+        ValidationErrors::merge_all(base, "movements", movements_errs)
     }
 }
 
@@ -119,11 +254,36 @@ impl Validated<TemplatedProgram> {
     pub async fn insert(
         self,
         owner_id: OwnerId,
+        quotas: &QuotaSettings,
         tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<ProgramMeta> {
-        let template = self.into_inner();
+        let mut template = self.into_inner();
+
+        let weeks: Vec<WeekTemplate> = match (template.days.take(), template.weeks.take()) {
+            (Some(days), _) => vec![WeekTemplate {
+                days,
+                training_days: vec![],
+            }],
+            (None, weeks) => weeks.unwrap_or_default(),
+        };
+
+        // the owner was checked to be present by the `Validate` impl
+        let profile_id = template.owner.unwrap_or_default();
+
+        // Everything is created for the owner of the profile, which may have been shared with the requester.
+        // Other than that, we don't need to assert any ownership, since we are deferring resource creation to
+        // respective models.
+        let owner_id =
+            Profile::assert_owner(profile_id, owner_id, Access::Write, &mut **tx).await?;
+
+        // New movements are matched to the owner's movements by name, ignoring case, so that a template can be used
+        // more than once, and by owners who already have the movements.
+        let existing_movements: HashMap<String, Uuid> = Movement::select_all(owner_id, &mut **tx)
+            .await?
+            .into_iter()
+            .map(|movement| (movement.name.to_lowercase(), movement.id))
+            .collect();
 
-        // We don't need to assert any ownership, since we are deferring resource creation to respective models.
         let mut movement_ids: Vec<_> = (0..template.movements.len()).map(|_| Uuid::nil()).collect();
 
         let mut new_movement_indexes: Vec<usize> = Vec::new();
@@ -135,14 +295,19 @@ impl Validated<TemplatedProgram> {
                     movement_ids[index] = id;
                 }
                 MovementTemplate::New(movement) => {
-                    movements_to_create.push(movement);
-                    new_movement_indexes.push(index);
+                    match existing_movements.get(&movement.name.to_lowercase()) {
+                        Some(id) => movement_ids[index] = *id,
+                        None => {
+                            movements_to_create.push(movement);
+                            new_movement_indexes.push(index);
+                        }
+                    }
                 }
             }
         }
 
         let new_movements =
-            CreateMovement::insert_many(&movements_to_create, owner_id, &mut **tx).await?;
+            CreateMovement::insert_many(&movements_to_create, owner_id, quotas, tx).await?;
 
         debug_assert_eq!(
             new_movement_indexes.len(),
@@ -156,41 +321,227 @@ impl Validated<TemplatedProgram> {
             movement_ids[index] = movement.id;
         }
 
-        // create the program - this checks ownership of the profile
         let program_meta = CreateProgram {
-            description: None,
+            description: template.description,
             name: template.name,
-            owner: template.owner,
+            owner: profile_id,
         }
-        .insert_one(owner_id, tx)
+        .insert_one(owner_id, quotas, tx)
         .await?;
 
-        let sets_to_create: Vec<CreateSet> = template
-            .days
+        // the program starts with one week, validated to be no more than `MAX_WEEKS` in total
+        if weeks.len() > 1 {
+            append_weeks(program_meta.id, weeks.len() as i16 - 1, owner_id, &mut **tx).await?;
+        }
+
+        let training_day_ids: Vec<_> = if template.training_days.is_empty() {
+            vec![]
+        } else {
+            TrainingDay::insert_many(
+                program_meta.id,
+                &template.training_days,
+                owner_id,
+                &mut **tx,
+            )
+            .await?
             .into_iter()
-            .zip(0..7_i16)
-            .flat_map(|(day_template, index)| {
-                // SAFETY: `self.days` is an array of length 7, so `index` can never be more than 6.
-                let day: Day = unsafe { Day::from_i16_unchecked(index) };
-                let movement_ids = &movement_ids;
-
-                day_template.sets.into_iter().map(move |set| {
-                    CreateSet {
-                        amount: set.amount,
-                        day,
-                        description: set.description,
-                        movement_id: movement_ids[set.movement_index], // validated by `Validate` impl
-                        percentage_of_max: set.percentage_of_max_index.map(|idx| movement_ids[idx]), // validated by `Validate` impl
-                        program_id: program_meta.id,
+            .map(|training_day| training_day.id)
+            .collect()
+        };
+
+        let mut sets_to_create: Vec<CreateSet> = vec![];
+
+        for (week, week_template) in (0_i16..).zip(weeks) {
+            // SAFETY: each week is an array of length 7, so the index can never be more than 6.
+            let days = (0..7_i16)
+                .map(|index| unsafe { Day::from_i16_unchecked(index) })
+                .zip(week_template.days)
+                .map(|(day, day_template)| (Some(day), None, day_template));
+
+            // the number of training days in each week was checked by the `Validate` impl
+            let training_days = training_day_ids
+                .iter()
+                .zip(week_template.training_days)
+                .map(|(id, day_template)| (None, Some(*id), day_template));
+
+            for (day, training_day_id, day_template) in days.chain(training_days) {
+                sets_to_create.extend(day_template.sets.into_iter().map(|set| CreateSet {
+                    amount: set.amount,
+                    week,
+                    day,
+                    training_day_id,
+                    description: set.description,
+                    movement_id: movement_ids[set.movement_index], // validated by `Validate` impl
+                    percentage_of_max: set.percentage_of_max_index.map(|idx| movement_ids[idx]), // validated by `Validate` impl
+                    program_id: program_meta.id,
+                    reps: set.reps,
+                    reps_is_minimum: set.reps_is_minimum,
+                }));
+            }
+        }
+
+        CreateSet::insert_many(&sets_to_create, program_meta.id, owner_id, quotas, tx).await?;
+
+        Ok(program_meta)
+    }
+}
+
+impl TemplatedProgram {
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// How many weeks the program has
+    #[must_use]
+    pub fn weeks(&self) -> usize {
+        self.weeks.as_ref().map_or(1, Vec::len)
+    }
+
+    /// How many weekdays and training days have sets in the first week
+    #[must_use]
+    pub fn days_per_week(&self) -> usize {
+        let (days, training_days) = match (&self.days, &self.weeks) {
+            (Some(days), _) => (days.as_slice(), [].as_slice()),
+            (None, Some(weeks)) => weeks
+                .first()
+                .map_or(([].as_slice(), [].as_slice()), |week| {
+                    (week.days.as_slice(), week.training_days.as_slice())
+                }),
+            (None, None) => ([].as_slice(), [].as_slice()),
+        };
+
+        days.iter()
+            .chain(training_days)
+            .filter(|day| !day.sets.is_empty())
+            .count()
+    }
+
+    /// Build a template from the summaries of every week of a program, in order.
+    ///
+    /// The movements that sets refer to become new movements of the template, so that it can be imported by any
+    /// owner. Sets of movements that are not in `movements` are left out. The template does not name a profile to
+    /// create the program in, since the importer has to choose one anyway.
+    #[must_use]
+    pub fn from_summaries(
+        program: ProgramMeta,
+        summaries: Vec<ProgramSummary>,
+        movements: &[Movement],
+    ) -> Self {
+        let movements_by_id: HashMap<Uuid, &Movement> = movements
+            .iter()
+            .map(|movement| (movement.id, movement))
+            .collect();
+
+        let mut movement_indexes: HashMap<Uuid, usize> = HashMap::new();
+        let mut template_movements: Vec<MovementTemplate> = Vec::new();
+
+        let mut movement_index = |id: Uuid| -> Option<usize> {
+            if let Some(index) = movement_indexes.get(&id) {
+                return Some(*index);
+            }
+
+            let movement = movements_by_id.get(&id)?;
+            template_movements.push(MovementTemplate::New(CreateMovement {
+                name: movement.name.clone(),
+                description: movement.description.clone(),
+            }));
+            movement_indexes.insert(id, template_movements.len() - 1);
+            Some(template_movements.len() - 1)
+        };
+
+        let mut day_template = |sets: Vec<Set>| DayTemplate {
+            sets: sets
+                .into_iter()
+                .filter_map(|set| {
+                    Some(SetTemplate {
+                        movement_index: movement_index(set.movement_id)?,
+                        percentage_of_max_index: set
+                            .percentage_of_max
+                            .and_then(&mut movement_index),
                         reps: set.reps,
                         reps_is_minimum: set.reps_is_minimum,
-                    }
+                        description: set.description,
+                        amount: set.amount,
+                    })
                 })
+                .collect(),
+        };
+
+        // every week has the same training days
+        let training_days = summaries
+            .first()
+            .map(|summary| {
+                summary
+                    .training_days
+                    .iter()
+                    .map(|training_day| training_day.training_day.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let weeks = summaries
+            .into_iter()
+            .map(|summary| WeekTemplate {
+                days: [
+                    summary.sets_sunday,
+                    summary.sets_monday,
+                    summary.sets_tuesday,
+                    summary.sets_wednesday,
+                    summary.sets_thursday,
+                    summary.sets_friday,
+                    summary.sets_saturday,
+                ]
+                .map(&mut day_template),
+                training_days: summary
+                    .training_days
+                    .into_iter()
+                    .map(|training_day| day_template(training_day.sets))
+                    .collect(),
             })
             .collect();
 
-        CreateSet::insert_many(&sets_to_create, program_meta.id, owner_id, tx).await?;
+        TemplatedProgram {
+            version: TEMPLATE_VERSION,
+            name: program.name,
+            description: program.description,
+            owner: None,
+            training_days,
+            days: None,
+            weeks: Some(weeks),
+            movements: template_movements,
+        }
+    }
+}
 
-        Ok(program_meta)
+/// Export every week of a program as a template
+pub async fn export(
+    program_id: Uuid,
+    owner_id: OwnerId,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<Option<TemplatedProgram>> {
+    let Some(program) = Program::select_one(program_id, owner_id, &mut **tx).await? else {
+        return Ok(None);
+    };
+
+    let mut summaries = Vec::new();
+    let mut week = 0;
+
+    while let Some(summary) = gather_program_summary(program_id, week, owner_id, tx).await? {
+        summaries.push(summary);
+        week += 1;
     }
+
+    let movements = Movement::select_all(program.owner_id, &mut **tx).await?;
+
+    Ok(Some(TemplatedProgram::from_summaries(
+        program.into(),
+        summaries,
+        &movements,
+    )))
 }
@@ -0,0 +1,136 @@
+//! Per-owner limits on how much data can be stored.
+//!
+//! Quotas are checked by the model methods that insert rows, so every way of creating data is covered, including
+//! templates and restoring an export. Rows created through a shared profile count against the profile's owner.
+
+pub mod settings;
+
+use anyhow::{anyhow, Context};
+use const_format::formatcp;
+use http::StatusCode;
+use sqlx::Transaction;
+use thiserror::Error;
+
+use crate::{
+    auth::token::OwnerId,
+    db::{
+        tracing::{statements::SELECT, InstrumentExecutor},
+        DB,
+    },
+    db_span,
+    error::{ErrorWithStatus, OperationResult},
+    into_log_server_error,
+};
+
+use self::settings::QuotaSettings;
+
+/// Something that is limited by a quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Profiles,
+    Programs,
+    Sets,
+    Movements,
+    Maxes,
+    Reps,
+}
+
+impl Resource {
+    fn table(self) -> &'static str {
+        match self {
+            Resource::Profiles => "profiles",
+            Resource::Programs => "programs",
+            Resource::Sets => "program_sets",
+            Resource::Movements => "movements",
+            Resource::Maxes => "maxes",
+            Resource::Reps => "reps",
+        }
+    }
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Resource::Profiles => "profiles",
+            Resource::Programs => "programs",
+            Resource::Sets => "sets",
+            Resource::Movements => "movements",
+            Resource::Maxes => "maxes",
+            Resource::Reps => "rep records",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error(
+    "{} owners can have at most {limit} {}{}",
+    if *.registered { "Registered" } else { "Anonymous" },
+    .resource.name(),
+    if *.registered { "" } else { ", register to raise the limit" }
+)]
+pub struct QuotaExceeded {
+    pub resource: Resource,
+    pub registered: bool,
+    pub limit: u32,
+}
+
+/// Check that an owner can create `adding` more rows of a resource.
+///
+/// The owner row stays locked until the transaction ends, so concurrent requests from the same owner are checked one
+/// after another, and each one counts the rows inserted by the others.
+///
+/// Fails with a 403 (forbidden) if that would take the owner over their quota.
+pub async fn enforce(
+    resource: Resource,
+    adding: usize,
+    owner_id: OwnerId,
+    quotas: &QuotaSettings,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<()> {
+    let table = resource.table();
+
+    // NO KEY UPDATE still serializes quota checks, without conflicting with the locks taken by foreign keys
+    let registered = sqlx::query_as::<_, (bool,)>(formatcp!(
+        "{SELECT} o.expiry_date IS NULL FROM owners o WHERE o.id = $1 FOR NO KEY UPDATE"
+    ))
+    .bind(owner_id)
+    .fetch_optional((&mut **tx).instrument_executor(db_span!(SELECT, "owners")))
+    .await
+    .context("failed to lock owner for quota")
+    .map_err(into_log_server_error!())?;
+
+    // a missing owner is rejected by the insert itself
+    let Some((registered,)) = registered else {
+        return Ok(());
+    };
+
+    // counted in a separate statement, to see rows committed while waiting for the lock
+    let (count,) = sqlx::query_as::<_, (i64,)>(&format!(
+        "{SELECT} count(*) FROM {table} WHERE owner_id = $1"
+    ))
+    .bind(owner_id)
+    .fetch_one((&mut **tx).instrument_executor(db_span!("count quota usage")))
+    .await
+    .with_context(|| format!("failed to count {table} for quota"))
+    .map_err(into_log_server_error!())?;
+
+    let limit = quotas.limit(resource, registered);
+
+    if count.saturating_add(adding as i64) > i64::from(limit) {
+        metrics::increment_counter!(
+            "quotas.exceeded",
+            "resource" => resource.name(),
+            "tier" => if registered { "registered" } else { "anonymous" }
+        );
+
+        return Err(ErrorWithStatus::new(
+            StatusCode::FORBIDDEN,
+            anyhow!(QuotaExceeded {
+                resource,
+                registered,
+                limit,
+            }),
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use serde::Deserialize;
+
+use super::Resource;
+
+fn default_anonymous_profiles() -> u32 {
+    3
+}
+
+fn default_anonymous_programs() -> u32 {
+    10
+}
+
+fn default_anonymous_sets() -> u32 {
+    500
+}
+
+fn default_anonymous_movements() -> u32 {
+    50
+}
+
+fn default_anonymous_maxes() -> u32 {
+    2_000
+}
+
+fn default_anonymous_reps() -> u32 {
+    2_000
+}
+
+fn default_registered_profiles() -> u32 {
+    50
+}
+
+fn default_registered_programs() -> u32 {
+    200
+}
+
+fn default_registered_sets() -> u32 {
+    10_000
+}
+
+fn default_registered_movements() -> u32 {
+    500
+}
+
+fn default_registered_maxes() -> u32 {
+    100_000
+}
+
+fn default_registered_reps() -> u32 {
+    100_000
+}
+
+/// The most rows of each kind a single owner can have.
+///
+/// Anonymous owners get their own, lower limits, since anyone can create one.
+#[derive(Debug, Clone, Deserialize)]
+pub struct QuotaSettings {
+    #[serde(default = "default_anonymous_profiles")]
+    pub anonymous_profiles: u32,
+    #[serde(default = "default_anonymous_programs")]
+    pub anonymous_programs: u32,
+    #[serde(default = "default_anonymous_sets")]
+    pub anonymous_sets: u32,
+    #[serde(default = "default_anonymous_movements")]
+    pub anonymous_movements: u32,
+    #[serde(default = "default_anonymous_maxes")]
+    pub anonymous_maxes: u32,
+    #[serde(default = "default_anonymous_reps")]
+    pub anonymous_reps: u32,
+    #[serde(default = "default_registered_profiles")]
+    pub registered_profiles: u32,
+    #[serde(default = "default_registered_programs")]
+    pub registered_programs: u32,
+    #[serde(default = "default_registered_sets")]
+    pub registered_sets: u32,
+    #[serde(default = "default_registered_movements")]
+    pub registered_movements: u32,
+    #[serde(default = "default_registered_maxes")]
+    pub registered_maxes: u32,
+    #[serde(default = "default_registered_reps")]
+    pub registered_reps: u32,
+}
+
+impl Default for QuotaSettings {
+    fn default() -> Self {
+        Self {
+            anonymous_profiles: default_anonymous_profiles(),
+            anonymous_programs: default_anonymous_programs(),
+            anonymous_sets: default_anonymous_sets(),
+            anonymous_movements: default_anonymous_movements(),
+            anonymous_maxes: default_anonymous_maxes(),
+            anonymous_reps: default_anonymous_reps(),
+            registered_profiles: default_registered_profiles(),
+            registered_programs: default_registered_programs(),
+            registered_sets: default_registered_sets(),
+            registered_movements: default_registered_movements(),
+            registered_maxes: default_registered_maxes(),
+            registered_reps: default_registered_reps(),
+        }
+    }
+}
+
+impl QuotaSettings {
+    #[must_use]
+    pub fn limit(&self, resource: Resource, registered: bool) -> u32 {
+        match (resource, registered) {
+            (Resource::Profiles, false) => self.anonymous_profiles,
+            (Resource::Programs, false) => self.anonymous_programs,
+            (Resource::Sets, false) => self.anonymous_sets,
+            (Resource::Movements, false) => self.anonymous_movements,
+            (Resource::Maxes, false) => self.anonymous_maxes,
+            (Resource::Reps, false) => self.anonymous_reps,
+            (Resource::Profiles, true) => self.registered_profiles,
+            (Resource::Programs, true) => self.registered_programs,
+            (Resource::Sets, true) => self.registered_sets,
+            (Resource::Movements, true) => self.registered_movements,
+            (Resource::Maxes, true) => self.registered_maxes,
+            (Resource::Reps, true) => self.registered_reps,
+        }
+    }
+}
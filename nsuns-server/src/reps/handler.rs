@@ -12,6 +12,7 @@ use crate::{
     auth::token::OwnerId,
     db::{commit_ok, Pool},
     error::extract::WithErrorRejection,
+    quotas::settings::QuotaSettings,
     response_transforms::{created, or_404},
     transaction,
     validation::ValidatedJson,
@@ -41,12 +42,13 @@ pub async fn reps_index(
 #[tracing::instrument(skip_all)]
 pub async fn create_reps(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     ValidatedJson(reps): ValidatedJson<CreateReps>,
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
     let res = reps
-        .insert_one(owner_id, &mut tx)
+        .insert_one(owner_id, &quotas, &mut tx)
         .await
         .map(Json)
         .map(created);
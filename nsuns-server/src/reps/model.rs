@@ -23,9 +23,11 @@ use crate::{
     },
     db_span,
     error::{ErrorWithStatus, OperationResult},
-    into_log_server_error, log_server_error,
+    grants::model::Access,
+    has_access, into_log_server_error, log_server_error,
     movements::model::Movement,
     profiles::model::Profile,
+    quotas::{self, settings::QuotaSettings, Resource},
 };
 
 const TABLE: &str = "reps";
@@ -45,7 +47,7 @@ where
 }
 
 #[serde_as]
-#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow, ToSchema, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow, Validate, ToSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Reps {
     #[schema(value_type = String, format = Int64)]
@@ -53,6 +55,7 @@ pub struct Reps {
     pub id: i64,
     pub profile_id: Uuid,
     pub movement_id: Uuid,
+    #[validate(range(min = 0))]
     pub amount: Option<i32>,
     #[schema(value_type = i64)]
     #[serde(with = "ts_milliseconds")]
@@ -66,7 +69,8 @@ impl Reps {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Vec<Self>> {
         sqlx::query_as::<_, Self>(formatcp!(
-            "{SELECT} * FROM {TABLE} WHERE profile_id = $1 AND owner_id = $2 ORDER BY timestamp"
+            "{SELECT} * FROM {TABLE} WHERE profile_id = $1 AND {} ORDER BY timestamp",
+            has_access!(Read, "profile_id", "$2")
         ))
         .bind(profile_id)
         .bind(owner_id)
@@ -76,6 +80,59 @@ impl Reps {
         .map_err(into_log_server_error!())
     }
 
+    pub async fn select_all(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} * FROM {TABLE} WHERE owner_id = $1 ORDER BY timestamp"
+        ))
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select all reps")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Insert existing records, keeping their timestamps.
+    ///
+    /// The ids are ignored, and new ones are generated.
+    pub async fn insert_many(
+        records: &[Self],
+        owner_id: OwnerId,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<()> {
+        quotas::enforce(Resource::Reps, records.len(), owner_id, quotas, tx).await?;
+
+        // sqlx does not yet support providing iterators for bound data.
+        let profile_ids: Vec<_> = records.iter().map(|r| r.profile_id).collect();
+        let movement_ids: Vec<_> = records.iter().map(|r| r.movement_id).collect();
+        let amounts: Vec<_> = records.iter().map(|r| r.amount).collect();
+        let timestamps: Vec<_> = records.iter().map(|r| r.timestamp).collect();
+
+        sqlx::query(formatcp!(
+            "{INSERT_INTO} {TABLE} (profile_id, movement_id, amount, timestamp, owner_id)
+            VALUES (
+                unnest($1),
+                unnest($2),
+                unnest($3),
+                unnest($4),
+                $5
+            )"
+        ))
+        .bind(&profile_ids)
+        .bind(&movement_ids)
+        .bind(&amounts)
+        .bind(&timestamps)
+        .bind(owner_id)
+        .execute((&mut **tx).instrument_executor(db_span!(INSERT_INTO, TABLE)))
+        .await
+        .map_err(|e| handle_error(e, || "failed to insert reps"))
+        .map_err(log_server_error!())
+        .map(|_| ())
+    }
+
     pub async fn select_latest(
         movement_id: Uuid,
         profile_id: Uuid,
@@ -83,7 +140,8 @@ impl Reps {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Option<Self>> {
         sqlx::query_as::<_, Self>(formatcp!(
-                "{SELECT} * FROM {TABLE} WHERE movement_id = $1 AND profile_id = $2 AND owner_id = $3 ORDER BY timestamp DESC LIMIT 1"
+                "{SELECT} * FROM {TABLE} WHERE movement_id = $1 AND profile_id = $2 AND {} ORDER BY timestamp DESC LIMIT 1",
+                has_access!(Read, "profile_id", "$3")
             ))
             .bind(movement_id)
             .bind(profile_id)
@@ -108,9 +166,13 @@ impl CreateReps {
     pub async fn insert_one(
         self,
         owner_id: OwnerId,
+        quotas: &QuotaSettings,
         tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<Reps> {
-        Profile::assert_owner(self.profile_id, owner_id, &mut **tx).await?;
+        let owner_id =
+            Profile::assert_owner(self.profile_id, owner_id, Access::Write, &mut **tx).await?;
+        quotas::enforce(Resource::Reps, 1, owner_id, quotas, tx).await?;
+
         Movement::assert_owner(self.movement_id, owner_id, &mut **tx).await?;
 
         sqlx::query_as::<_, (i64, NaiveDateTime)>(formatcp!(
@@ -152,7 +214,8 @@ impl UpdateReps {
         executor: impl Executor<'_, Database = DB>,
     ) -> OperationResult<Option<Reps>> {
         sqlx::query_as::<_, Reps>(formatcp!(
-            "{UPDATE} {TABLE} SET amount = $1 WHERE id = $2 AND owner_id = $3 RETURNING *"
+            "{UPDATE} {TABLE} SET amount = $1 WHERE id = $2 AND {} RETURNING *",
+            has_access!(Write, "profile_id", "$3")
         ))
         .bind(self.amount)
         .bind(self.id)
@@ -176,8 +239,9 @@ pub async fn delete_latest_reps(
 ) -> OperationResult<Option<i64>> {
     sqlx::query_as::<_, (i64,)>(formatcp!(
         "{DELETE_FROM} {TABLE} WHERE id = any(
-            array(SELECT id FROM {TABLE} WHERE movement_id = $1 AND profile_id = $2 AND owner_id = $3 ORDER BY timestamp DESC LIMIT 1)
-        ) RETURNING id"
+            array(SELECT id FROM {TABLE} WHERE movement_id = $1 AND profile_id = $2 AND {} ORDER BY timestamp DESC LIMIT 1)
+        ) RETURNING id",
+        has_access!(Write, "profile_id", "$3")
     ))
     .bind(movement_id)
     .bind(profile_id)
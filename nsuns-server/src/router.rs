@@ -14,20 +14,28 @@ use tower_http::{
 };
 
 use crate::{
+    admin,
     auth::{
         self,
+        csrf::protect_csrf,
         middleware::{manage_tokens, redirect_on_missing_auth_cookie},
-        token::JwtKeys,
+        oidc::WithOidc,
+        settings::TwoFactorSettings,
+        throttle::LoginThrottle,
+        token::{JwtKeys, SessionLifetimes},
     },
     db::Pool,
     error::middleware::json_errors,
+    grants,
     health::health_check,
     maxes, movements,
     observability::{metrics::middleware::WithMetrics, tracing::middleware::WithTracing},
     openapi::WithOpenApi,
-    profiles, program, reps, sets,
+    profiles, program,
+    quotas::settings::QuotaSettings,
+    reps, sets,
     settings::Settings,
-    updates,
+    templates, training_days, updates,
 };
 
 pub const PROFILES_PATH: &str = "/api/profiles";
@@ -39,6 +47,11 @@ pub const REPS_PATH: &str = "/api/reps";
 pub const UPDATES_PATH: &str = "/api/updates";
 pub const HEALTH_PATH: &str = "/actuator/health";
 pub const AUTH_PATH: &str = "/api/auth";
+pub const OIDC_PATH: &str = "/api/auth/oidc";
+pub const ADMIN_PATH: &str = "/api/admin";
+pub const GRANTS_PATH: &str = "/api/grants";
+pub const TRAINING_DAYS_PATH: &str = "/api/training-days";
+pub const TEMPLATES_PATH: &str = "/api/templates";
 
 trait StaticFiles<P> {
     fn static_files(self, static_dir: Option<P>) -> Self;
@@ -76,6 +89,10 @@ where
 pub struct AppState {
     pub pool: Pool,
     pub keys: JwtKeys,
+    pub lifetimes: SessionLifetimes,
+    pub login_throttle: LoginThrottle,
+    pub two_factor: TwoFactorSettings,
+    pub quotas: QuotaSettings,
 }
 
 pub trait State: Clone + Send + Sync + 'static {}
@@ -91,9 +108,15 @@ pub fn router(state: AppState, settings: &Settings) -> anyhow::Result<Router> {
         .nest(MAXES_PATH, maxes::router())
         .nest(REPS_PATH, reps::router())
         .nest(UPDATES_PATH, updates::router())
+        .nest(GRANTS_PATH, grants::router())
+        .nest(TRAINING_DAYS_PATH, training_days::router())
+        .nest(TEMPLATES_PATH, templates::router())
         .nest(AUTH_PATH, auth::router())
+        .nest(ADMIN_PATH, admin::router())
+        .with_oidc(&state, &settings.auth.oidc)
         .with_state(state.clone())
         .route_layer(from_fn_with_state(state.clone(), manage_tokens))
+        .route_layer(from_fn_with_state(state.clone(), protect_csrf))
         .with_openapi(&settings.openapi)
         .static_files(settings.server.static_dir.as_ref())
         .layer(CookieManagerLayer::new())
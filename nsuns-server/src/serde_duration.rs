@@ -1,4 +1,6 @@
-//! Deserialize a `std::time::Duration` from a string like "1ms", "1s", "1us", "1ns"
+//! Deserialize a `std::time::Duration` from a string like "1ms", "1s", "1us", "1ns", "1m", "1h", "1d".
+//!
+//! Durations too long to represent are rejected like any other invalid string.
 
 use std::time::Duration;
 
@@ -35,6 +37,27 @@ fn duration_from_str(s: &str) -> Option<Duration> {
             .parse::<u64>()
             .map(Duration::from_secs)
             .ok()
+    } else if t.ends_with('m') {
+        t[0..t.len() - 1]
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .and_then(|m| m.checked_mul(60))
+            .map(Duration::from_secs)
+    } else if t.ends_with('h') {
+        t[0..t.len() - 1]
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .and_then(|h| h.checked_mul(60 * 60))
+            .map(Duration::from_secs)
+    } else if t.ends_with('d') {
+        t[0..t.len() - 1]
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .and_then(|d| d.checked_mul(60 * 60 * 24))
+            .map(Duration::from_secs)
     } else {
         None
     }
@@ -89,6 +112,23 @@ mod tests {
         assert_eq!(Some(Duration::from_secs(10)), duration_from_str(" 10 s "));
     }
 
+    #[test]
+    fn minutes_hours_days() {
+        assert_eq!(Some(Duration::from_secs(600)), duration_from_str("10m"));
+        assert_eq!(Some(Duration::from_secs(36000)), duration_from_str("10 h"));
+        assert_eq!(
+            Some(Duration::from_secs(864000)),
+            duration_from_str(" 10 d ")
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        assert_eq!(None, duration_from_str(&format!("{}m", u64::MAX)));
+        assert_eq!(None, duration_from_str(&format!("{}h", u64::MAX / 60)));
+        assert_eq!(None, duration_from_str(&format!("{}d", u64::MAX / 60)));
+    }
+
     #[test]
     fn none() {
         assert_eq!(None, duration_from_str("10"));
@@ -5,6 +5,7 @@ use axum::{extract::connect_info::Connected, serve::IncomingStream, Router};
 use tokio::net::TcpListener;
 
 use crate::{
+    auth::token::JwtKeys,
     db, log_error,
     router::{router, AppState},
     settings::Settings,
@@ -19,10 +20,18 @@ pub async fn initialize(settings: &Settings) -> anyhow::Result<Router> {
         .await
         .map_err(log_error!())?;
 
+    let keys = JwtKeys::new(&settings.auth)
+        .context("failed to load JWT keys")
+        .map_err(log_error!())?;
+
     router(
         AppState {
             pool,
-            keys: (&settings.auth).into(),
+            keys,
+            lifetimes: (&settings.auth).into(),
+            login_throttle: (&settings.auth).into(),
+            two_factor: settings.auth.two_factor.clone(),
+            quotas: settings.quotas.clone(),
         },
         settings,
     )
@@ -43,12 +52,11 @@ impl Connected<IncomingStream<'_>> for ClientInfo {
     }
 }
 
-pub async fn run(settings: &Settings) -> anyhow::Result<()> {
+/// Serve an app created by [`initialize`]
+pub async fn run(settings: &Settings, app: Router) -> anyhow::Result<()> {
     let addr = SocketAddr::from((std::net::Ipv4Addr::UNSPECIFIED, settings.server.port));
     let tcp = TcpListener::bind(addr).await?;
 
-    let app = initialize(settings).await?;
-
     tracing::info!("listening on {}", addr);
 
     axum::serve(tcp, app.into_make_service_with_connect_info::<ClientInfo>())
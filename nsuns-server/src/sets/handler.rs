@@ -8,22 +8,41 @@ use uuid::Uuid;
 use crate::{
     auth::token::OwnerId,
     db::{commit_ok, Pool},
+    quotas::settings::QuotaSettings,
     response_transforms::{created, no_content_or_404, or_404},
     transaction,
     validation::ValidatedJson,
 };
 
-use super::model::{delete_one, CreateSet, UpdateSet};
+use super::model::{delete_one, CreateSet, CreateSets, MoveSet, UpdateSet};
 
 #[tracing::instrument(skip_all)]
 pub async fn create_set(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     ValidatedJson(set): ValidatedJson<CreateSet>,
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
     let res = set
-        .insert_one(owner_id, &mut tx)
+        .insert_one(owner_id, &quotas, &mut tx)
+        .await
+        .map(Json)
+        .map(created);
+
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn create_sets(
+    State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
+    owner_id: OwnerId,
+    ValidatedJson(sets): ValidatedJson<CreateSets>,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = sets
+        .insert_all(owner_id, &quotas, &mut tx)
         .await
         .map(Json)
         .map(created);
@@ -46,6 +65,21 @@ pub async fn update_set(
     commit_ok(res, tx).await
 }
 
+#[tracing::instrument(skip_all)]
+pub async fn move_set(
+    State(pool): State<Pool>,
+    owner_id: OwnerId,
+    ValidatedJson(set): ValidatedJson<MoveSet>,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = set
+        .move_one(owner_id, &mut tx)
+        .await
+        .map(or_404::<_, Json<_>>);
+
+    commit_ok(res, tx).await
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn delete_set(
     State(pool): State<Pool>,
@@ -1,4 +1,7 @@
-use std::fmt::Display;
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fmt::Display,
+};
 
 use anyhow::{anyhow, Context};
 use axum::http::StatusCode;
@@ -8,7 +11,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 use sqlx::{Executor, Transaction};
 use utoipa::ToSchema;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 use crate::{
     auth::token::OwnerId,
@@ -21,9 +24,12 @@ use crate::{
     },
     db_span,
     error::{ErrorWithStatus, OperationResult},
-    into_log_server_error, log_server_error,
+    grants::model::Access,
+    has_access, into_log_server_error, log_server_error,
     movements::model::Movement,
-    program::model::{get_set_ids, update_set_ids, Program, ProgramSetIds},
+    program::model::{get_set_ids, update_set_ids, Program},
+    quotas::{self, settings::QuotaSettings, Resource},
+    vec::MoveWithin,
 };
 
 const TABLE: &str = "program_sets";
@@ -67,17 +73,75 @@ impl Day {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow, ToSchema, PartialEq)]
+/// Where a set is in a week of a program: either on a weekday, or on one of the program's training days
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Slot {
+    Day(Day),
+    TrainingDay(Uuid),
+}
+
+impl Slot {
+    /// The slot for a weekday or a training day id, if exactly one of them is provided
+    #[must_use]
+    pub fn new(day: Option<Day>, training_day_id: Option<Uuid>) -> Option<Self> {
+        match (day, training_day_id) {
+            (Some(day), None) => Some(Slot::Day(day)),
+            (None, Some(training_day_id)) => Some(Slot::TrainingDay(training_day_id)),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn day(self) -> Option<Day> {
+        match self {
+            Slot::Day(day) => Some(day),
+            Slot::TrainingDay(_) => None,
+        }
+    }
+
+    #[must_use]
+    pub fn training_day_id(self) -> Option<Uuid> {
+        match self {
+            Slot::Day(_) => None,
+            Slot::TrainingDay(id) => Some(id),
+        }
+    }
+}
+
+/// Validate that exactly one of `day` or `trainingDayId` was provided
+pub(crate) fn validate_slot(
+    day: Option<Day>,
+    training_day_id: Option<Uuid>,
+) -> Result<Slot, ValidationError> {
+    Slot::new(day, training_day_id)
+        .ok_or_else(|| ValidationError::new("exactly one of day or trainingDayId must be provided"))
+}
+
+/// Get the slot of a model that was validated with [`validate_slot`]
+fn validated_slot(day: Option<Day>, training_day_id: Option<Uuid>) -> OperationResult<Slot> {
+    validate_slot(day, training_day_id)
+        .map_err(|e| ErrorWithStatus::new(StatusCode::BAD_REQUEST, anyhow!(e)))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow, Validate, ToSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Set {
     pub id: Uuid,
     pub program_id: Uuid,
-    #[schema(value_type = i16)]
-    pub day: Day,
+    #[validate(range(min = 0))]
+    pub week: i16,
+    /// The weekday of the set, unless it belongs to a training day
+    #[schema(value_type = Option<i16>)]
+    pub day: Option<Day>,
+    /// The training day of the set, unless it belongs to a weekday
+    pub training_day_id: Option<Uuid>,
     pub movement_id: Uuid,
+    #[validate(range(min = 0))]
     pub reps: Option<i32>,
     pub reps_is_minimum: bool,
+    #[validate(length(min = 1))]
     pub description: Option<String>,
+    #[validate(range(min = 0))]
     pub amount: f64,
     pub percentage_of_max: Option<Uuid>,
 }
@@ -96,15 +160,71 @@ impl Set {
         .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
         .await
     }
+
+    pub async fn select_all(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Set>> {
+        // sets are in order within their day, for exporting
+        sqlx::query_as::<_, Set>(formatcp!(
+            "{SELECT} * FROM {TABLE} WHERE owner_id = $1 ORDER BY ordering"
+        ))
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select all sets")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Select the sets of one week of a program, in order for each weekday and training day
+    pub async fn select_for_week(
+        program_id: Uuid,
+        week: i16,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Set>> {
+        sqlx::query_as::<_, Set>(formatcp!(
+            "{SELECT} * FROM {TABLE}
+            WHERE program_id = $1 AND week = $2 AND owner_id = $3
+            ORDER BY day, training_day_id, ordering"
+        ))
+        .bind(program_id)
+        .bind(week)
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .with_context(|| {
+            format!("failed to select sets for week={week} of program with id={program_id}")
+        })
+        .map_err(into_log_server_error!())
+    }
+
+    /// Where the set is in its week
+    #[must_use]
+    pub fn slot(&self) -> Option<Slot> {
+        Slot::new(self.day, self.training_day_id)
+    }
+}
+
+fn validate_create_set_slot(set: &CreateSet) -> Result<(), ValidationError> {
+    validate_slot(set.day, set.training_day_id).map(|_| ())
 }
 
+/// A set to create on either a weekday or a training day of a program
 #[derive(Debug, Deserialize, Serialize, Clone, Validate, ToSchema)]
+#[validate(schema(function = "validate_create_set_slot", skip_on_field_errors = false))]
 #[serde(rename_all = "camelCase")]
 pub struct CreateSet {
     pub program_id: Uuid,
     pub movement_id: Uuid,
-    #[schema(value_type = i16)]
-    pub day: Day,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub week: i16,
+    #[serde(default)]
+    #[schema(value_type = Option<i16>)]
+    pub day: Option<Day>,
+    #[serde(default)]
+    pub training_day_id: Option<Uuid>,
     #[validate(range(min = 0))]
     pub reps: Option<i32>,
     #[serde(default)]
@@ -124,8 +244,40 @@ impl CreateSet {
         sets: &[Self],
         program_id: Uuid,
         owner_id: OwnerId,
+        quotas: &QuotaSettings,
         tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<Vec<Set>> {
+        quotas::enforce(Resource::Sets, sets.len(), owner_id, quotas, tx).await?;
+
+        let slots = sets
+            .iter()
+            .map(|s| validated_slot(s.day, s.training_day_id))
+            .collect::<OperationResult<Vec<_>>>()?;
+
+        // Sets are ordered after the sets their day already has, in the order they were given.
+        let mut next_ordering: BTreeMap<(i16, Slot), i32> = BTreeMap::new();
+        let mut ordering: Vec<i32> = Vec::with_capacity(sets.len());
+
+        for (set, slot) in sets.iter().zip(&slots) {
+            let next = match next_ordering.entry((set.week, *slot)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let set_ids = get_set_ids(program_id, set.week, *slot, true, owner_id, tx)
+                        .await?
+                        .ok_or_else(|| {
+                            ErrorWithStatus::new(
+                                StatusCode::CONFLICT,
+                                anyhow!("referenced training day or program week does not exist"),
+                            )
+                        })?;
+                    entry.insert(set_ids.len() as i32)
+                }
+            };
+
+            ordering.push(*next);
+            *next += 1;
+        }
+
         // sqlx does not yet support providing iterators for bound data.
         let movement_ids: Vec<_> = sets.iter().map(|s| s.movement_id).collect();
         let reps: Vec<_> = sets.iter().map(|s| s.reps).collect();
@@ -133,7 +285,9 @@ impl CreateSet {
         let description: Vec<_> = sets.iter().map(|s| s.description.as_deref()).collect();
         let amount: Vec<_> = sets.iter().map(|s| s.amount).collect();
         let percentage_of_max: Vec<_> = sets.iter().map(|s| s.percentage_of_max).collect();
-        let day: Vec<_> = sets.iter().map(|s| s.day as i16).collect();
+        let day: Vec<_> = slots.iter().map(|s| s.day().map(|d| d as i16)).collect();
+        let training_day_id: Vec<_> = slots.iter().map(|s| s.training_day_id()).collect();
+        let week: Vec<_> = sets.iter().map(|s| s.week).collect();
 
         Movement::assert_all_owner(&movement_ids, owner_id, &mut **tx).await?;
 
@@ -146,6 +300,9 @@ impl CreateSet {
                 amount,
                 percentage_of_max,
                 day,
+                training_day_id,
+                ordering,
+                week,
                 program_id,
                 owner_id
             ) VALUES (
@@ -156,8 +313,11 @@ impl CreateSet {
                 unnest($5),
                 unnest($6),
                 unnest($7),
-                $8,
-                $9
+                unnest($8),
+                unnest($9),
+                unnest($10),
+                $11,
+                $12
             ) RETURNING *"
         ))
         .bind(&movement_ids)
@@ -167,6 +327,9 @@ impl CreateSet {
         .bind(&amount)
         .bind(&percentage_of_max)
         .bind(&day)
+        .bind(&training_day_id)
+        .bind(&ordering)
+        .bind(&week)
         .bind(program_id)
         .bind(owner_id)
         .fetch_all((&mut **tx).instrument_executor(db_span!(INSERT_INTO, TABLE)))
@@ -174,50 +337,36 @@ impl CreateSet {
         .map_err(|e| handle_error(e, || "failed to insert new set"))
         .map_err(log_server_error!())?;
 
-        let program_opt = ProgramSetIds::select_one(program_id, true, owner_id, &mut **tx).await?;
-
-        if let Some(mut program) = program_opt {
-            for set in &sets {
-                match set.day {
-                    Day::Sunday => program.set_ids_sunday.push(set.id),
-                    Day::Monday => program.set_ids_monday.push(set.id),
-                    Day::Tuesday => program.set_ids_tuesday.push(set.id),
-                    Day::Wednesday => program.set_ids_wednesday.push(set.id),
-                    Day::Thursday => program.set_ids_thursday.push(set.id),
-                    Day::Friday => program.set_ids_friday.push(set.id),
-                    Day::Saturday => program.set_ids_saturday.push(set.id),
-                }
-            }
-
-            program.update_one(owner_id, &mut **tx).await?;
-
-            Ok(sets)
-        } else {
-            Err(ErrorWithStatus::new(
-                StatusCode::CONFLICT,
-                anyhow!("referenced program does not exist"),
-            ))
-        }
+        Ok(sets)
     }
 
     pub async fn insert_one(
         self,
         owner_id: OwnerId,
+        quotas: &QuotaSettings,
         tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<Option<Set>> {
-        Program::assert_owner(self.program_id, owner_id, &mut **tx).await?;
+        let owner_id =
+            Program::assert_owner(self.program_id, owner_id, Access::Write, &mut **tx).await?;
+        quotas::enforce(Resource::Sets, 1, owner_id, quotas, tx).await?;
+
         Movement::assert_owner(self.movement_id, owner_id, &mut **tx).await?;
         if let Some(percentage_of_max) = self.percentage_of_max {
             Movement::assert_owner(percentage_of_max, owner_id, &mut **tx).await?;
         }
 
-        let set_ids = get_set_ids(self.program_id, self.day, true, owner_id, &mut **tx).await?;
+        let slot = validated_slot(self.day, self.training_day_id)?;
+
+        let set_ids = get_set_ids(self.program_id, self.week, slot, true, owner_id, tx).await?;
+
+        if let Some(set_ids) = set_ids {
+            // new sets go at the end of their day
+            let ordering = set_ids.len() as i32;
 
-        if let Some(mut set_ids) = set_ids {
             let id = sqlx::query_as::<_, (Uuid,)>(formatcp!(
                 "{INSERT_INTO} {TABLE} (
-                    movement_id, reps, reps_is_minimum, description, amount, percentage_of_max, program_id, day, owner_id
-                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
+                    movement_id, reps, reps_is_minimum, description, amount, percentage_of_max, program_id, week, day, training_day_id, ordering, owner_id
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING id",
             ))
             .bind(self.movement_id)
             .bind(self.reps)
@@ -226,7 +375,10 @@ impl CreateSet {
             .bind(self.amount)
             .bind(self.percentage_of_max)
             .bind(self.program_id)
+            .bind(self.week)
             .bind(self.day)
+            .bind(self.training_day_id)
+            .bind(ordering)
             .bind(owner_id)
             .fetch_one((&mut **tx).instrument_executor(db_span!(INSERT_INTO, TABLE)))
             .await
@@ -234,14 +386,12 @@ impl CreateSet {
             .map_err(log_server_error!())?
             .0;
 
-            set_ids.push(id);
-
-            update_set_ids(self.program_id, self.day, &set_ids, owner_id, &mut **tx).await?;
-
             Ok(Some(Set {
                 id,
                 program_id: self.program_id,
+                week: self.week,
                 day: self.day,
+                training_day_id: self.training_day_id,
                 movement_id: self.movement_id,
                 reps: self.reps,
                 reps_is_minimum: self.reps_is_minimum,
@@ -255,13 +405,89 @@ impl CreateSet {
     }
 }
 
+/// Sets to create in one request, which are either all created or none are
+#[derive(Debug, Deserialize, Serialize, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSets {
+    #[validate]
+    pub sets: Vec<CreateSet>,
+}
+
+impl CreateSets {
+    /// Insert every set, grouped by program in the order the programs first appear
+    pub async fn insert_all(
+        self,
+        owner_id: OwnerId,
+        quotas: &QuotaSettings,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Vec<Set>> {
+        let mut program_ids: Vec<Uuid> = vec![];
+
+        for set in &self.sets {
+            if !program_ids.contains(&set.program_id) {
+                program_ids.push(set.program_id);
+            }
+        }
+
+        let mut inserted = Vec::with_capacity(self.sets.len());
+
+        for program_id in program_ids {
+            let owner_id =
+                Program::assert_owner(program_id, owner_id, Access::Write, &mut **tx).await?;
+
+            let sets: Vec<_> = self
+                .sets
+                .iter()
+                .filter(|set| set.program_id == program_id)
+                .cloned()
+                .collect();
+
+            let percentage_of_max: Vec<_> = sets
+                .iter()
+                .filter_map(|set| set.percentage_of_max)
+                .collect();
+            Movement::assert_all_owner(&percentage_of_max, owner_id, &mut **tx).await?;
+
+            inserted.extend(CreateSet::insert_many(&sets, program_id, owner_id, quotas, tx).await?);
+        }
+
+        Ok(inserted)
+    }
+}
+
+/// The profile a set belongs to, through its program
+const PROFILE_ID: &str = "(SELECT p.owner FROM programs p WHERE p.id = program_id)";
+
+/// Select the owner of a set, if `owner_id` is allowed to change it
+async fn select_writable_owner(
+    id: Uuid,
+    owner_id: OwnerId,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<Option<OwnerId>> {
+    sqlx::query_as::<_, (OwnerId,)>(formatcp!(
+        "{SELECT} owner_id FROM {TABLE} WHERE id = $1 AND {}",
+        has_access!(Write, PROFILE_ID, "$2")
+    ))
+    .bind(id)
+    .bind(owner_id)
+    .fetch_optional(executor.instrument_executor(db_span!(SELECT, TABLE)))
+    .await
+    .with_context(|| format!("failed to fetch set with id={id}"))
+    .map_err(into_log_server_error!())
+    .map(|row| row.map(|(owner_id,)| owner_id))
+}
+
 pub async fn delete_one(
     id: Uuid,
     owner_id: OwnerId,
     tx: &mut Transaction<'_, DB>,
 ) -> OperationResult<Option<()>> {
-    let res = sqlx::query_as::<_, (Uuid, Day)>(formatcp!(
-        "{DELETE_FROM} {TABLE} WHERE id = $1 AND owner_id = $2 RETURNING program_id, day",
+    let Some(owner_id) = select_writable_owner(id, owner_id, &mut **tx).await? else {
+        return Ok(None);
+    };
+
+    let res = sqlx::query_as::<_, (Uuid, i16, Option<Day>, Option<Uuid>)>(formatcp!(
+        "{DELETE_FROM} {TABLE} WHERE id = $1 AND owner_id = $2 RETURNING program_id, week, day, training_day_id",
     ))
     .bind(id)
     .bind(owner_id)
@@ -270,12 +496,13 @@ pub async fn delete_one(
     .with_context(|| format!("failed to delete set with id={id}"))
     .map_err(into_log_server_error!())?;
 
-    if let Some((program_id, day)) = res {
-        let set_ids = get_set_ids(program_id, day, true, owner_id, &mut **tx).await?;
+    if let Some((program_id, week, day, training_day_id)) = res {
+        let slot = validated_slot(day, training_day_id)?;
+        let set_ids = get_set_ids(program_id, week, slot, true, owner_id, tx).await?;
 
         if let Some(set_ids) = set_ids {
             let set_ids: Vec<_> = set_ids.into_iter().filter(|set_id| *set_id != id).collect();
-            update_set_ids(program_id, day, &set_ids, owner_id, &mut **tx).await?;
+            update_set_ids(program_id, week, slot, &set_ids, owner_id, &mut **tx).await?;
         }
         Ok(Some(()))
     } else {
@@ -305,12 +532,16 @@ impl UpdateSet {
         owner_id: OwnerId,
         tx: &mut Transaction<'_, DB>,
     ) -> OperationResult<Option<Set>> {
+        let Some(owner_id) = select_writable_owner(self.id, owner_id, &mut **tx).await? else {
+            return Ok(None);
+        };
+
         Movement::assert_owner(self.movement_id, owner_id, &mut **tx).await?;
         if let Some(percentage_of_max) = self.percentage_of_max {
             Movement::assert_owner(percentage_of_max, owner_id, &mut **tx).await?;
         }
 
-        let res = sqlx::query_as::<_, (Uuid, Day)>(formatcp!(
+        let res = sqlx::query_as::<_, (Uuid, i16, Option<Day>, Option<Uuid>)>(formatcp!(
             "{UPDATE} {TABLE} SET
             movement_id = $1,
             reps = $2,
@@ -319,7 +550,7 @@ impl UpdateSet {
             amount = $5,
             percentage_of_max = $6
             WHERE id = $7 AND owner_id = $8
-            RETURNING program_id, day
+            RETURNING program_id, week, day, training_day_id
         ",
         ))
         .bind(self.movement_id)
@@ -339,10 +570,12 @@ impl UpdateSet {
         })
         .map_err(log_server_error!())?;
 
-        Ok(res.map(|(program_id, day)| Set {
+        Ok(res.map(|(program_id, week, day, training_day_id)| Set {
             id: self.id,
             program_id,
+            week,
             day,
+            training_day_id,
             movement_id: self.movement_id,
             reps: self.reps,
             reps_is_minimum: self.reps_is_minimum,
@@ -352,3 +585,204 @@ impl UpdateSet {
         }))
     }
 }
+
+fn validate_move_set_slot(set: &MoveSet) -> Result<(), ValidationError> {
+    validate_slot(set.day, set.training_day_id).map(|_| ())
+}
+
+/// Move a set to a position on a weekday or training day, which may be in another week or program
+#[derive(Debug, Deserialize, Serialize, Clone, Validate, ToSchema)]
+#[validate(schema(function = "validate_move_set_slot", skip_on_field_errors = false))]
+#[serde(rename_all = "camelCase")]
+pub struct MoveSet {
+    pub id: Uuid,
+    /// The program to move the set to
+    pub program_id: Uuid,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub week: i16,
+    #[serde(default)]
+    #[schema(value_type = Option<i16>)]
+    pub day: Option<Day>,
+    #[serde(default)]
+    pub training_day_id: Option<Uuid>,
+    /// Where the set ends up among the sets of its new day
+    pub index: usize,
+}
+
+fn index_out_of_bounds() -> ErrorWithStatus<anyhow::Error> {
+    ErrorWithStatus::new(StatusCode::CONFLICT, anyhow!("index out of bounds"))
+}
+
+/// Identifies the row that is locked for a day in a week of a program: the week for weekdays, since all weekdays of a
+/// week share it, or the training day, which is shared by all weeks
+fn slot_lock_key(program_id: Uuid, week: i16, slot: Slot) -> (Uuid, Option<Uuid>, i16) {
+    match slot {
+        Slot::Day(_) => (program_id, None, week),
+        Slot::TrainingDay(training_day_id) => (program_id, Some(training_day_id), 0),
+    }
+}
+
+impl MoveSet {
+    pub async fn move_one(
+        self,
+        owner_id: OwnerId,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Option<Set>> {
+        let Some(set_owner_id) = select_writable_owner(self.id, owner_id, &mut **tx).await? else {
+            return Ok(None);
+        };
+
+        let program_owner_id =
+            Program::assert_owner(self.program_id, owner_id, Access::Write, &mut **tx).await?;
+
+        // movements belong to an owner, so sets cannot take them to programs of another owner
+        if set_owner_id.as_uuid() != program_owner_id.as_uuid() {
+            return Err(ErrorWithStatus::new(
+                StatusCode::CONFLICT,
+                anyhow!("sets can only be moved between programs of the same owner"),
+            ));
+        }
+
+        let slot = validated_slot(self.day, self.training_day_id)?;
+
+        let (from_program_id, from_week, from_day, from_training_day_id) =
+            sqlx::query_as::<_, (Uuid, i16, Option<Day>, Option<Uuid>)>(formatcp!(
+                "{SELECT} program_id, week, day, training_day_id FROM {TABLE} WHERE id = $1"
+            ))
+            .bind(self.id)
+            .fetch_one((&mut **tx).instrument_executor(db_span!(SELECT, TABLE)))
+            .await
+            .with_context(|| format!("failed to fetch set with id={id}", id = self.id))
+            .map_err(into_log_server_error!())?;
+
+        let from_slot = validated_slot(from_day, from_training_day_id)?;
+
+        // Both days are locked before any set is changed, like every other change to the order of a day, and always
+        // in the same order, so concurrent moves in opposite directions cannot deadlock.
+        let from_key = slot_lock_key(from_program_id, from_week, from_slot);
+        let to_key = slot_lock_key(self.program_id, self.week, slot);
+
+        let (from_ids, to_ids) = if from_key <= to_key {
+            let from_ids =
+                get_set_ids(from_program_id, from_week, from_slot, true, owner_id, tx).await?;
+            let to_ids = get_set_ids(self.program_id, self.week, slot, true, owner_id, tx).await?;
+            (from_ids, to_ids)
+        } else {
+            let to_ids = get_set_ids(self.program_id, self.week, slot, true, owner_id, tx).await?;
+            let from_ids =
+                get_set_ids(from_program_id, from_week, from_slot, true, owner_id, tx).await?;
+            (from_ids, to_ids)
+        };
+
+        // the set was read before taking the locks, so another request may have moved it in the meantime
+        let Some((mut from_ids, from_index)) = from_ids.and_then(|ids| {
+            let index = ids.iter().position(|id| *id == self.id)?;
+            Some((ids, index))
+        }) else {
+            return Err(ErrorWithStatus::new(
+                StatusCode::CONFLICT,
+                anyhow!("set was changed by another request, try again"),
+            ));
+        };
+
+        if (from_program_id, from_week, from_slot) == (self.program_id, self.week, slot) {
+            if self.index >= from_ids.len() {
+                return Err(index_out_of_bounds());
+            }
+
+            if from_ids.move_within(from_index, self.index) {
+                update_set_ids(
+                    from_program_id,
+                    from_week,
+                    slot,
+                    &from_ids,
+                    owner_id,
+                    &mut **tx,
+                )
+                .await?;
+            }
+        } else {
+            let mut to_ids = to_ids.ok_or_else(|| {
+                ErrorWithStatus::new(
+                    StatusCode::CONFLICT,
+                    anyhow!("referenced training day or program week does not exist"),
+                )
+            })?;
+
+            if self.index > to_ids.len() {
+                return Err(index_out_of_bounds());
+            }
+
+            // placed after the sets of the new day for now, which is a position none of them hold
+            sqlx::query(formatcp!(
+                "{UPDATE} {TABLE} SET program_id = $1, week = $2, day = $3, training_day_id = $4, ordering = $5
+                WHERE id = $6"
+            ))
+            .bind(self.program_id)
+            .bind(self.week)
+            .bind(slot.day())
+            .bind(slot.training_day_id())
+            .bind(to_ids.len() as i32)
+            .bind(self.id)
+            .execute((&mut **tx).instrument_executor(db_span!(UPDATE, TABLE)))
+            .await
+            .with_context(|| format!("failed to move set with id={id}", id = self.id))
+            .map_err(into_log_server_error!())?;
+
+            from_ids.remove(from_index);
+            to_ids.insert(self.index, self.id);
+
+            update_set_ids(
+                from_program_id,
+                from_week,
+                from_slot,
+                &from_ids,
+                owner_id,
+                &mut **tx,
+            )
+            .await?;
+            update_set_ids(
+                self.program_id,
+                self.week,
+                slot,
+                &to_ids,
+                owner_id,
+                &mut **tx,
+            )
+            .await?;
+        }
+
+        sqlx::query_as::<_, Set>(formatcp!("{SELECT} * FROM {TABLE} WHERE id = $1"))
+            .bind(self.id)
+            .fetch_optional((&mut **tx).instrument_executor(db_span!(SELECT, TABLE)))
+            .await
+            .with_context(|| format!("failed to fetch set with id={id}", id = self.id))
+            .map_err(into_log_server_error!())
+    }
+}
+
+/// Delete the sets of a week, and move the sets of later weeks up by one week.
+///
+/// The weeks themselves are left for the caller to renumber.
+pub async fn remove_week_sets(
+    program_id: Uuid,
+    week: i16,
+    owner_id: OwnerId,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<()> {
+    sqlx::query(formatcp!(
+        "WITH deleted AS (
+            {DELETE_FROM} {TABLE} WHERE program_id = $1 AND week = $2 AND owner_id = $3
+        )
+        {UPDATE} {TABLE} SET week = week - 1 WHERE program_id = $1 AND week > $2 AND owner_id = $3"
+    ))
+    .bind(program_id)
+    .bind(week)
+    .bind(owner_id)
+    .execute(executor.instrument_executor(db_span!(DELETE_FROM, TABLE)))
+    .await
+    .with_context(|| format!("failed to delete sets of week={week} and program_id={program_id}"))
+    .map_err(into_log_server_error!())
+    .map(|_| ())
+}
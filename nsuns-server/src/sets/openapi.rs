@@ -6,7 +6,7 @@ use utoipa::{
         response::Response,
         ComponentsBuilder, PathItemType, PathsBuilder, ResponseBuilder,
     },
-    ToSchema,
+    PartialSchema, ToSchema,
 };
 
 use crate::{
@@ -17,7 +17,7 @@ use crate::{
     router::SETS_PATH,
 };
 
-use super::model::{CreateSet, Set, UpdateSet};
+use super::model::{CreateSet, CreateSets, MoveSet, Set, UpdateSet};
 
 pub struct SetsModule;
 
@@ -33,6 +33,8 @@ impl Customizer<ComponentsBuilder> for SetsModule {
             .schema_from::<Set>()
             .schema_from::<CreateSet>()
             .schema_from::<UpdateSet>()
+            .schema_from::<MoveSet>()
+            .schema_from::<CreateSets>()
     }
 }
 
@@ -58,6 +60,37 @@ impl Customizer<PathsBuilder> for SetsModule {
             .tag(TAG)
             .build();
 
+        let bulk_op = OperationBuilder::new()
+            .description(Some(
+                "Create many sets at once. Either every set is created, or none are, and validation errors name the index of the set they are for.",
+            ))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(CreateSets::schema().1)
+                    .build(),
+            ))
+            .response(
+                created(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<Set>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let move_op = OperationBuilder::new()
+            .description(Some(
+                "Move a set to a position on a weekday or training day, which may be in another week or program of the same owner",
+            ))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(MoveSet::schema().1)
+                    .build(),
+            ))
+            .response(ok(), set_response())
+            .tag(TAG)
+            .build();
+
         let delete_op = OperationBuilder::new()
             .parameters(id_path_param(Some("The set to delete")))
             .response(no_content(), Response::new("no content"))
@@ -72,6 +105,18 @@ impl Customizer<PathsBuilder> for SetsModule {
                     .operation(PathItemType::Put, put_op)
                     .build(),
             )
+            .path(
+                concatcp!(SETS_PATH, "/bulk"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, bulk_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(SETS_PATH, "/move"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, move_op)
+                    .build(),
+            )
             .path(
                 concatcp!(SETS_PATH, "/{id}"),
                 PathItemBuilder::new()
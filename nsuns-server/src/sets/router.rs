@@ -4,15 +4,18 @@ use axum::{
     Router,
 };
 
-use crate::{db::Pool, router::State};
+use crate::{db::Pool, quotas::settings::QuotaSettings, router::State};
 
-use super::handler::{create_set, delete_set, update_set};
+use super::handler::{create_set, create_sets, delete_set, move_set, update_set};
 
 pub fn router<S: State>() -> Router<S>
 where
     Pool: FromRef<S>,
+    QuotaSettings: FromRef<S>,
 {
     Router::new()
         .route("/", post(create_set).put(update_set))
+        .route("/bulk", post(create_sets))
+        .route("/move", post(move_set))
         .route("/:id", delete(delete_set))
 }
@@ -10,8 +10,10 @@ use serde::Deserialize;
 use crate::{
     auth::settings::AuthSettings,
     db::settings::DatabaseSettings,
+    integrity::settings::IntegritySettings,
     observability::{metrics::settings::MetricsFeature, tracing::settings::LogSettings},
     openapi::settings::OpenApiFeature,
+    quotas::settings::QuotaSettings,
 };
 
 fn default_server_port() -> u16 {
@@ -55,6 +57,10 @@ pub struct Settings {
     #[serde(default)]
     pub logging: LogSettings,
     pub auth: AuthSettings,
+    #[serde(default)]
+    pub quotas: QuotaSettings,
+    #[serde(default)]
+    pub integrity: IntegritySettings,
 }
 
 pub trait SetEnvOverride {
@@ -0,0 +1,26 @@
+use axum::{extract::Path, response::IntoResponse, Json};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{error::extract::WithErrorRejection, response_transforms::or_404};
+
+use super::model::{find, TemplateSummary};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
+pub struct TemplatePath {
+    /// The slug of the template, such as `nsuns-5day`
+    pub slug: String,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn templates_index() -> impl IntoResponse {
+    Json(TemplateSummary::select_all())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_template(
+    WithErrorRejection(Path(path)): WithErrorRejection<Path<TemplatePath>>,
+) -> impl IntoResponse {
+    or_404::<_, Json<_>>(find(&path.slug).cloned())
+}
@@ -0,0 +1,12 @@
+//! A catalog of standard nSuns programs, compiled into the server.
+//!
+//! Each template is a [`TemplatedProgram`](crate::program::templated::model::TemplatedProgram) without an owner.
+//! Clients set the `owner` of a template and create a program from it with `/api/programs/from-template`, which uses
+//! the owner's movements with the same names, and creates the ones they do not have yet.
+
+pub mod handler;
+pub mod model;
+pub mod openapi;
+pub mod router;
+
+pub use router::router;
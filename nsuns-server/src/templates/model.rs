@@ -0,0 +1,108 @@
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::program::templated::model::TemplatedProgram;
+
+/// The templates of the catalog, by slug, in the order they are listed
+const CATALOG: [(&str, &str); 4] = [
+    ("nsuns-4day", include_str!("catalog/nsuns-4day.json")),
+    ("nsuns-5day", include_str!("catalog/nsuns-5day.json")),
+    (
+        "nsuns-5day-weekend",
+        include_str!("catalog/nsuns-5day-weekend.json"),
+    ),
+    ("nsuns-6day", include_str!("catalog/nsuns-6day.json")),
+];
+
+static TEMPLATES: OnceLock<Vec<(&'static str, TemplatedProgram)>> = OnceLock::new();
+
+fn templates() -> &'static [(&'static str, TemplatedProgram)] {
+    TEMPLATES.get_or_init(|| {
+        CATALOG
+            .iter()
+            .map(|(slug, document)| {
+                let template = serde_json::from_str(document)
+                    .unwrap_or_else(|e| panic!("template {slug} is not a valid program: {e}"));
+                (*slug, template)
+            })
+            .collect()
+    })
+}
+
+/// A template of the catalog, without its sets
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateSummary {
+    pub slug: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// How many days of the week have sets
+    pub days_per_week: usize,
+    pub weeks: usize,
+}
+
+impl TemplateSummary {
+    /// List every template of the catalog
+    #[must_use]
+    pub fn select_all() -> Vec<Self> {
+        templates()
+            .iter()
+            .map(|(slug, template)| TemplateSummary {
+                slug: (*slug).to_owned(),
+                name: template.name().to_owned(),
+                description: template.description().map(ToOwned::to_owned),
+                days_per_week: template.days_per_week(),
+                weeks: template.weeks(),
+            })
+            .collect()
+    }
+}
+
+/// Find a template of the catalog by its slug
+#[must_use]
+pub fn find(slug: &str) -> Option<&'static TemplatedProgram> {
+    templates()
+        .iter()
+        .find(|(template_slug, _)| *template_slug == slug)
+        .map(|(_, template)| template)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+    use validator::Validate;
+
+    use super::*;
+
+    #[test]
+    fn test_templates_are_valid_with_an_owner() {
+        for (slug, document) in CATALOG {
+            let mut template: serde_json::Value = serde_json::from_str(document).unwrap();
+            template["owner"] = serde_json::json!(Uuid::nil());
+
+            let template: TemplatedProgram = serde_json::from_value(template).unwrap();
+
+            assert!(template.validate().is_ok(), "template {slug} is invalid");
+        }
+    }
+
+    #[test]
+    fn test_days_per_week() {
+        let days: Vec<_> = TemplateSummary::select_all()
+            .into_iter()
+            .map(|template| (template.slug, template.days_per_week))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("nsuns-4day".to_owned(), 4),
+                ("nsuns-5day".to_owned(), 5),
+                ("nsuns-5day-weekend".to_owned(), 5),
+                ("nsuns-6day".to_owned(), 6),
+            ],
+            days
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use const_format::concatcp;
+use utoipa::{
+    openapi::{
+        path::{OperationBuilder, PathItemBuilder},
+        ComponentsBuilder, PathItemType, PathsBuilder, ResponseBuilder,
+    },
+    IntoParams, PartialSchema, ToSchema,
+};
+
+use crate::{
+    openapi::{
+        extensions::{ok, param_in_default, JsonContent},
+        Customizer,
+    },
+    program::templated::model::TemplatedProgram,
+    router::TEMPLATES_PATH,
+};
+
+use super::{handler::TemplatePath, model::TemplateSummary};
+
+pub struct TemplatesModule;
+
+const TAG: &str = "Templates";
+
+impl Customizer<ComponentsBuilder> for TemplatesModule {
+    fn customize(builder: ComponentsBuilder) -> ComponentsBuilder {
+        builder.schema_from::<TemplateSummary>()
+    }
+}
+
+impl Customizer<PathsBuilder> for TemplatesModule {
+    fn customize(builder: PathsBuilder) -> PathsBuilder {
+        let get_op = OperationBuilder::new()
+            .description(Some("List the standard programs that come with the server"))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(Vec::<TemplateSummary>::schema())
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let get_one_op = OperationBuilder::new()
+            .description(Some(
+                "Fetch a standard program, which can be created for a profile by setting its `owner` and sending it to `/api/programs/from-template`",
+            ))
+            .parameters(Some(TemplatePath::into_params(param_in_default)))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(TemplatedProgram::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        builder
+            .path(
+                TEMPLATES_PATH,
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, get_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(TEMPLATES_PATH, "/{slug}"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, get_one_op)
+                    .build(),
+            )
+    }
+}
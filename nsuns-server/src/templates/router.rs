@@ -0,0 +1,11 @@
+use axum::{routing::get, Router};
+
+use crate::router::State;
+
+use super::handler::{get_template, templates_index};
+
+pub fn router<S: State>() -> Router<S> {
+    Router::new()
+        .route("/", get(templates_index))
+        .route("/:slug", get(get_template))
+}
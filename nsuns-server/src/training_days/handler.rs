@@ -0,0 +1,133 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use http::StatusCode;
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{
+    acquire,
+    auth::token::OwnerId,
+    db::{commit_ok, Pool},
+    error::extract::WithErrorRejection,
+    response_transforms::{created, no_content_or_404, or_404},
+    transaction,
+    validation::ValidatedJson,
+};
+
+use super::model::{
+    delete_one, next_training_day as select_next_training_day, CreateTrainingDay,
+    ReorderTrainingDays, TrainingDay, TrainingLog, UpdateTrainingDay,
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingDaysQuery {
+    pub program_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "camelCase")]
+pub struct NextTrainingDayQuery {
+    pub profile_id: Uuid,
+    /// The program to rotate through. Defaults to the program the profile last completed a training day of.
+    pub program_id: Option<Uuid>,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn training_days_index(
+    State(pool): State<Pool>,
+    WithErrorRejection(Query(query)): WithErrorRejection<Query<TrainingDaysQuery>>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut conn = acquire!(&pool).await?;
+    TrainingDay::select_for_program(query.program_id, owner_id, &mut *conn)
+        .await
+        .map(Json)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn create_training_day(
+    State(pool): State<Pool>,
+    owner_id: OwnerId,
+    ValidatedJson(training_day): ValidatedJson<CreateTrainingDay>,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = training_day
+        .insert_one(owner_id, &mut tx)
+        .await
+        .map(Json)
+        .map(created);
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn update_training_day(
+    State(pool): State<Pool>,
+    owner_id: OwnerId,
+    ValidatedJson(training_day): ValidatedJson<UpdateTrainingDay>,
+) -> impl IntoResponse {
+    let mut conn = acquire!(&pool).await?;
+    training_day
+        .update_one(owner_id, &mut *conn)
+        .await
+        .map(or_404::<_, Json<_>>)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn delete_training_day(
+    State(pool): State<Pool>,
+    Path(id): Path<Uuid>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = delete_one(id, owner_id, &mut tx)
+        .await
+        .map(no_content_or_404);
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn reorder_training_days(
+    State(pool): State<Pool>,
+    owner_id: OwnerId,
+    ValidatedJson(reorder): ValidatedJson<ReorderTrainingDays>,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = reorder
+        .reorder(owner_id, &mut tx)
+        .await
+        .map(or_404::<_, Json<_>>);
+    commit_ok(res, tx).await
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn complete_training_day(
+    State(pool): State<Pool>,
+    Path(id): Path<Uuid>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut conn = acquire!(&pool).await?;
+    TrainingLog::complete(id, owner_id, &mut *conn)
+        .await
+        .map(|log| log.map(Json).map(created))
+        .map(or_404::<_, (StatusCode, Json<_>)>)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn next_training_day(
+    State(pool): State<Pool>,
+    WithErrorRejection(Query(query)): WithErrorRejection<Query<NextTrainingDayQuery>>,
+    owner_id: OwnerId,
+) -> impl IntoResponse {
+    let mut tx = transaction!(&pool).await?;
+    let res = select_next_training_day(query.profile_id, query.program_id, owner_id, &mut tx)
+        .await
+        .map(or_404::<_, Json<_>>);
+    commit_ok(res, tx).await
+}
@@ -0,0 +1,11 @@
+//! Rotating training days, such as "Day A" and "Day B", for programs that do not follow the days of the week.
+//!
+//! A program can have any number of training days in order, alongside its weekdays. Each time a profile completes
+//! a training day it is logged, so the next one in the rotation can be looked up.
+
+pub mod handler;
+pub mod model;
+pub mod openapi;
+pub mod router;
+
+pub use router::router;
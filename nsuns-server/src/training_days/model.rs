@@ -0,0 +1,457 @@
+use anyhow::{anyhow, Context};
+use axum::http::StatusCode;
+use chrono::naive::serde::ts_milliseconds;
+use chrono::NaiveDateTime;
+use const_format::formatcp;
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    assert_owner,
+    auth::token::OwnerId,
+    db::{
+        tracing::{
+            statements::{DELETE_FROM, INSERT_INTO, SELECT, UPDATE},
+            InstrumentExecutor,
+        },
+        DB,
+    },
+    db_span,
+    error::{ErrorWithStatus, OperationResult},
+    has_access, into_log_server_error,
+    vec::MoveWithin,
+};
+
+const TABLE: &str = "training_days";
+
+const LOGS_TABLE: &str = "training_logs";
+
+/// The profile a training day belongs to, through its program
+const PROFILE_ID: &str = "(SELECT p.owner FROM programs p WHERE p.id = program_id)";
+
+/// A named day that a program rotates through, such as "Day A", instead of following weekdays
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingDay {
+    pub id: Uuid,
+    pub program_id: Uuid,
+    pub name: String,
+    /// Where the training day is in the rotation, starting at 0
+    pub position: i16,
+}
+
+const COLS: &str = "id, program_id, name, position";
+
+impl TrainingDay {
+    pub async fn select_for_program(
+        program_id: Uuid,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} {COLS} FROM {TABLE} WHERE program_id = $1 AND {} ORDER BY position",
+            has_access!(Read, PROFILE_ID, "$2")
+        ))
+        .bind(program_id)
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .with_context(|| format!("failed to select training days for program_id={program_id}"))
+        .map_err(into_log_server_error!())
+    }
+
+    pub async fn select_all(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} {COLS} FROM {TABLE} WHERE owner_id = $1 ORDER BY program_id, position"
+        ))
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, TABLE)))
+        .await
+        .context("failed to select all training days")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Add training days to the end of a program's rotation, in order.
+    ///
+    /// This does _NOT_ check access to the program.
+    pub async fn insert_many(
+        program_id: Uuid,
+        names: &[String],
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "WITH next AS (
+                {SELECT} COALESCE(MAX(position) + 1, 0) AS position FROM {TABLE} WHERE program_id = $1
+            )
+            {INSERT_INTO} {TABLE} (program_id, name, position, owner_id)
+            {SELECT} $1, n.name, next.position + n.index - 1, $3
+            FROM next, unnest($2::VARCHAR[]) WITH ORDINALITY n(name, index)
+            RETURNING {COLS}"
+        ))
+        .bind(program_id)
+        .bind(names)
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(INSERT_INTO, TABLE)))
+        .await
+        .with_context(|| format!("failed to add training days to program with id={program_id}"))
+        .map_err(into_log_server_error!())
+        .map(|mut days| {
+            days.sort_by_key(|day| day.position);
+            days
+        })
+    }
+}
+
+/// Lock a program that `owner_id` can change, so its training days can be added or renumbered.
+///
+/// Returns the owner of the program, or [`None`] if the program was not found.
+async fn lock_program(
+    program_id: Uuid,
+    owner_id: OwnerId,
+    executor: impl Executor<'_, Database = DB>,
+) -> OperationResult<Option<OwnerId>> {
+    sqlx::query_as::<_, (OwnerId,)>(formatcp!(
+        "{SELECT} owner_id FROM programs WHERE id = $1 AND {} FOR UPDATE",
+        has_access!(Write, "owner", "$2")
+    ))
+    .bind(program_id)
+    .bind(owner_id)
+    .fetch_optional(executor.instrument_executor(db_span!(SELECT, "programs")))
+    .await
+    .with_context(|| format!("failed to lock program with id={program_id}"))
+    .map_err(into_log_server_error!())
+    .map(|row| row.map(|(owner_id,)| owner_id))
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTrainingDay {
+    pub program_id: Uuid,
+    #[validate(length(min = 1))]
+    pub name: String,
+}
+
+impl CreateTrainingDay {
+    /// Add a training day to the end of a program's rotation
+    pub async fn insert_one(
+        self,
+        owner_id: OwnerId,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<TrainingDay> {
+        let owner_id = lock_program(self.program_id, owner_id, &mut **tx)
+            .await?
+            .ok_or_else(|| assert_owner!(@error "program"))?;
+
+        TrainingDay::insert_many(self.program_id, &[self.name], owner_id, &mut **tx)
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("no training day was added").into())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTrainingDay {
+    pub id: Uuid,
+    #[validate(length(min = 1))]
+    pub name: String,
+}
+
+impl UpdateTrainingDay {
+    pub async fn update_one(
+        self,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<TrainingDay>> {
+        sqlx::query_as::<_, TrainingDay>(formatcp!(
+            "{UPDATE} {TABLE} SET name = $1 WHERE id = $2 AND {} RETURNING {COLS}",
+            has_access!(Write, PROFILE_ID, "$3")
+        ))
+        .bind(&self.name)
+        .bind(self.id)
+        .bind(owner_id)
+        .fetch_optional(executor.instrument_executor(db_span!(UPDATE, TABLE)))
+        .await
+        .with_context(|| format!("failed to update training day with id={}", self.id))
+        .map_err(into_log_server_error!())
+    }
+}
+
+/// Delete a training day along with its sets. Later training days move up to fill the gap.
+pub async fn delete_one(
+    id: Uuid,
+    owner_id: OwnerId,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<Option<TrainingDay>> {
+    let program_id = sqlx::query_as::<_, (Uuid,)>(formatcp!(
+        "{SELECT} program_id FROM {TABLE} WHERE id = $1 AND {}",
+        has_access!(Write, PROFILE_ID, "$2")
+    ))
+    .bind(id)
+    .bind(owner_id)
+    .fetch_optional((&mut **tx).instrument_executor(db_span!(SELECT, TABLE)))
+    .await
+    .with_context(|| format!("failed to fetch training day with id={id}"))
+    .map_err(into_log_server_error!())?;
+
+    let Some((program_id,)) = program_id else {
+        return Ok(None);
+    };
+
+    let Some(owner_id) = lock_program(program_id, owner_id, &mut **tx).await? else {
+        return Ok(None);
+    };
+
+    let deleted = sqlx::query_as::<_, TrainingDay>(formatcp!(
+        "{DELETE_FROM} {TABLE} WHERE id = $1 AND owner_id = $2 RETURNING {COLS}"
+    ))
+    .bind(id)
+    .bind(owner_id)
+    .fetch_optional((&mut **tx).instrument_executor(db_span!(DELETE_FROM, TABLE)))
+    .await
+    .with_context(|| format!("failed to delete training day with id={id}"))
+    .map_err(into_log_server_error!())?;
+
+    if let Some(deleted) = &deleted {
+        sqlx::query(formatcp!(
+            "{UPDATE} {TABLE} SET position = position - 1 WHERE program_id = $1 AND position > $2 AND owner_id = $3"
+        ))
+        .bind(deleted.program_id)
+        .bind(deleted.position)
+        .bind(owner_id)
+        .execute((&mut **tx).instrument_executor(db_span!(UPDATE, TABLE)))
+        .await
+        .with_context(|| format!("failed to renumber training days of program with id={program_id}"))
+        .map_err(into_log_server_error!())?;
+    }
+
+    Ok(deleted)
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderTrainingDays {
+    pub program_id: Uuid,
+    #[validate(range(min = 0))]
+    pub from: usize,
+    #[validate(range(min = 0))]
+    pub to: usize,
+}
+
+impl ReorderTrainingDays {
+    /// Move a training day to another position in the rotation, returning the training days in their new order
+    pub async fn reorder(
+        &self,
+        owner_id: OwnerId,
+        tx: &mut Transaction<'_, DB>,
+    ) -> OperationResult<Option<Vec<TrainingDay>>> {
+        let Some(owner_id) = lock_program(self.program_id, owner_id, &mut **tx).await? else {
+            return Ok(None);
+        };
+
+        let mut days =
+            TrainingDay::select_for_program(self.program_id, owner_id, &mut **tx).await?;
+
+        if self.from >= days.len() || self.to >= days.len() {
+            return Err(ErrorWithStatus::new(
+                StatusCode::CONFLICT,
+                anyhow!("index out of bounds"),
+            ));
+        }
+
+        if days.move_within(self.from, self.to) {
+            let ids: Vec<_> = days.iter().map(|day| day.id).collect();
+
+            sqlx::query(formatcp!(
+                "{UPDATE} {TABLE} d SET position = o.index - 1
+                FROM unnest($1::UUID[]) WITH ORDINALITY o(id, index)
+                WHERE d.id = o.id AND d.program_id = $2 AND d.owner_id = $3"
+            ))
+            .bind(&ids)
+            .bind(self.program_id)
+            .bind(owner_id)
+            .execute((&mut **tx).instrument_executor(db_span!(UPDATE, TABLE)))
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to reorder training days of program with id={}",
+                    self.program_id
+                )
+            })
+            .map_err(into_log_server_error!())?;
+
+            for (position, day) in (0..).zip(days.iter_mut()) {
+                day.position = position;
+            }
+        }
+
+        Ok(Some(days))
+    }
+}
+
+/// A training day that a profile completed
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainingLog {
+    pub id: Uuid,
+    pub profile_id: Uuid,
+    pub program_id: Uuid,
+    pub training_day_id: Uuid,
+    #[schema(value_type = i64)]
+    #[serde(with = "ts_milliseconds")]
+    pub completed_on: NaiveDateTime,
+}
+
+const LOG_COLS: &str = "id, profile_id, program_id, training_day_id, completed_on";
+
+impl TrainingLog {
+    /// Record that the profile of a training day's program completed it
+    pub async fn complete(
+        training_day_id: Uuid,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{INSERT_INTO} {LOGS_TABLE} (profile_id, program_id, training_day_id, owner_id)
+            {SELECT} {PROFILE_ID}, program_id, id, owner_id FROM {TABLE} WHERE id = $1 AND {}
+            RETURNING {LOG_COLS}",
+            has_access!(Write, PROFILE_ID, "$2")
+        ))
+        .bind(training_day_id)
+        .bind(owner_id)
+        .fetch_optional(executor.instrument_executor(db_span!(INSERT_INTO, LOGS_TABLE)))
+        .await
+        .with_context(|| format!("failed to complete training day with id={training_day_id}"))
+        .map_err(into_log_server_error!())
+    }
+
+    /// Select the training day that a profile most recently completed, optionally only for one program
+    pub async fn select_latest(
+        profile_id: Uuid,
+        program_id: Option<Uuid>,
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Option<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} {LOG_COLS} FROM {LOGS_TABLE}
+            WHERE profile_id = $1 AND ($2::UUID IS NULL OR program_id = $2) AND {}
+            ORDER BY completed_on DESC LIMIT 1",
+            has_access!(Read, "profile_id", "$3")
+        ))
+        .bind(profile_id)
+        .bind(program_id)
+        .bind(owner_id)
+        .fetch_optional(executor.instrument_executor(db_span!(SELECT, LOGS_TABLE)))
+        .await
+        .with_context(|| format!("failed to fetch latest training log for profile_id={profile_id}"))
+        .map_err(into_log_server_error!())
+    }
+
+    pub async fn select_all(
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<Vec<Self>> {
+        sqlx::query_as::<_, Self>(formatcp!(
+            "{SELECT} {LOG_COLS} FROM {LOGS_TABLE} WHERE owner_id = $1 ORDER BY completed_on"
+        ))
+        .bind(owner_id)
+        .fetch_all(executor.instrument_executor(db_span!(SELECT, LOGS_TABLE)))
+        .await
+        .context("failed to select all training logs")
+        .map_err(into_log_server_error!())
+    }
+
+    /// Insert existing logs, keeping when they were completed.
+    ///
+    /// The ids are ignored, and new ones are generated. This does _NOT_ check access to the profiles or programs.
+    pub async fn insert_many(
+        logs: &[Self],
+        owner_id: OwnerId,
+        executor: impl Executor<'_, Database = DB>,
+    ) -> OperationResult<()> {
+        // sqlx does not yet support providing iterators for bound data.
+        let profile_ids: Vec<_> = logs.iter().map(|l| l.profile_id).collect();
+        let program_ids: Vec<_> = logs.iter().map(|l| l.program_id).collect();
+        let training_day_ids: Vec<_> = logs.iter().map(|l| l.training_day_id).collect();
+        let completed_on: Vec<_> = logs.iter().map(|l| l.completed_on).collect();
+
+        sqlx::query(formatcp!(
+            "{INSERT_INTO} {LOGS_TABLE} (profile_id, program_id, training_day_id, completed_on, owner_id)
+            VALUES (
+                unnest($1),
+                unnest($2),
+                unnest($3),
+                unnest($4),
+                $5
+            )"
+        ))
+        .bind(&profile_ids)
+        .bind(&program_ids)
+        .bind(&training_day_ids)
+        .bind(&completed_on)
+        .bind(owner_id)
+        .execute(executor.instrument_executor(db_span!(INSERT_INTO, LOGS_TABLE)))
+        .await
+        .context("failed to insert training logs")
+        .map_err(into_log_server_error!())
+        .map(|_| ())
+    }
+}
+
+/// The training day a profile should do next
+#[derive(Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NextTrainingDay {
+    pub training_day: TrainingDay,
+    /// The last training day of the program that the profile completed, if any
+    pub last_completed: Option<TrainingLog>,
+}
+
+/// Find the training day after the one a profile last completed, wrapping around to the start of the rotation.
+///
+/// Without a program, the program the profile last completed a training day of is used.
+pub async fn next_training_day(
+    profile_id: Uuid,
+    program_id: Option<Uuid>,
+    owner_id: OwnerId,
+    tx: &mut Transaction<'_, DB>,
+) -> OperationResult<Option<NextTrainingDay>> {
+    let last_completed =
+        TrainingLog::select_latest(profile_id, program_id, owner_id, &mut **tx).await?;
+
+    let Some(program_id) = program_id.or(last_completed.as_ref().map(|log| log.program_id)) else {
+        return Ok(None);
+    };
+
+    let last_day_id = last_completed.as_ref().map(|log| log.training_day_id);
+
+    let training_day = sqlx::query_as::<_, TrainingDay>(formatcp!(
+        "{SELECT} {COLS} FROM {TABLE}
+        WHERE program_id = $1 AND {PROFILE_ID} = $2 AND {}
+        ORDER BY position <= COALESCE(({SELECT} d.position FROM {TABLE} d WHERE d.id = $4), -1), position
+        LIMIT 1",
+        has_access!(Read, PROFILE_ID, "$3")
+    ))
+    .bind(program_id)
+    .bind(profile_id)
+    .bind(owner_id)
+    .bind(last_day_id)
+    .fetch_optional((&mut **tx).instrument_executor(db_span!(SELECT, TABLE)))
+    .await
+    .with_context(|| {
+        format!("failed to fetch next training day for profile_id={profile_id} and program_id={program_id}")
+    })
+    .map_err(into_log_server_error!())?;
+
+    Ok(training_day.map(|training_day| NextTrainingDay {
+        training_day,
+        last_completed,
+    }))
+}
@@ -0,0 +1,171 @@
+use const_format::concatcp;
+use utoipa::{
+    openapi::{
+        path::{OperationBuilder, PathItemBuilder},
+        request_body::RequestBodyBuilder,
+        response::Response,
+        ComponentsBuilder, PathItemType, PathsBuilder, ResponseBuilder,
+    },
+    IntoParams, PartialSchema, ToSchema,
+};
+
+use crate::{
+    openapi::{
+        extensions::{created, id_path_param, no_content, ok, param_in_default, JsonContent},
+        Customizer,
+    },
+    router::TRAINING_DAYS_PATH,
+};
+
+use super::{
+    handler::{NextTrainingDayQuery, TrainingDaysQuery},
+    model::{
+        CreateTrainingDay, NextTrainingDay, ReorderTrainingDays, TrainingDay, TrainingLog,
+        UpdateTrainingDay,
+    },
+};
+
+pub struct TrainingDaysModule;
+
+fn training_day_response() -> Response {
+    ResponseBuilder::new()
+        .json_content(TrainingDay::schema().1)
+        .build()
+}
+
+fn training_days_response() -> Response {
+    ResponseBuilder::new()
+        .json_content(Vec::<TrainingDay>::schema())
+        .build()
+}
+
+const TAG: &str = "Training Days";
+
+impl Customizer<ComponentsBuilder> for TrainingDaysModule {
+    fn customize(builder: ComponentsBuilder) -> ComponentsBuilder {
+        builder
+            .schema_from::<TrainingDay>()
+            .schema_from::<CreateTrainingDay>()
+            .schema_from::<UpdateTrainingDay>()
+            .schema_from::<ReorderTrainingDays>()
+            .schema_from::<TrainingLog>()
+            .schema_from::<NextTrainingDay>()
+    }
+}
+
+impl Customizer<PathsBuilder> for TrainingDaysModule {
+    fn customize(builder: PathsBuilder) -> PathsBuilder {
+        let get_op = OperationBuilder::new()
+            .description(Some("List the training days of a program, in order"))
+            .parameters(Some(TrainingDaysQuery::into_params(param_in_default)))
+            .response(ok(), training_days_response())
+            .tag(TAG)
+            .build();
+
+        let post_op = OperationBuilder::new()
+            .description(Some(
+                "Add a training day to the end of a program's rotation",
+            ))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(CreateTrainingDay::schema().1)
+                    .build(),
+            ))
+            .response(created(), training_day_response())
+            .tag(TAG)
+            .build();
+
+        let put_op = OperationBuilder::new()
+            .description(Some("Rename a training day"))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(UpdateTrainingDay::schema().1)
+                    .build(),
+            ))
+            .response(ok(), training_day_response())
+            .tag(TAG)
+            .build();
+
+        let delete_op = OperationBuilder::new()
+            .description(Some("Delete a training day along with its sets"))
+            .parameters(id_path_param(Some("The training day to delete")))
+            .response(no_content(), Response::new("no content"))
+            .tag(TAG)
+            .build();
+
+        let reorder_op = OperationBuilder::new()
+            .description(Some(
+                "Move a training day to another position in the rotation",
+            ))
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .json_content(ReorderTrainingDays::schema().1)
+                    .build(),
+            ))
+            .response(ok(), training_days_response())
+            .tag(TAG)
+            .build();
+
+        let complete_op = OperationBuilder::new()
+            .description(Some(
+                "Record that the profile of the program completed a training day",
+            ))
+            .parameters(id_path_param(Some("The training day that was completed")))
+            .response(
+                created(),
+                ResponseBuilder::new()
+                    .json_content(TrainingLog::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        let next_op = OperationBuilder::new()
+            .description(Some(
+                "Find the training day that comes after the one a profile last completed",
+            ))
+            .parameters(Some(NextTrainingDayQuery::into_params(param_in_default)))
+            .response(
+                ok(),
+                ResponseBuilder::new()
+                    .json_content(NextTrainingDay::schema().1)
+                    .build(),
+            )
+            .tag(TAG)
+            .build();
+
+        builder
+            .path(
+                TRAINING_DAYS_PATH,
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, get_op)
+                    .operation(PathItemType::Post, post_op)
+                    .operation(PathItemType::Put, put_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(TRAINING_DAYS_PATH, "/next"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Get, next_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(TRAINING_DAYS_PATH, "/reorder"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, reorder_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(TRAINING_DAYS_PATH, "/{id}"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Delete, delete_op)
+                    .build(),
+            )
+            .path(
+                concatcp!(TRAINING_DAYS_PATH, "/{id}/complete"),
+                PathItemBuilder::new()
+                    .operation(PathItemType::Post, complete_op)
+                    .build(),
+            )
+    }
+}
@@ -0,0 +1,29 @@
+use axum::{
+    extract::FromRef,
+    routing::{delete, get, post},
+    Router,
+};
+
+use crate::{db::Pool, router::State};
+
+use super::handler::{
+    complete_training_day, create_training_day, delete_training_day, next_training_day,
+    reorder_training_days, training_days_index, update_training_day,
+};
+
+pub fn router<S: State>() -> Router<S>
+where
+    Pool: FromRef<S>,
+{
+    Router::new()
+        .route(
+            "/",
+            get(training_days_index)
+                .post(create_training_day)
+                .put(update_training_day),
+        )
+        .route("/next", get(next_training_day))
+        .route("/reorder", post(reorder_training_days))
+        .route("/:id", delete(delete_training_day))
+        .route("/:id/complete", post(complete_training_day))
+}
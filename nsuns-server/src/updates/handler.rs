@@ -10,6 +10,7 @@ use crate::{
     db::{commit_ok, Pool, DB},
     error::{extract::WithErrorRejection, OperationResult},
     maxes::model::{delete_latest_maxes, CreateMax, Max},
+    quotas::settings::QuotaSettings,
     reps::model::{delete_latest_reps, CreateReps, Reps},
     transaction,
 };
@@ -41,6 +42,7 @@ async fn run_updates(
     tx: &mut Transaction<'_, DB>,
     updates: Updates,
     owner_id: OwnerId,
+    quotas: &QuotaSettings,
 ) -> OperationResult<UpdatedState> {
     let mut new_maxes = Vec::<Max>::with_capacity(updates.movement_ids.len());
     let mut new_reps = Vec::<Reps>::with_capacity(updates.movement_ids.len());
@@ -60,7 +62,7 @@ async fn run_updates(
                 movement_id: latest_max.movement_id,
                 profile_id: latest_max.profile_id,
             }
-            .insert_one(owner_id, tx)
+            .insert_one(owner_id, quotas, tx)
             .await?;
 
             let new_rep = CreateReps {
@@ -68,7 +70,7 @@ async fn run_updates(
                 movement_id: latest_max.movement_id,
                 profile_id: latest_max.profile_id,
             }
-            .insert_one(owner_id, tx)
+            .insert_one(owner_id, quotas, tx)
             .await?;
 
             new_maxes.push(new_max);
@@ -85,11 +87,14 @@ async fn run_updates(
 #[tracing::instrument(skip_all)]
 pub async fn updates(
     State(pool): State<Pool>,
+    State(quotas): State<QuotaSettings>,
     owner_id: OwnerId,
     WithErrorRejection(Json(updates)): WithErrorRejection<Json<Updates>>,
 ) -> impl IntoResponse {
     let mut tx = transaction!(&pool).await?;
-    let res = run_updates(&mut tx, updates, owner_id).await.map(Json);
+    let res = run_updates(&mut tx, updates, owner_id, &quotas)
+        .await
+        .map(Json);
     commit_ok(res, tx).await
 }
 
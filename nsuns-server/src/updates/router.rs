@@ -1,12 +1,13 @@
 use axum::{extract::FromRef, routing::post, Router};
 
-use crate::{db::Pool, router::State};
+use crate::{db::Pool, quotas::settings::QuotaSettings, router::State};
 
 use super::handler::{undo, updates};
 
 pub fn router<S: State>() -> Router<S>
 where
     Pool: FromRef<S>,
+    QuotaSettings: FromRef<S>,
 {
     Router::new().route("/", post(updates).delete(undo))
 }
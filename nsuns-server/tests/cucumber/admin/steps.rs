@@ -0,0 +1,220 @@
+use cucumber::{given, then, when};
+use http::StatusCode;
+use nsuns_server::{
+    auth::user::{update_role, Role},
+    router::{ADMIN_PATH, AUTH_PATH},
+};
+use serde_json::json;
+
+use crate::{
+    admin::world::SetAsideSession,
+    common,
+    util::{Auth, JsonBody},
+    world::NsunsWorld,
+};
+
+#[given(regex = r#"^"(.*)" is an administrator"#)]
+async fn appoint_administrator(world: &mut NsunsWorld, username: String) {
+    let mut conn = common::connect(&world.database).await.unwrap();
+
+    assert!(update_role(&username, Role::Admin, &mut conn)
+        .await
+        .unwrap());
+}
+
+#[given("I set my session aside")]
+async fn set_session_aside(world: &mut NsunsWorld) {
+    world.admin_world.set_aside_session = Some(SetAsideSession {
+        auth_cookie: world.auth_cookie.take(),
+        csrf_token: world.csrf_token.take(),
+    });
+}
+
+/// Switch to the session that was set aside, setting the current one aside in its place
+#[when("I go back to the session I set aside")]
+async fn resume_session(world: &mut NsunsWorld) {
+    let session = world
+        .admin_world
+        .set_aside_session
+        .as_mut()
+        .expect("No session was set aside");
+
+    std::mem::swap(&mut world.auth_cookie, &mut session.auth_cookie);
+    std::mem::swap(&mut world.csrf_token, &mut session.csrf_token);
+}
+
+#[when("I list all users")]
+async fn list_users(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .get(&format!("{ADMIN_PATH}/users"))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+    world.admin_world.users = if res.status().is_success() {
+        res.json().await
+    } else {
+        vec![]
+    };
+}
+
+async fn user_action(world: &mut NsunsWorld, username: &str, action: &str) {
+    list_users(world).await;
+
+    let id = world
+        .admin_world
+        .user_by_name(username)
+        .expect("User is not listed")
+        .id;
+
+    let url = format!("{ADMIN_PATH}/users/{id}/{action}");
+
+    let req = if action == "sessions" {
+        world.client.delete(&url)
+    } else {
+        world.client.post(&url)
+    };
+
+    let res = req.authed(world).send().await;
+
+    world.auth_world.status = Some(res.status());
+
+    if action == "reset-token" && res.status().is_success() {
+        world.admin_world.reset_token = Some(res.json().await);
+    }
+}
+
+#[when(regex = r#"^I disable the user "(.*)""#)]
+async fn disable_user(world: &mut NsunsWorld, username: String) {
+    user_action(world, &username, "disable").await;
+}
+
+#[when(regex = r#"^I enable the user "(.*)""#)]
+async fn enable_user(world: &mut NsunsWorld, username: String) {
+    user_action(world, &username, "enable").await;
+}
+
+#[when(regex = r#"^I revoke every session of "(.*)""#)]
+async fn revoke_sessions(world: &mut NsunsWorld, username: String) {
+    user_action(world, &username, "sessions").await;
+}
+
+#[when(regex = r#"^I create a password reset token for "(.*)""#)]
+async fn create_reset_token(world: &mut NsunsWorld, username: String) {
+    user_action(world, &username, "reset-token").await;
+}
+
+#[when(regex = r#"^The password is reset to "(.*)" with the token from the administrator"#)]
+async fn reset_with_admin_token(world: &mut NsunsWorld, new_password: String) {
+    let token = world.admin_world.unwrap_reset_token().token.clone();
+
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/password-reset"))
+        .json_body(&json!({ "token": token, "newPassword": new_password }))
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[then(regex = r#"^"(.*)" is listed as an? (user|admin) who was recently active"#)]
+async fn user_listed(world: &mut NsunsWorld, username: String, role: String) {
+    let user = world
+        .admin_world
+        .user_by_name(&username)
+        .expect("User is not listed");
+
+    let expected_role = if role == "admin" {
+        Role::Admin
+    } else {
+        Role::User
+    };
+
+    assert_eq!(expected_role, user.role);
+    assert!(!user.disabled);
+    assert!(user.last_activity.is_some());
+}
+
+#[when("I count owners")]
+async fn count_owners(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .get(&format!("{ADMIN_PATH}/owners"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    world.admin_world.owner_counts = Some(res.json().await);
+}
+
+#[then(regex = r"^There (?:is|are) (\d+) registered and (\d+) anonymous owners?")]
+async fn owners_counted(world: &mut NsunsWorld, registered: i64, anonymous: i64) {
+    let counts = world.admin_world.unwrap_owner_counts();
+
+    assert_eq!(registered, counts.registered);
+    assert_eq!(anonymous, counts.anonymous);
+    assert_eq!(0, counts.pending_cleanup);
+}
+
+#[given("The sets of my program have gaps in their order")]
+async fn spread_out_sets(world: &mut NsunsWorld) {
+    let mut conn = common::connect(&world.database).await.unwrap();
+
+    sqlx::query("UPDATE program_sets SET ordering = ordering * 2 + 1 WHERE program_id = $1")
+        .bind(world.program_world.unwrap_program_meta().id)
+        .execute(&mut conn)
+        .await
+        .unwrap();
+}
+
+#[given("The first set of my program is in a week that does not exist")]
+async fn move_set_out_of_program(world: &mut NsunsWorld) {
+    let mut conn = common::connect(&world.database).await.unwrap();
+
+    sqlx::query(
+        "UPDATE program_sets SET week = 9 WHERE id = (
+            SELECT id FROM program_sets WHERE program_id = $1 ORDER BY ordering LIMIT 1
+        )",
+    )
+    .bind(world.program_world.unwrap_program_meta().id)
+    .execute(&mut conn)
+    .await
+    .unwrap();
+}
+
+#[when(regex = r"^I (check|repair) program integrity")]
+async fn check_integrity(world: &mut NsunsWorld, action: String) {
+    let req = if action == "repair" {
+        world.client.post(&format!("{ADMIN_PATH}/integrity/repair"))
+    } else {
+        world.client.get(&format!("{ADMIN_PATH}/integrity"))
+    };
+
+    let res = req.authed(world).send().await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    world.admin_world.integrity_report = Some(res.json().await);
+}
+
+#[then(
+    regex = r"^(\d+) sets? (?:is|was|are|were) without a week and (\d+) (?:is|was|are|were) out of order"
+)]
+async fn integrity_problems(world: &mut NsunsWorld, without_week: usize, misordered: usize) {
+    let report = world.admin_world.unwrap_integrity_report();
+
+    assert_eq!(without_week, report.sets_without_week.len());
+    assert_eq!(misordered, report.misordered_sets.len());
+    assert!(report.sets_in_foreign_training_day.is_empty());
+    assert!(report.foreign_percentage_of_max.is_empty());
+}
+
+#[then("No program has integrity problems")]
+async fn no_integrity_problems(world: &mut NsunsWorld) {
+    assert_eq!(0, world.admin_world.unwrap_integrity_report().issues());
+}
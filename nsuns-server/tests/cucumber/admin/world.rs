@@ -0,0 +1,44 @@
+use nsuns_server::{
+    admin::model::{CreatedResetToken, OwnerCounts, UserSummary},
+    integrity::IntegrityReport,
+};
+
+/// The session cookie and CSRF token of a session that is not in use
+#[derive(Debug, Default)]
+pub struct SetAsideSession {
+    pub auth_cookie: Option<String>,
+    pub csrf_token: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AdminWorld {
+    pub users: Vec<UserSummary>,
+    pub owner_counts: Option<OwnerCounts>,
+    pub reset_token: Option<CreatedResetToken>,
+    pub set_aside_session: Option<SetAsideSession>,
+    pub integrity_report: Option<IntegrityReport>,
+}
+
+impl AdminWorld {
+    pub fn user_by_name(&self, username: &str) -> Option<&UserSummary> {
+        self.users.iter().find(|user| user.username == username)
+    }
+
+    pub fn unwrap_owner_counts(&self) -> &OwnerCounts {
+        self.owner_counts
+            .as_ref()
+            .expect("No owner counts injected into global state")
+    }
+
+    pub fn unwrap_integrity_report(&self) -> &IntegrityReport {
+        self.integrity_report
+            .as_ref()
+            .expect("No integrity report injected into global state")
+    }
+
+    pub fn unwrap_reset_token(&self) -> &CreatedResetToken {
+        self.reset_token
+            .as_ref()
+            .expect("No reset token injected into global state")
+    }
+}
@@ -0,0 +1,173 @@
+//! A minimal OpenID Connect identity provider, to test logging in without an external service.
+//!
+//! The `login_hint` passed to the authorization endpoint is used as the subject and username of the user.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Redirect},
+    routing::{get, post},
+    Form, Json, Router,
+};
+use chrono::{Duration, Utc};
+use http::StatusCode;
+use jsonwebtoken::{encode, EncodingKey, Header};
+use nsuns_server::auth::secret::{generate_secret, hash_secret};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::{net::TcpListener, sync::OnceCell};
+
+pub const CLIENT_ID: &str = "nsuns";
+
+const KEY_ID: &str = "test-key";
+
+static ISSUER_URL: OnceCell<String> = OnceCell::const_new();
+
+#[derive(Debug, Clone)]
+struct AuthorizationRequest {
+    subject: String,
+    nonce: String,
+    code_challenge: String,
+}
+
+#[derive(Clone)]
+struct IssuerState {
+    url: String,
+    codes: Arc<Mutex<HashMap<String, AuthorizationRequest>>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthorizeParams {
+    client_id: String,
+    redirect_uri: String,
+    state: String,
+    nonce: String,
+    code_challenge: String,
+    code_challenge_method: String,
+    login_hint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenParams {
+    grant_type: String,
+    code: String,
+    code_verifier: String,
+    client_id: String,
+}
+
+async fn discovery(State(state): State<IssuerState>) -> impl IntoResponse {
+    Json(json!({
+        "issuer": state.url,
+        "authorization_endpoint": format!("{}/authorize", state.url),
+        "token_endpoint": format!("{}/token", state.url),
+        "jwks_uri": format!("{}/jwks", state.url),
+    }))
+}
+
+async fn jwks() -> impl IntoResponse {
+    (
+        [("Content-Type", "application/json")],
+        include_str!("../../fixtures/oidc-issuer-jwks.json"),
+    )
+}
+
+async fn authorize(
+    State(state): State<IssuerState>,
+    Query(params): Query<AuthorizeParams>,
+) -> impl IntoResponse {
+    assert_eq!(CLIENT_ID, params.client_id);
+    assert_eq!("S256", params.code_challenge_method);
+
+    let code = generate_secret();
+
+    state.codes.lock().unwrap().insert(
+        code.clone(),
+        AuthorizationRequest {
+            subject: params.login_hint,
+            nonce: params.nonce,
+            code_challenge: params.code_challenge,
+        },
+    );
+
+    Redirect::to(&format!(
+        "{}?code={code}&state={}",
+        params.redirect_uri, params.state
+    ))
+}
+
+async fn token(
+    State(state): State<IssuerState>,
+    Form(params): Form<TokenParams>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let request = state
+        .codes
+        .lock()
+        .unwrap()
+        .remove(&params.code)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    if params.grant_type != "authorization_code"
+        || params.client_id != CLIENT_ID
+        || hash_secret(&params.code_verifier) != request.code_challenge
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut header = Header::new(jsonwebtoken::Algorithm::RS256);
+    header.kid = Some(KEY_ID.to_string());
+
+    let key =
+        EncodingKey::from_rsa_pem(include_bytes!("../../fixtures/oidc-issuer-key.pem")).unwrap();
+
+    let now = Utc::now();
+
+    let id_token = encode(
+        &header,
+        &json!({
+            "iss": state.url,
+            "sub": request.subject,
+            "aud": CLIENT_ID,
+            "iat": now.timestamp(),
+            "exp": (now + Duration::minutes(5)).timestamp(),
+            "nonce": request.nonce,
+            "preferred_username": request.subject,
+        }),
+        &key,
+    )
+    .unwrap();
+
+    Ok(Json(json!({
+        "access_token": generate_secret(),
+        "token_type": "Bearer",
+        "id_token": id_token,
+    })))
+}
+
+/// Start the mock issuer if it is not already running, and return its URL
+pub async fn issuer_url() -> String {
+    ISSUER_URL
+        .get_or_init(|| async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+
+            let router = Router::new()
+                .route("/.well-known/openid-configuration", get(discovery))
+                .route("/jwks", get(jwks))
+                .route("/authorize", get(authorize))
+                .route("/token", post(token))
+                .with_state(IssuerState {
+                    url: url.clone(),
+                    codes: Default::default(),
+                });
+
+            tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+            url
+        })
+        .await
+        .clone()
+}
@@ -1 +1,3 @@
+pub mod issuer;
 pub mod steps;
+pub mod world;
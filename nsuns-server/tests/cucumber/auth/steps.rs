@@ -1,9 +1,51 @@
-use cucumber::given;
+use std::collections::HashMap;
+
+use axum_test_helper::TestResponse;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{Duration, Utc};
+use cucumber::{given, then, when};
 use http::StatusCode;
-use nsuns_server::router::AUTH_PATH;
+use nsuns_server::{
+    auth::{
+        access_token::{AccessTokenScope, CreateAccessToken},
+        archive::model::Archive,
+        csrf::CSRF_COOKIE_NAME,
+        token::COOKIE_NAME,
+        two_factor::{
+            model::{CompleteLogin, RecoveryCodes, SecondFactor, TotpEnrollment},
+            totp::code_at,
+        },
+        user::AgentInfo,
+    },
+    profiles::model::CreateProfile,
+    router::{AUTH_PATH, OIDC_PATH, PROFILES_PATH},
+};
+use serde_json::{json, Value};
 use tower_cookies::Cookie;
+use uuid::Uuid;
+
+use crate::{
+    util::{Auth, JsonBody},
+    world::NsunsWorld,
+};
 
-use crate::world::NsunsWorld;
+fn find_cookie(res: &TestResponse, name: &str) -> Option<String> {
+    res.headers()
+        .get_all("Set-Cookie")
+        .iter()
+        .map(|header| Cookie::parse(header.to_str().unwrap().to_owned()).unwrap())
+        .find(|cookie| cookie.name() == name)
+        .map(|cookie| cookie.value().to_owned())
+}
+
+fn store_auth_cookie(world: &mut NsunsWorld, res: &TestResponse) {
+    if let Some(cookie) = find_cookie(res, COOKIE_NAME) {
+        world.auth_cookie = Some(cookie)
+    }
+    if let Some(csrf_token) = find_cookie(res, CSRF_COOKIE_NAME) {
+        world.csrf_token = Some(csrf_token)
+    }
+}
 
 #[given(regex = r#"I am an anonymous user"#)]
 async fn anonymous(world: &mut NsunsWorld) {
@@ -13,10 +55,681 @@ async fn anonymous(world: &mut NsunsWorld) {
         .send()
         .await;
 
-    if let Some(header) = res.headers().get("Set-Cookie") {
-        let cookie = Cookie::parse(header.to_str().unwrap()).unwrap();
-        world.auth_cookie = Some(cookie.value().to_owned())
-    }
+    store_auth_cookie(world, &res);
 
     assert_eq!(StatusCode::NO_CONTENT, res.status());
 }
+
+#[when(regex = r#"^I register with username "(.*)" and password "(.*)""#)]
+#[given(regex = r#"^I am registered with username "(.*)" and password "(.*)""#)]
+async fn register(world: &mut NsunsWorld, username: String, password: String) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/register"))
+        .json_body(&json!({ "username": username, "password": password }))
+        .authed(world)
+        .send()
+        .await;
+
+    store_auth_cookie(world, &res);
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when("I fetch my user info")]
+async fn fetch_user_info(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .get(&format!("{AUTH_PATH}/user-info"))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+    world.auth_world.agent_info = if res.status().is_success() {
+        Some(res.json().await)
+    } else {
+        None
+    };
+}
+
+#[then(regex = r#"^I am logged in as "(.*)""#)]
+async fn logged_in_as(world: &mut NsunsWorld, username: String) {
+    match &world.auth_world.agent_info {
+        Some(AgentInfo::User(user)) => assert_eq!(username, user.username),
+        _ => panic!("Not logged in as a user"),
+    }
+}
+
+#[then(regex = r"^The request fails with status (\d+)")]
+async fn request_fails(world: &mut NsunsWorld, status: u16) {
+    assert_eq!(status, world.auth_world.unwrap_status().as_u16());
+}
+
+#[when(regex = r#"^I claim my account with username "(.*)" and password "(.*)""#)]
+async fn claim(world: &mut NsunsWorld, username: String, password: String) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/claim"))
+        .json_body(&json!({ "username": username, "password": password }))
+        .authed(world)
+        .send()
+        .await;
+
+    store_auth_cookie(world, &res);
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when(regex = r#"^I log in with username "(.*)" and password "(.*)""#)]
+async fn login(world: &mut NsunsWorld, username: String, password: String) {
+    let credentials = STANDARD.encode(format!("{username}:{password}"));
+
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/login"))
+        .header("Authorization", &format!("Basic {credentials}"))
+        .send()
+        .await;
+
+    store_auth_cookie(world, &res);
+
+    world.auth_world.status = Some(res.status());
+    world.auth_world.retry_after = res
+        .headers()
+        .get("Retry-After")
+        .map(|value| value.to_str().unwrap().parse().unwrap());
+    world.auth_world.login_challenge = if res.status() == StatusCode::ACCEPTED {
+        Some(res.json().await)
+    } else {
+        None
+    };
+}
+
+#[when(regex = r#"^I fail to log in as "(.*)" (\d+) times"#)]
+async fn fail_login(world: &mut NsunsWorld, username: String, times: usize) {
+    for _ in 0..times {
+        login(world, username.clone(), "wrong password".to_owned()).await;
+        assert_eq!(StatusCode::UNAUTHORIZED, world.auth_world.unwrap_status());
+    }
+}
+
+#[then("I am asked to retry later")]
+async fn asked_to_retry(world: &mut NsunsWorld) {
+    assert_eq!(
+        StatusCode::TOO_MANY_REQUESTS,
+        world.auth_world.unwrap_status()
+    );
+    assert!(world
+        .auth_world
+        .retry_after
+        .is_some_and(|seconds| seconds > 0));
+}
+
+#[when(regex = r#"^I change my password from "(.*)" to "(.*)""#)]
+async fn change_password(world: &mut NsunsWorld, current_password: String, new_password: String) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/password"))
+        .json_body(&json!({ "currentPassword": current_password, "newPassword": new_password }))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when(regex = r#"^I reset my password to "(.*)" with token "(.*)""#)]
+async fn reset_password(world: &mut NsunsWorld, new_password: String, token: String) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/password-reset"))
+        .json_body(&json!({ "token": token, "newPassword": new_password }))
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[then(regex = r"^The request succeeds with status (\d+)")]
+async fn request_succeeds(world: &mut NsunsWorld, status: u16) {
+    assert_eq!(status, world.auth_world.unwrap_status().as_u16());
+}
+
+#[when("I list my sessions")]
+async fn list_sessions(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .get(&format!("{AUTH_PATH}/sessions"))
+        .header("User-Agent", "nsuns-tests")
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+    world.auth_world.sessions = if res.status().is_success() {
+        res.json().await
+    } else {
+        vec![]
+    };
+}
+
+#[then(regex = r"^I have (\d+) active sessions?")]
+async fn active_sessions(world: &mut NsunsWorld, count: usize) {
+    assert_eq!(count, world.auth_world.sessions.len());
+}
+
+#[then(regex = r#"^My current session was used by "(.*)""#)]
+async fn current_session_user_agent(world: &mut NsunsWorld, user_agent: String) {
+    let current = world
+        .auth_world
+        .sessions
+        .iter()
+        .find(|session| session.current)
+        .expect("current session is listed");
+
+    assert_eq!(Some(user_agent), current.user_agent);
+}
+
+#[when("I revoke my other session")]
+async fn revoke_other_session(world: &mut NsunsWorld) {
+    let id = world
+        .auth_world
+        .sessions
+        .iter()
+        .find(|session| !session.current)
+        .expect("another session is listed")
+        .id;
+
+    let res = world
+        .client
+        .delete(&format!("{AUTH_PATH}/sessions/{id}"))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when("I log out everywhere")]
+async fn logout_everywhere(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .delete(&format!("{AUTH_PATH}/sessions"))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when(regex = r#"^I create a (read-only|read-write) access token named "(.*)""#)]
+#[given(regex = r#"^I have a (read-only|read-write) access token named "(.*)""#)]
+async fn create_access_token(world: &mut NsunsWorld, scope: String, name: String) {
+    let scope = match scope.as_str() {
+        "read-only" => AccessTokenScope::ReadOnly,
+        _ => AccessTokenScope::ReadWrite,
+    };
+
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/tokens"))
+        .json_body(&CreateAccessToken { name, scope })
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+    if res.status().is_success() {
+        world.auth_world.access_token = Some(res.json().await);
+    }
+}
+
+#[when("I revoke my access token")]
+async fn revoke_access_token(world: &mut NsunsWorld) {
+    let id = world.auth_world.unwrap_access_token().access_token.id;
+
+    let res = world
+        .client
+        .delete(&format!("{AUTH_PATH}/tokens/{id}"))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when("I authenticate with my access token")]
+async fn use_access_token(world: &mut NsunsWorld) {
+    world.bearer_token = Some(world.auth_world.unwrap_access_token().token.clone());
+    world.auth_cookie = None;
+}
+
+#[when(regex = r#"^I try to create a profile with name "(.*)""#)]
+async fn try_create_profile(world: &mut NsunsWorld, name: String) {
+    let res = world
+        .client
+        .post(PROFILES_PATH)
+        .json_body(&CreateProfile { name })
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+async fn complete_oidc_login(world: &mut NsunsWorld, query: &str, state_cookie: Option<String>) {
+    let mut cookies = vec![];
+    if let Some(state) = state_cookie {
+        cookies.push(Cookie::new("OIDC_STATE", state).encoded().to_string());
+    }
+    if let Some(auth_cookie) = &world.auth_cookie {
+        cookies.push(
+            Cookie::new(COOKIE_NAME, auth_cookie.clone())
+                .encoded()
+                .to_string(),
+        );
+    }
+
+    let res = world
+        .client
+        .get(&format!("{OIDC_PATH}/callback?{query}"))
+        .header("Cookie", &cookies.join("; "))
+        .send()
+        .await;
+
+    store_auth_cookie(world, &res);
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when(regex = r#"^I log in with the identity provider as "(.*)""#)]
+#[given(regex = r#"^I am logged in with the identity provider as "(.*)""#)]
+async fn oidc_login(world: &mut NsunsWorld, subject: String) {
+    let res = world
+        .client
+        .get(&format!("{OIDC_PATH}/login"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::SEE_OTHER, res.status());
+
+    let state_cookie = find_cookie(&res, "OIDC_STATE");
+    let authorization_url = res.headers().get("Location").unwrap().to_str().unwrap();
+
+    // log in at the identity provider, which redirects back to the callback
+    let provider_res = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(format!("{authorization_url}&login_hint={subject}"))
+        .send()
+        .await
+        .unwrap();
+
+    let callback_url = provider_res.headers()["Location"].to_str().unwrap();
+    let (_, query) = callback_url.split_once('?').unwrap();
+
+    complete_oidc_login(world, query, state_cookie).await;
+}
+
+#[when(regex = r#"^I complete an identity provider login with state "(.*)""#)]
+async fn forged_oidc_login(world: &mut NsunsWorld, state: String) {
+    complete_oidc_login(world, &format!("code=forged&state={state}"), None).await;
+}
+
+fn decode_base32(encoded: &str) -> Vec<u8> {
+    const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut bytes = vec![];
+    let mut buffer = 0_u32;
+    let mut bits = 0;
+
+    for c in encoded.chars() {
+        buffer = (buffer << 5) | ALPHABET.find(c).unwrap() as u32;
+        bits += 5;
+
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    bytes
+}
+
+#[given("I have enabled two-factor authentication")]
+async fn enable_two_factor(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/two-factor/enroll"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    let enrollment: TotpEnrollment = res.json().await;
+    assert!(enrollment.provisioning_uri.starts_with("otpauth://totp/"));
+
+    let secret = decode_base32(&enrollment.secret);
+
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/two-factor/confirm"))
+        .json_body(&SecondFactor {
+            code: code_at(&secret, Utc::now()),
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    let codes: RecoveryCodes = res.json().await;
+
+    world.auth_world.totp_secret = Some(secret);
+    world.auth_world.recovery_codes = codes.recovery_codes;
+}
+
+#[then("I am asked for a second factor")]
+async fn asked_for_second_factor(world: &mut NsunsWorld) {
+    assert_eq!(StatusCode::ACCEPTED, world.auth_world.unwrap_status());
+    world.auth_world.unwrap_login_challenge();
+}
+
+async fn complete_login(world: &mut NsunsWorld, code: String) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/login/two-factor"))
+        .json_body(&CompleteLogin {
+            challenge: world.auth_world.unwrap_login_challenge().challenge.clone(),
+            code,
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    store_auth_cookie(world, &res);
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when("I enter the code from my authenticator")]
+async fn enter_totp_code(world: &mut NsunsWorld) {
+    // the current code was used to confirm enrollment, so use the next one, which is accepted for clock drift
+    let code = code_at(
+        world.auth_world.unwrap_totp_secret(),
+        Utc::now() + Duration::seconds(30),
+    );
+
+    complete_login(world, code).await;
+}
+
+#[when(regex = r"^I enter recovery code (\d+)")]
+async fn enter_recovery_code(world: &mut NsunsWorld, index: usize) {
+    let code = world.auth_world.recovery_codes[index - 1].clone();
+
+    complete_login(world, code).await;
+}
+
+#[when(regex = r"^I enter a wrong code (\d+) times")]
+async fn enter_wrong_code(world: &mut NsunsWorld, times: usize) {
+    for _ in 0..times {
+        complete_login(world, "not-a-recovery-code".to_owned()).await;
+        assert_eq!(StatusCode::FORBIDDEN, world.auth_world.unwrap_status());
+    }
+}
+
+async fn disable_two_factor(world: &mut NsunsWorld, code: String) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/two-factor/disable"))
+        .json_body(&SecondFactor { code })
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when(regex = r"^I disable two-factor authentication with recovery code (\d+)")]
+async fn disable_two_factor_with_recovery_code(world: &mut NsunsWorld, index: usize) {
+    let code = world.auth_world.recovery_codes[index - 1].clone();
+
+    disable_two_factor(world, code).await;
+}
+
+#[when(regex = r"^I try to disable two-factor authentication with a wrong code (\d+) times")]
+async fn disable_two_factor_with_wrong_code(world: &mut NsunsWorld, times: usize) {
+    for attempt in 1..=times {
+        if attempt > 1 {
+            assert_eq!(StatusCode::FORBIDDEN, world.auth_world.unwrap_status());
+        }
+        disable_two_factor(world, "not-a-recovery-code".to_owned()).await;
+    }
+}
+
+#[when("I fetch the session signing keys")]
+async fn fetch_jwks(world: &mut NsunsWorld) {
+    let res = world.client.get(&format!("{AUTH_PATH}/jwks")).send().await;
+
+    world.auth_world.status = Some(res.status());
+    world.auth_world.jwks = if res.status().is_success() {
+        Some(res.json().await)
+    } else {
+        None
+    };
+}
+
+#[then(regex = r#"^The signing keys only include the public key "(.*)""#)]
+async fn jwks_include(world: &mut NsunsWorld, kid: String) {
+    let jwks = world
+        .auth_world
+        .jwks
+        .as_ref()
+        .expect("No signing keys injected into global state");
+
+    assert_eq!(1, jwks.keys.len());
+    assert!(jwks.find(&kid).is_some());
+}
+
+#[when("I forget my CSRF token")]
+async fn forget_csrf_token(world: &mut NsunsWorld) {
+    world.csrf_token = None;
+}
+
+#[when(regex = r#"^I delete my account with password "(.*)""#)]
+async fn delete_account(world: &mut NsunsWorld, password: String) {
+    let res = world
+        .client
+        .delete(&format!("{AUTH_PATH}/account"))
+        .json_body(&json!({ "password": password }))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when("I delete my account without a password")]
+async fn delete_account_without_password(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .delete(&format!("{AUTH_PATH}/account"))
+        .json_body(&json!({}))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+async fn export(world: &NsunsWorld) -> Archive {
+    let res = world
+        .client
+        .get(&format!("{AUTH_PATH}/export"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    res.json().await
+}
+
+#[when("I export my data")]
+async fn export_data(world: &mut NsunsWorld) {
+    world.auth_world.archive = Some(export(world).await);
+}
+
+#[when("I restore my exported data")]
+async fn restore_data(world: &mut NsunsWorld) {
+    let res = world
+        .client
+        .post(&format!("{AUTH_PATH}/restore"))
+        .json_body(world.auth_world.unwrap_archive())
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when("I change every max in my exported data to be negative")]
+async fn negate_archived_maxes(world: &mut NsunsWorld) {
+    let archive = world
+        .auth_world
+        .archive
+        .as_mut()
+        .expect("No archive injected into global state");
+
+    for max in &mut archive.maxes {
+        max.amount = -max.amount;
+    }
+}
+
+/// The contents of an archive, with ids replaced by names, since restoring creates new ids
+fn archive_contents(archive: &Archive) -> Value {
+    let profiles: HashMap<Uuid, &str> = archive
+        .profiles
+        .iter()
+        .map(|profile| (profile.id, profile.name.as_str()))
+        .collect();
+
+    let movements: HashMap<Uuid, &str> = archive
+        .movements
+        .iter()
+        .map(|movement| (movement.id, movement.name.as_str()))
+        .collect();
+
+    let describe_sets = |set_ids: &Vec<Uuid>| -> Vec<Value> {
+        set_ids
+            .iter()
+            .map(|set_id| {
+                let set = archive.sets.iter().find(|set| set.id == *set_id).unwrap();
+                json!([movements[&set.movement_id], set.reps, set.amount])
+            })
+            .collect()
+    };
+
+    let training_days: HashMap<Uuid, &str> = archive
+        .programs
+        .iter()
+        .flat_map(|program| &program.training_days)
+        .map(|training_day| (training_day.id, training_day.name.as_str()))
+        .collect();
+
+    let programs: Vec<_> = archive
+        .programs
+        .iter()
+        .map(|program| {
+            let weeks: Vec<Vec<Vec<_>>> = program
+                .weeks
+                .iter()
+                .map(|week| {
+                    [
+                        &week.set_ids_sunday,
+                        &week.set_ids_monday,
+                        &week.set_ids_tuesday,
+                        &week.set_ids_wednesday,
+                        &week.set_ids_thursday,
+                        &week.set_ids_friday,
+                        &week.set_ids_saturday,
+                    ]
+                    .into_iter()
+                    .map(describe_sets)
+                    .collect()
+                })
+                .collect();
+
+            let program_training_days: Vec<_> = program
+                .training_days
+                .iter()
+                .map(|training_day| {
+                    let weeks: Vec<_> = training_day.set_ids.iter().map(describe_sets).collect();
+                    json!([training_day.name, weeks])
+                })
+                .collect();
+
+            json!([
+                program.name,
+                profiles[&program.owner],
+                weeks,
+                program_training_days
+            ])
+        })
+        .collect();
+
+    let mut maxes: Vec<_> = archive
+        .maxes
+        .iter()
+        .map(|max| json!([movements[&max.movement_id], max.amount]))
+        .collect();
+    maxes.sort_by_key(Value::to_string);
+
+    let mut reps: Vec<_> = archive
+        .reps
+        .iter()
+        .map(|reps| json!([movements[&reps.movement_id], reps.amount]))
+        .collect();
+    reps.sort_by_key(Value::to_string);
+
+    let training_logs: Vec<_> = archive
+        .training_logs
+        .iter()
+        .map(|log| {
+            json!([
+                profiles[&log.profile_id],
+                training_days[&log.training_day_id]
+            ])
+        })
+        .collect();
+
+    let mut profile_names: Vec<_> = profiles.into_values().collect();
+    profile_names.sort();
+
+    let mut movement_names: Vec<_> = movements.into_values().collect();
+    movement_names.sort();
+
+    json!({
+        "profiles": profile_names,
+        "movements": movement_names,
+        "programs": programs,
+        "maxes": maxes,
+        "reps": reps,
+        "trainingLogs": training_logs,
+    })
+}
+
+#[then("My data matches the export")]
+async fn data_matches_export(world: &mut NsunsWorld) {
+    let restored = export(world).await;
+
+    assert_eq!(
+        archive_contents(world.auth_world.unwrap_archive()),
+        archive_contents(&restored)
+    );
+}
@@ -0,0 +1,51 @@
+use hyper::StatusCode;
+use jsonwebtoken::jwk::JwkSet;
+use nsuns_server::auth::{
+    access_token::CreatedAccessToken, archive::model::Archive, token::SessionInfo,
+    two_factor::model::LoginChallenge, user::AgentInfo,
+};
+
+#[derive(Debug, Default)]
+pub struct AuthWorld {
+    pub status: Option<StatusCode>,
+    pub agent_info: Option<AgentInfo>,
+    pub sessions: Vec<SessionInfo>,
+    pub access_token: Option<CreatedAccessToken>,
+    pub retry_after: Option<u64>,
+    pub totp_secret: Option<Vec<u8>>,
+    pub recovery_codes: Vec<String>,
+    pub login_challenge: Option<LoginChallenge>,
+    pub jwks: Option<JwkSet>,
+    pub archive: Option<Archive>,
+}
+
+impl AuthWorld {
+    pub fn unwrap_status(&self) -> StatusCode {
+        self.status
+            .expect("No response status injected into global state")
+    }
+
+    pub fn unwrap_access_token(&self) -> &CreatedAccessToken {
+        self.access_token
+            .as_ref()
+            .expect("No access token injected into global state")
+    }
+
+    pub fn unwrap_totp_secret(&self) -> &[u8] {
+        self.totp_secret
+            .as_ref()
+            .expect("No TOTP secret injected into global state")
+    }
+
+    pub fn unwrap_archive(&self) -> &Archive {
+        self.archive
+            .as_ref()
+            .expect("No archive injected into global state")
+    }
+
+    pub fn unwrap_login_challenge(&self) -> &LoginChallenge {
+        self.login_challenge
+            .as_ref()
+            .expect("No login challenge injected into global state")
+    }
+}
@@ -1,9 +1,18 @@
 use axum::Router;
+use jsonwebtoken::Algorithm;
 use nsuns_server::{
-    auth::settings::AuthSettings,
+    auth::{
+        oidc::settings::{OidcFeature, OidcSettings},
+        settings::{
+            default_anonymous_session_ttl, default_password_reset_ttl, default_recent_login_ttl,
+            default_session_renewal_threshold, default_user_session_ttl, AuthSettings,
+            JwtKeySettings,
+        },
+    },
     db::settings::{default_timeout, DatabaseSettings},
     observability::metrics::settings::MetricsFeature,
     openapi::settings::OpenApiFeature,
+    router::OIDC_PATH,
     server,
     settings::{ServerSettings, Settings},
 };
@@ -11,9 +20,11 @@ use secrecy::ExposeSecret;
 use sqlx::Connection;
 use uuid::Uuid;
 
-/// Create a randomized DB to re-use the container for multiple tests concurrently
-async fn randomize_db(mut settings: DatabaseSettings) -> anyhow::Result<DatabaseSettings> {
-    let mut conn = sqlx::PgConnection::connect(&format!(
+use crate::auth::issuer::{issuer_url, CLIENT_ID};
+
+/// Connect directly to the database, for setup that is not possible through the API
+pub async fn connect(settings: &DatabaseSettings) -> sqlx::Result<sqlx::PgConnection> {
+    sqlx::PgConnection::connect(&format!(
         "postgres://{}:{}@{}:{}/{}",
         settings.username,
         settings.password.expose_secret(),
@@ -21,7 +32,12 @@ async fn randomize_db(mut settings: DatabaseSettings) -> anyhow::Result<Database
         settings.port,
         settings.database
     ))
-    .await?;
+    .await
+}
+
+/// Create a randomized DB to re-use the container for multiple tests concurrently
+async fn randomize_db(mut settings: DatabaseSettings) -> anyhow::Result<DatabaseSettings> {
+    let mut conn = connect(&settings).await?;
 
     let database = format!("db_{}", Uuid::new_v4().to_string().replace('-', "_"));
 
@@ -36,33 +52,64 @@ async fn randomize_db(mut settings: DatabaseSettings) -> anyhow::Result<Database
     Ok(settings)
 }
 
-pub async fn init() -> Router {
-    server::initialize(&Settings {
+/// Create the router, and the settings for the database it uses
+pub async fn init() -> (Router, DatabaseSettings) {
+    let database = randomize_db(DatabaseSettings {
+        database: "postgres".to_string(),
+        host: "localhost".to_string(),
+        password: "postgres".to_string().into(),
+        username: "postgres".to_string(),
+        port: 5433,
+        migrations: "db/migrations".to_string(),
+        // try to limit connection pressure on test DB
+        max_connections: 1,
+        timeout: default_timeout(),
+    })
+    .await
+    .unwrap();
+
+    let router = server::initialize(&Settings {
         server: ServerSettings {
             port: 0,
             static_dir: None,
         },
-        database: randomize_db(DatabaseSettings {
-            database: "postgres".to_string(),
-            host: "localhost".to_string(),
-            password: "postgres".to_string().into(),
-            username: "postgres".to_string(),
-            port: 5433,
-            migrations: "db/migrations".to_string(),
-            // try to limit connection pressure on test DB
-            max_connections: 1,
-            timeout: default_timeout(),
-        })
-        .await
-        .unwrap(),
+        database: database.clone(),
         metrics: MetricsFeature::Disabled,
         openapi: OpenApiFeature::Disabled,
         logging: Default::default(),
         auth: AuthSettings {
-            jwt_encoding_secret: "jwt-secret".to_owned().into(),
-            jwt_decoding_secret: "jwt-secret".to_owned().into(),
+            jwt_keys: vec![JwtKeySettings {
+                kid: "test-key".to_owned(),
+                algorithm: Algorithm::EdDSA,
+                key: include_str!("../fixtures/jwt-ed25519-key.pem")
+                    .to_owned()
+                    .into(),
+            }],
+            jwt_encoding_secret: Some("jwt-secret".to_owned().into()),
+            jwt_decoding_secret: Some("jwt-secret".to_owned().into()),
+            publish_jwks: true,
+            password_reset_ttl: default_password_reset_ttl(),
+            user_session_ttl: default_user_session_ttl(),
+            anonymous_session_ttl: default_anonymous_session_ttl(),
+            session_renewal_threshold: default_session_renewal_threshold(),
+            recent_login_ttl: default_recent_login_ttl(),
+            login_throttle: Default::default(),
+            two_factor: Default::default(),
+            oidc: OidcFeature::Enabled(OidcSettings {
+                issuer: issuer_url().await,
+                client_id: CLIENT_ID.to_string(),
+                client_secret: None,
+                redirect_url: format!("http://localhost{OIDC_PATH}/callback"),
+                scopes: vec!["openid".to_string(), "profile".to_string()],
+                username_claim: "preferred_username".to_string(),
+                post_login_redirect: "/".to_string(),
+            }),
         },
+        quotas: Default::default(),
+        integrity: Default::default(),
     })
     .await
-    .unwrap()
+    .unwrap();
+
+    (router, database)
 }
@@ -0,0 +1,106 @@
+use cucumber::{given, then, when};
+use hyper::StatusCode;
+use nsuns_server::{
+    grants::model::{Access, CreateGrant},
+    maxes::model::CreateMax,
+    router::{GRANTS_PATH, MAXES_PATH},
+};
+
+use crate::{
+    util::{Auth, JsonBody},
+    world::NsunsWorld,
+};
+
+fn access_from_str(access: &str) -> Access {
+    match access {
+        "read" => Access::Read,
+        "write" => Access::Write,
+        _ => panic!(r#"access "{access}" not recognized"#),
+    }
+}
+
+#[given(regex = r#"^I share the profile with "(.*)" with (read|write) access"#)]
+#[when(regex = r#"^I share the profile with "(.*)" with (read|write) access"#)]
+async fn share_profile(world: &mut NsunsWorld, username: String, access: String) {
+    let res = world
+        .client
+        .put(GRANTS_PATH)
+        .json_body(&CreateGrant {
+            profile_id: world.profile_world.unwrap_profile().id,
+            username,
+            access: access_from_str(&access),
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    world.grants_world.grant = Some(res.json().await);
+}
+
+#[when("I stop sharing the profile")]
+async fn stop_sharing(world: &mut NsunsWorld) {
+    let grant_id = world.grants_world.unwrap_grant().id;
+
+    let res = world
+        .client
+        .delete(&format!("{GRANTS_PATH}/{grant_id}"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, res.status());
+}
+
+#[when("I fetch the profiles shared with me")]
+async fn fetch_shared_profiles(world: &mut NsunsWorld) {
+    world.grants_world.shared_profiles = world
+        .client
+        .get(&format!("{GRANTS_PATH}/shared"))
+        .authed(world)
+        .send()
+        .await
+        .json()
+        .await;
+}
+
+#[then(regex = r#"^The profile "(.*)" is shared with me with (read|write) access"#)]
+async fn profile_is_shared(world: &mut NsunsWorld, name: String, access: String) {
+    let shared = world
+        .grants_world
+        .shared_profiles
+        .iter()
+        .find(|profile| profile.name == name)
+        .expect("Profile is not shared");
+
+    assert_eq!(access_from_str(&access), shared.access);
+}
+
+#[when(regex = r#"^I try to log a max of (\d+) in "(.*)""#)]
+async fn try_create_max(world: &mut NsunsWorld, amount: f64, movement_name: String) {
+    let movement_id = world
+        .movement_world
+        .movement_by_name(&movement_name)
+        .expect("Movement does not exist")
+        .id;
+
+    let res = world
+        .client
+        .post(MAXES_PATH)
+        .json_body(&CreateMax {
+            profile_id: world.profile_world.unwrap_profile().id,
+            movement_id,
+            amount,
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[then("I do not see any maxes")]
+async fn no_maxes(world: &mut NsunsWorld) {
+    assert!(world.maxes_world.maxes.is_empty());
+}
@@ -0,0 +1,15 @@
+use nsuns_server::grants::model::{Grant, SharedProfile};
+
+#[derive(Debug, Default)]
+pub struct GrantsWorld {
+    pub grant: Option<Grant>,
+    pub shared_profiles: Vec<SharedProfile>,
+}
+
+impl GrantsWorld {
+    pub fn unwrap_grant(&self) -> &Grant {
+        self.grant
+            .as_ref()
+            .expect("No grant injected into global state")
+    }
+}
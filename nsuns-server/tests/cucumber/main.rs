@@ -1,13 +1,17 @@
 use cucumber::World;
 
+mod admin;
 mod auth;
 mod common;
+mod grants;
 mod maxes;
 mod movement;
 mod profile;
 mod program;
 mod reps;
 mod sets;
+mod templates;
+mod training_days;
 mod updates;
 mod util;
 mod world;
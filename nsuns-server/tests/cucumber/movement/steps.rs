@@ -44,6 +44,25 @@ async fn fetch_movements(world: &mut NsunsWorld) {
         .await;
 }
 
+#[when("I fetch all movements for my profile")]
+async fn fetch_movements_for_profile(world: &mut NsunsWorld) {
+    let profile_id = world.profile_world.unwrap_profile().id;
+
+    world.movement_world.movements = world
+        .client
+        .get(&format!("{MOVEMENTS_PATH}?profileId={profile_id}"))
+        .authed(world)
+        .send()
+        .await
+        .json::<_>()
+        .await;
+}
+
+#[then(regex = r"^I have (\d+) movements$")]
+async fn count_movements(world: &mut NsunsWorld, count: usize) {
+    assert_eq!(count, world.movement_world.movements.len());
+}
+
 #[then(regex = r#"My movement has the name "(.*)""#)]
 async fn have_movement(world: &mut NsunsWorld, name: String) {
     let movement_id = world.movement_world.unwrap_movement().id;
@@ -1,9 +1,12 @@
 use cucumber::{given, then, when};
 use hyper::StatusCode;
 use nsuns_server::{
-    program::model::{CreateProgram, UpdateProgram},
+    program::model::{
+        AddWeek, CloneProgram, CreateProgram, ProgramMeta, ProgramWeek, UpdateProgram,
+    },
     router::PROGRAMS_PATH,
 };
+use serde_json::json;
 
 use crate::{
     util::{Auth, JsonBody},
@@ -53,7 +56,10 @@ async fn fetch_program_summary(world: &mut NsunsWorld) {
     world.program_world.program_summary = Some(
         world
             .client
-            .get(&format!("{PROGRAMS_PATH}/{program_id}"))
+            .get(&format!(
+                "{PROGRAMS_PATH}/{program_id}?week={}",
+                world.program_world.week
+            ))
             .authed(world)
             .send()
             .await
@@ -118,3 +124,201 @@ async fn program_not_found(world: &mut NsunsWorld) {
 
     assert!(program_meta.is_none(), "Program exists");
 }
+
+async fn add_week(world: &mut NsunsWorld, add_week: AddWeek) {
+    let program_id = world.program_world.unwrap_program_meta().id;
+
+    let res = world
+        .client
+        .post(&format!("{PROGRAMS_PATH}/{program_id}/weeks"))
+        .json_body(&add_week)
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::CREATED, res.status());
+
+    let week: ProgramWeek = res.json().await;
+    world.program_world.week = week.week;
+}
+
+#[when("I add a week to my program")]
+#[given("I added a week to my program")]
+async fn add_empty_week(world: &mut NsunsWorld) {
+    add_week(world, AddWeek::default()).await;
+}
+
+#[when(regex = r"^I copy week (\d+) into a new week")]
+async fn copy_week(world: &mut NsunsWorld, week: i16) {
+    add_week(
+        world,
+        AddWeek {
+            copy_from: Some(week),
+        },
+    )
+    .await;
+}
+
+#[when(regex = r"^I (try to )?delete week (\d+) of my program")]
+async fn delete_week(world: &mut NsunsWorld, attempt: String, week: i16) {
+    let program_id = world.program_world.unwrap_program_meta().id;
+
+    let res = world
+        .client
+        .delete(&format!("{PROGRAMS_PATH}/{program_id}/weeks/{week}"))
+        .authed(world)
+        .send()
+        .await;
+
+    if attempt.is_empty() {
+        assert_eq!(StatusCode::OK, res.status());
+    }
+
+    world.auth_world.status = Some(res.status());
+}
+
+#[when(regex = r#"^I clone my program into the current profile with name "(.*)"$"#)]
+async fn clone_program(world: &mut NsunsWorld, name: String) {
+    let program_id = world.program_world.unwrap_program_meta().id;
+
+    let res = world
+        .client
+        .post(&format!("{PROGRAMS_PATH}/{program_id}/clone"))
+        .json_body(&CloneProgram {
+            owner: Some(world.profile_world.unwrap_profile().id),
+            name: Some(name),
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::CREATED, res.status());
+
+    world.program_world.program_meta = Some(res.json().await);
+    world.program_world.week = 0;
+}
+
+#[when("I export my program")]
+async fn export_program(world: &mut NsunsWorld) {
+    let program_id = world.program_world.unwrap_program_meta().id;
+
+    let res = world
+        .client
+        .get(&format!("{PROGRAMS_PATH}/{program_id}/export"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    world.program_world.template = Some(res.json().await);
+}
+
+#[then("The exported program does not name a profile")]
+async fn exported_without_owner(world: &mut NsunsWorld) {
+    let template = world
+        .program_world
+        .template
+        .as_ref()
+        .expect("No exported program or template injected into global state");
+
+    assert!(template.get("owner").is_none());
+}
+
+#[when(regex = r"^I (try to )?import the (?:exported program|template)(?: as version (\d+))?$")]
+async fn import_program(world: &mut NsunsWorld, attempt: String, version: String) {
+    let mut template = world
+        .program_world
+        .template
+        .clone()
+        .expect("No exported program or template injected into global state");
+
+    template["owner"] = json!(world.profile_world.unwrap_profile().id);
+
+    if !version.is_empty() {
+        template["version"] = json!(version.parse::<u32>().unwrap());
+    }
+
+    let res = world
+        .client
+        .post(&format!("{PROGRAMS_PATH}/from-template"))
+        .json_body(&template)
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+
+    if attempt.is_empty() {
+        assert_eq!(StatusCode::CREATED, res.status());
+        world.program_world.program_meta = Some(res.json().await);
+        world.program_world.week = 0;
+    }
+}
+
+#[when(regex = r"^I switch to week (\d+)")]
+async fn switch_week(world: &mut NsunsWorld, week: i16) {
+    world.program_world.week = week;
+}
+
+#[then(regex = r"^My program has (\d+) weeks?")]
+async fn program_weeks(world: &mut NsunsWorld, weeks: i16) {
+    assert_eq!(weeks, world.program_world.unwrap_program_summary().weeks);
+}
+
+#[when(regex = r#"^I create a program from a template with (\d+) weeks of "(.*)" on (\S+)"#)]
+async fn create_from_template(world: &mut NsunsWorld, weeks: usize, movement: String, day: String) {
+    let day_index = [
+        "Sunday",
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+    ]
+    .iter()
+    .position(|name| *name == day)
+    .unwrap_or_else(|| panic!(r#"day "{day}" not recognized"#));
+
+    let weeks: Vec<_> = (0..weeks)
+        .map(|week| {
+            let days: Vec<_> = (0..7)
+                .map(|index| {
+                    let sets = if index == day_index {
+                        vec![json!({
+                            "movementIndex": 0,
+                            "percentageOfMaxIndex": null,
+                            "reps": 5,
+                            "repsIsMinimum": false,
+                            "description": null,
+                            "amount": 100 + 10 * week,
+                        })]
+                    } else {
+                        vec![]
+                    };
+                    json!({ "sets": sets })
+                })
+                .collect();
+            json!({ "days": days })
+        })
+        .collect();
+
+    let res = world
+        .client
+        .post(&format!("{PROGRAMS_PATH}/from-template"))
+        .json_body(&json!({
+            "name": "template",
+            "owner": world.profile_world.unwrap_profile().id,
+            "weeks": weeks,
+            "movements": [{ "type": "new", "name": movement, "description": null }],
+        }))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::CREATED, res.status());
+
+    let program_meta: ProgramMeta = res.json().await;
+    world.program_world.program_meta = Some(program_meta);
+}
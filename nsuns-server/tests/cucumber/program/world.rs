@@ -5,6 +5,12 @@ pub struct ProgramWorld {
     pub program_meta: Option<ProgramMeta>,
     pub program_summary: Option<ProgramSummary>,
     pub programs_for_profile: Vec<ProgramMeta>,
+    /// The week that sets are created in and fetched from
+    pub week: i16,
+    /// The body of the last request that was expected to fail
+    pub error: Option<String>,
+    /// The last exported program or fetched template
+    pub template: Option<serde_json::Value>,
 }
 
 impl ProgramWorld {
@@ -1,10 +1,10 @@
 use cucumber::{given, then, when};
 use hyper::StatusCode;
 use nsuns_server::{
-    program::model::ProgramSummary,
+    program::model::{DaySet, ProgramSummary, ReplaceDay},
     program::{model::ReorderSets, router::REORDER_SETS_PATH},
     router::{PROGRAMS_PATH, SETS_PATH},
-    sets::model::{CreateSet, Day, Set},
+    sets::model::{CreateSet, CreateSets, Day, MoveSet, Set},
 };
 
 use crate::{
@@ -37,8 +37,8 @@ fn sets_for_day(program: &ProgramSummary, day: Day) -> &Vec<Set> {
     }
 }
 
-#[when(regex = r#"I create a "(.*)" set for (\S+)"#)]
-#[given(regex = r#"I have a "(.*)" set for (\S+)"#)]
+#[when(regex = r#"I create a "(.*)" set for (\S+)$"#)]
+#[given(regex = r#"I have a "(.*)" set for (\S+)$"#)]
 pub async fn create_set(world: &mut NsunsWorld, movement_name: String, day: String) {
     let movement_id = world
         .movement_world
@@ -52,7 +52,9 @@ pub async fn create_set(world: &mut NsunsWorld, movement_name: String, day: Stri
 
     let create_set = CreateSet {
         amount: 70.0,
-        day,
+        week: world.program_world.week,
+        day: Some(day),
+        training_day_id: None,
         description: None,
         movement_id,
         program_id,
@@ -72,7 +74,7 @@ pub async fn create_set(world: &mut NsunsWorld, movement_name: String, day: Stri
     assert_eq!(StatusCode::CREATED, res.status());
 }
 
-#[then(regex = r"My program has (\[.*\]) on (\S+)")]
+#[then(regex = r"My program has (\[.*\]) on (\S+)$")]
 pub async fn test_sets_for_day(world: &mut NsunsWorld, movement_names: String, day: String) {
     let names: Vec<String> = serde_json::from_str(&movement_names)
         .unwrap_or_else(|_| panic!("Could not deserialize {movement_names} into array of strings"));
@@ -89,6 +91,30 @@ pub async fn test_sets_for_day(world: &mut NsunsWorld, movement_names: String, d
     assert_eq!(names, actual_names);
 }
 
+#[then(regex = r#"^My program has (\d+) "(.*)" sets on (\S+)$"#)]
+pub async fn count_sets_for_day(
+    world: &mut NsunsWorld,
+    count: usize,
+    movement_name: String,
+    day: String,
+) {
+    let movement_id = world
+        .movement_world
+        .movement_by_name(&movement_name)
+        .unwrap_or_else(|| panic!(r#"Movement "{movement_name}" not found"#))
+        .id;
+
+    let actual = sets_for_day(
+        world.program_world.unwrap_program_summary(),
+        day_from_str(&day),
+    )
+    .iter()
+    .filter(|set| set.movement_id == movement_id)
+    .count();
+
+    assert_eq!(count, actual);
+}
+
 #[when(regex = r"I reorder (\S+) from (\d+) to (\d+)")]
 pub async fn reorder_sets(world: &mut NsunsWorld, day: String, from: usize, to: usize) {
     let program_id = world.program_world.unwrap_program_meta().id;
@@ -97,7 +123,9 @@ pub async fn reorder_sets(world: &mut NsunsWorld, day: String, from: usize, to:
         .client
         .post(&format!("{PROGRAMS_PATH}{REORDER_SETS_PATH}"))
         .json_body(&ReorderSets {
-            day: day_from_str(&day),
+            week: world.program_world.week,
+            day: Some(day_from_str(&day)),
+            training_day_id: None,
             from,
             to,
             program_id,
@@ -108,3 +136,211 @@ pub async fn reorder_sets(world: &mut NsunsWorld, day: String, from: usize, to:
 
     assert_eq!(StatusCode::OK, res.status());
 }
+
+#[when(regex = r"I delete set (\d+) on (\S+)$")]
+pub async fn delete_set(world: &mut NsunsWorld, index: usize, day: String) {
+    let id = sets_for_day(
+        world.program_world.unwrap_program_summary(),
+        day_from_str(&day),
+    )
+    .get(index)
+    .unwrap_or_else(|| panic!("No set {index} on {day}"))
+    .id;
+
+    let res = world
+        .client
+        .delete(&format!("{SETS_PATH}/{id}"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, res.status());
+}
+
+/// Move a set of the fetched program summary to the current program, which may be another program
+#[when(regex = r"I move set (\d+) on (\S+) to (\S+) at (\d+)$")]
+pub async fn move_set(
+    world: &mut NsunsWorld,
+    index: usize,
+    from: String,
+    to: String,
+    to_index: usize,
+) {
+    let id = sets_for_day(
+        world.program_world.unwrap_program_summary(),
+        day_from_str(&from),
+    )
+    .get(index)
+    .unwrap_or_else(|| panic!("No set {index} on {from}"))
+    .id;
+
+    let res = world
+        .client
+        .post(&format!("{SETS_PATH}/move"))
+        .json_body(&MoveSet {
+            id,
+            program_id: world.program_world.unwrap_program_meta().id,
+            week: world.program_world.week,
+            day: Some(day_from_str(&to)),
+            training_day_id: None,
+            index: to_index,
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+}
+
+/// Replace the sets of a day with a list of `"<day> <index>"` to keep a set of the fetched program summary,
+/// or `"new <movement>"` to create one
+#[when(regex = r"I (try to )?replace (\S+) with (\[.*\])$")]
+pub async fn replace_day(world: &mut NsunsWorld, attempt: String, day: String, sets: String) {
+    let sets: Vec<String> = serde_json::from_str(&sets)
+        .unwrap_or_else(|_| panic!("Could not deserialize {sets} into array of strings"));
+
+    let summary = world.program_world.unwrap_program_summary();
+
+    let sets: Vec<_> = sets
+        .iter()
+        .map(|set| {
+            let (kind, value) = set
+                .split_once(' ')
+                .unwrap_or_else(|| panic!(r#"set "{set}" not recognized"#));
+
+            if kind == "new" {
+                let movement_id = world
+                    .movement_world
+                    .movement_by_name(value)
+                    .unwrap_or_else(|| panic!(r#"Movement "{value}" not found"#))
+                    .id;
+
+                DaySet {
+                    id: None,
+                    movement_id,
+                    reps: None,
+                    reps_is_minimum: false,
+                    description: None,
+                    amount: 70.0,
+                    percentage_of_max: None,
+                }
+            } else {
+                let index: usize = value.parse().expect("set index is not a number");
+                let existing = sets_for_day(summary, day_from_str(kind))
+                    .get(index)
+                    .unwrap_or_else(|| panic!("No set {index} on {kind}"));
+
+                DaySet {
+                    id: Some(existing.id),
+                    movement_id: existing.movement_id,
+                    reps: existing.reps,
+                    reps_is_minimum: existing.reps_is_minimum,
+                    description: existing.description.clone(),
+                    amount: existing.amount,
+                    percentage_of_max: existing.percentage_of_max,
+                }
+            }
+        })
+        .collect();
+
+    let program_id = world.program_world.unwrap_program_meta().id;
+    let day = day_from_str(&day) as i16;
+    let week = world.program_world.week;
+
+    let res = world
+        .client
+        .put(&format!(
+            "{PROGRAMS_PATH}/{program_id}/days/{day}?week={week}"
+        ))
+        .json_body(&ReplaceDay { sets })
+        .authed(world)
+        .send()
+        .await;
+
+    if attempt.is_empty() {
+        assert_eq!(StatusCode::OK, res.status());
+    }
+
+    world.auth_world.status = Some(res.status());
+}
+
+/// Create a set for each movement in one request, giving set `invalid` a negative amount if it is set
+async fn create_sets_in_bulk(
+    world: &mut NsunsWorld,
+    movement_names: &str,
+    day: &str,
+    invalid: Option<usize>,
+) -> StatusCode {
+    let names: Vec<String> = serde_json::from_str(movement_names)
+        .unwrap_or_else(|_| panic!("Could not deserialize {movement_names} into array of strings"));
+
+    let sets = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| CreateSet {
+            amount: if invalid == Some(index) { -1.0 } else { 70.0 },
+            week: world.program_world.week,
+            day: Some(day_from_str(day)),
+            training_day_id: None,
+            description: None,
+            movement_id: world
+                .movement_world
+                .movement_by_name(name)
+                .unwrap_or_else(|| panic!(r#"Movement "{name}" not found"#))
+                .id,
+            program_id: world.program_world.unwrap_program_meta().id,
+            percentage_of_max: None,
+            reps: None,
+            reps_is_minimum: false,
+        })
+        .collect();
+
+    let res = world
+        .client
+        .post(&format!("{SETS_PATH}/bulk"))
+        .json_body(&CreateSets { sets })
+        .authed(world)
+        .send()
+        .await;
+
+    let status = res.status();
+
+    if !status.is_success() {
+        world.program_world.error = Some(res.text().await);
+    }
+
+    status
+}
+
+#[when(regex = r"I create (\[.*\]) sets for (\S+) in bulk$")]
+pub async fn create_sets(world: &mut NsunsWorld, movement_names: String, day: String) {
+    let status = create_sets_in_bulk(world, &movement_names, &day, None).await;
+
+    assert_eq!(StatusCode::CREATED, status);
+}
+
+#[when(regex = r"I try to create (\[.*\]) sets for (\S+) in bulk with an invalid set (\d+)$")]
+pub async fn create_invalid_sets(
+    world: &mut NsunsWorld,
+    movement_names: String,
+    day: String,
+    invalid: usize,
+) {
+    let status = create_sets_in_bulk(world, &movement_names, &day, Some(invalid)).await;
+
+    world.auth_world.status = Some(status);
+}
+
+#[then(regex = r"The error is about set (\d+)$")]
+pub async fn error_names_set(world: &mut NsunsWorld, index: usize) {
+    let error = world
+        .program_world
+        .error
+        .as_ref()
+        .expect("No error injected into global state");
+
+    assert!(
+        error.contains(&format!("sets[{index}]")),
+        "{error} is not about set {index}"
+    );
+}
@@ -0,0 +1,2 @@
+mod steps;
+pub mod world;
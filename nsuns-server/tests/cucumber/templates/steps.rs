@@ -0,0 +1,45 @@
+use cucumber::{then, when};
+use hyper::StatusCode;
+use nsuns_server::router::TEMPLATES_PATH;
+
+use crate::{util::Auth, world::NsunsWorld};
+
+#[when("I fetch the templates")]
+async fn fetch_templates(world: &mut NsunsWorld) {
+    world.templates_world.templates = world
+        .client
+        .get(TEMPLATES_PATH)
+        .authed(world)
+        .send()
+        .await
+        .json::<_>()
+        .await;
+}
+
+#[then(regex = r#"^The template "(.*)" has (\d+) days a week$"#)]
+async fn template_days(world: &mut NsunsWorld, slug: String, days: usize) {
+    let template = world
+        .templates_world
+        .templates
+        .iter()
+        .find(|template| template.slug == slug)
+        .unwrap_or_else(|| panic!(r#"Template "{slug}" not found"#));
+
+    assert_eq!(days, template.days_per_week);
+}
+
+#[when(regex = r#"^I fetch the template "(.*)"$"#)]
+async fn fetch_template(world: &mut NsunsWorld, slug: String) {
+    let res = world
+        .client
+        .get(&format!("{TEMPLATES_PATH}/{slug}"))
+        .authed(world)
+        .send()
+        .await;
+
+    world.auth_world.status = Some(res.status());
+
+    if res.status() == StatusCode::OK {
+        world.program_world.template = Some(res.json().await);
+    }
+}
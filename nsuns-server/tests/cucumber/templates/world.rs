@@ -0,0 +1,6 @@
+use nsuns_server::templates::model::TemplateSummary;
+
+#[derive(Debug, Default)]
+pub struct TemplatesWorld {
+    pub templates: Vec<TemplateSummary>,
+}
@@ -0,0 +1,277 @@
+use cucumber::{given, then, when};
+use hyper::StatusCode;
+use nsuns_server::{
+    program::{
+        model::{ProgramMeta, ReorderSets},
+        router::REORDER_SETS_PATH,
+    },
+    router::{PROGRAMS_PATH, SETS_PATH, TRAINING_DAYS_PATH},
+    sets::model::CreateSet,
+    training_days::model::{CreateTrainingDay, ReorderTrainingDays},
+};
+use serde_json::json;
+
+use crate::{
+    util::{Auth, JsonBody},
+    world::NsunsWorld,
+};
+
+fn names_from_str(names: &str) -> Vec<String> {
+    serde_json::from_str(names)
+        .unwrap_or_else(|_| panic!("Could not deserialize {names} into array of strings"))
+}
+
+#[when("I fetch my training days")]
+async fn fetch_training_days(world: &mut NsunsWorld) {
+    let program_id = world.program_world.unwrap_program_meta().id;
+
+    let res = world
+        .client
+        .get(&format!("{TRAINING_DAYS_PATH}?programId={program_id}"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+
+    world.training_days_world.training_days = res.json().await;
+}
+
+#[when(regex = r"^I add the training days (\[.*\]) to my program")]
+#[given(regex = r"^My program has the training days (\[.*\])")]
+async fn add_training_days(world: &mut NsunsWorld, names: String) {
+    let program_id = world.program_world.unwrap_program_meta().id;
+
+    for name in names_from_str(&names) {
+        let res = world
+            .client
+            .post(TRAINING_DAYS_PATH)
+            .json_body(&CreateTrainingDay { program_id, name })
+            .authed(world)
+            .send()
+            .await;
+
+        assert_eq!(StatusCode::CREATED, res.status());
+    }
+
+    fetch_training_days(world).await;
+}
+
+#[then(regex = r"^My training days are (\[.*\])")]
+async fn test_training_days(world: &mut NsunsWorld, names: String) {
+    let actual: Vec<_> = world
+        .training_days_world
+        .training_days
+        .iter()
+        .map(|training_day| training_day.name.clone())
+        .collect();
+
+    assert_eq!(names_from_str(&names), actual);
+}
+
+#[when(regex = r"^I move training day (\d+) to (\d+)")]
+async fn reorder_training_days(world: &mut NsunsWorld, from: usize, to: usize) {
+    let program_id = world.program_world.unwrap_program_meta().id;
+
+    let res = world
+        .client
+        .post(&format!("{TRAINING_DAYS_PATH}/reorder"))
+        .json_body(&ReorderTrainingDays {
+            program_id,
+            from,
+            to,
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+}
+
+#[when(regex = r#"^I delete the training day "(.*)""#)]
+async fn delete_training_day(world: &mut NsunsWorld, name: String) {
+    let id = world.training_days_world.training_day_by_name(&name).id;
+
+    let res = world
+        .client
+        .delete(&format!("{TRAINING_DAYS_PATH}/{id}"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::NO_CONTENT, res.status());
+}
+
+#[when(regex = r#"^I complete "(.*)""#)]
+#[given(regex = r#"^I completed "(.*)""#)]
+async fn complete_training_day(world: &mut NsunsWorld, name: String) {
+    let id = world.training_days_world.training_day_by_name(&name).id;
+
+    let res = world
+        .client
+        .post(&format!("{TRAINING_DAYS_PATH}/{id}/complete"))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::CREATED, res.status());
+}
+
+#[when(regex = r"^I fetch the next training day( of my profile)?")]
+async fn fetch_next_training_day(world: &mut NsunsWorld, any_program: String) {
+    let profile_id = world.profile_world.unwrap_profile().id;
+
+    let path = if any_program.is_empty() {
+        let program_id = world.program_world.unwrap_program_meta().id;
+        format!("{TRAINING_DAYS_PATH}/next?profileId={profile_id}&programId={program_id}")
+    } else {
+        format!("{TRAINING_DAYS_PATH}/next?profileId={profile_id}")
+    };
+
+    let res = world.client.get(&path).authed(world).send().await;
+
+    world.training_days_world.next_training_day = match res.status() {
+        StatusCode::OK => Some(res.json().await),
+        StatusCode::NOT_FOUND => None,
+        status => panic!("unexpected status {status}"),
+    };
+}
+
+#[then(regex = r#"^The next training day is "(.*)""#)]
+async fn test_next_training_day(world: &mut NsunsWorld, name: String) {
+    let next = world
+        .training_days_world
+        .next_training_day
+        .as_ref()
+        .expect("No next training day");
+
+    assert_eq!(name, next.training_day.name);
+}
+
+#[then("There is no next training day")]
+async fn no_next_training_day(world: &mut NsunsWorld) {
+    assert!(world.training_days_world.next_training_day.is_none());
+}
+
+#[when(regex = r#"^I create a "(.*)" set for training day "(.*)""#)]
+#[given(regex = r#"^I have a "(.*)" set for training day "(.*)""#)]
+async fn create_set(world: &mut NsunsWorld, movement_name: String, name: String) {
+    let movement_id = world
+        .movement_world
+        .movement_by_name(&movement_name)
+        .unwrap_or_else(|| panic!(r#"Movement "{movement_name}" not found"#))
+        .id;
+
+    let create_set = CreateSet {
+        amount: 70.0,
+        week: world.program_world.week,
+        day: None,
+        training_day_id: Some(world.training_days_world.training_day_by_name(&name).id),
+        description: None,
+        movement_id,
+        program_id: world.program_world.unwrap_program_meta().id,
+        percentage_of_max: None,
+        reps: None,
+        reps_is_minimum: false,
+    };
+
+    let res = world
+        .client
+        .post(SETS_PATH)
+        .json_body(&create_set)
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::CREATED, res.status());
+}
+
+#[when(regex = r#"^I reorder training day "(.*)" from (\d+) to (\d+)"#)]
+async fn reorder_sets(world: &mut NsunsWorld, name: String, from: usize, to: usize) {
+    let res = world
+        .client
+        .post(&format!("{PROGRAMS_PATH}{REORDER_SETS_PATH}"))
+        .json_body(&ReorderSets {
+            program_id: world.program_world.unwrap_program_meta().id,
+            week: world.program_world.week,
+            day: None,
+            training_day_id: Some(world.training_days_world.training_day_by_name(&name).id),
+            from,
+            to,
+        })
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::OK, res.status());
+}
+
+#[then(regex = r#"^My program has (\[.*\]) on training day "(.*)""#)]
+async fn test_sets_for_training_day(world: &mut NsunsWorld, movement_names: String, name: String) {
+    let training_day = world
+        .program_world
+        .unwrap_program_summary()
+        .training_days
+        .iter()
+        .find(|training_day| training_day.training_day.name == name)
+        .unwrap_or_else(|| panic!(r#"Training day "{name}" not found in summary"#));
+
+    let actual_names: Vec<_> = training_day
+        .sets
+        .iter()
+        .filter_map(|set| world.movement_world.movement_by_id(set.movement_id))
+        .map(|movement| movement.name.clone())
+        .collect();
+
+    assert_eq!(names_from_str(&movement_names), actual_names);
+}
+
+#[when(
+    regex = r#"^I create a program from a template with training days (\[.*\]) and "(.*)" on "(.*)""#
+)]
+async fn create_from_template(
+    world: &mut NsunsWorld,
+    names: String,
+    movement: String,
+    training_day: String,
+) {
+    let names = names_from_str(&names);
+
+    let training_days: Vec<_> = names
+        .iter()
+        .map(|name| {
+            let sets = if *name == training_day {
+                vec![json!({
+                    "movementIndex": 0,
+                    "percentageOfMaxIndex": null,
+                    "reps": 5,
+                    "repsIsMinimum": false,
+                    "description": null,
+                    "amount": 100,
+                })]
+            } else {
+                vec![]
+            };
+            json!({ "sets": sets })
+        })
+        .collect();
+
+    let res = world
+        .client
+        .post(&format!("{PROGRAMS_PATH}/from-template"))
+        .json_body(&json!({
+            "name": "template",
+            "owner": world.profile_world.unwrap_profile().id,
+            "trainingDays": names,
+            "weeks": [{ "trainingDays": training_days }],
+            "movements": [{ "type": "new", "name": movement, "description": null }],
+        }))
+        .authed(world)
+        .send()
+        .await;
+
+    assert_eq!(StatusCode::CREATED, res.status());
+
+    let program_meta: ProgramMeta = res.json().await;
+    world.program_world.program_meta = Some(program_meta);
+}
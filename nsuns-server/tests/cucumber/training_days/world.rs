@@ -0,0 +1,16 @@
+use nsuns_server::training_days::model::{NextTrainingDay, TrainingDay};
+
+#[derive(Debug, Default)]
+pub struct TrainingDaysWorld {
+    pub training_days: Vec<TrainingDay>,
+    pub next_training_day: Option<NextTrainingDay>,
+}
+
+impl TrainingDaysWorld {
+    pub fn training_day_by_name(&self, name: &str) -> &TrainingDay {
+        self.training_days
+            .iter()
+            .find(|training_day| training_day.name == name)
+            .unwrap_or_else(|| panic!(r#"Training day "{name}" not found"#))
+    }
+}
@@ -1,5 +1,8 @@
 use axum_test_helper::RequestBuilder;
-use nsuns_server::auth::token::COOKIE_NAME;
+use nsuns_server::auth::{
+    csrf::{CSRF_COOKIE_NAME, CSRF_HEADER_NAME},
+    token::COOKIE_NAME,
+};
 use serde::Serialize;
 use tower_cookies::Cookie;
 
@@ -27,11 +30,23 @@ pub trait Auth {
 
 impl Auth for RequestBuilder {
     fn authed(self, world: &NsunsWorld) -> Self {
-        if let Some(cookie) = &world.auth_cookie {
-            self.header(
-                "Cookie",
-                &Cookie::new(COOKIE_NAME, cookie).encoded().to_string(),
-            )
+        if let Some(token) = &world.bearer_token {
+            self.header("Authorization", &format!("Bearer {token}"))
+        } else if let Some(cookie) = &world.auth_cookie {
+            let mut cookies = vec![Cookie::new(COOKIE_NAME, cookie).encoded().to_string()];
+
+            let builder = if let Some(csrf_token) = &world.csrf_token {
+                cookies.push(
+                    Cookie::new(CSRF_COOKIE_NAME, csrf_token)
+                        .encoded()
+                        .to_string(),
+                );
+                self.header(CSRF_HEADER_NAME, csrf_token)
+            } else {
+                self
+            };
+
+            builder.header("Cookie", &cookies.join("; "))
         } else {
             self
         }
@@ -2,33 +2,52 @@ use std::fmt::Debug;
 
 use axum_test_helper::TestClient;
 use cucumber::World;
+use nsuns_server::db::settings::DatabaseSettings;
 
 use crate::{
-    common, maxes::world::MaxesWorld, movement::world::MovementWorld, profile::world::ProfileWorld,
-    program::world::ProgramWorld,
+    admin::world::AdminWorld, auth::world::AuthWorld, common, grants::world::GrantsWorld,
+    maxes::world::MaxesWorld, movement::world::MovementWorld, profile::world::ProfileWorld,
+    program::world::ProgramWorld, templates::world::TemplatesWorld,
+    training_days::world::TrainingDaysWorld,
 };
 
 #[derive(World)]
 #[world(init = Self::new)]
 pub struct NsunsWorld {
     pub auth_cookie: Option<String>,
+    pub csrf_token: Option<String>,
+    pub bearer_token: Option<String>,
     pub client: TestClient,
+    pub database: DatabaseSettings,
+    pub auth_world: AuthWorld,
+    pub admin_world: AdminWorld,
+    pub grants_world: GrantsWorld,
     pub profile_world: ProfileWorld,
     pub movement_world: MovementWorld,
     pub program_world: ProgramWorld,
     pub maxes_world: MaxesWorld,
+    pub training_days_world: TrainingDaysWorld,
+    pub templates_world: TemplatesWorld,
 }
 
 impl NsunsWorld {
     async fn new() -> Self {
-        let router = common::init().await;
+        let (router, database) = common::init().await;
         Self {
             auth_cookie: None,
+            csrf_token: None,
+            bearer_token: None,
             client: TestClient::new(router).await,
+            database,
+            auth_world: Default::default(),
+            admin_world: Default::default(),
+            grants_world: Default::default(),
             profile_world: Default::default(),
             movement_world: Default::default(),
             program_world: Default::default(),
             maxes_world: Default::default(),
+            training_days_world: Default::default(),
+            templates_world: Default::default(),
         }
     }
 }
@@ -37,11 +56,19 @@ impl Debug for NsunsWorld {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("NsunsWorld")
             .field("auth_cookie", &self.auth_cookie)
+            .field("csrf_token", &self.csrf_token)
+            .field("bearer_token", &self.bearer_token)
             .field("client", &"anonymous-client")
+            .field("database", &self.database.database)
+            .field("auth_world", &self.auth_world)
+            .field("admin_world", &self.admin_world)
+            .field("grants_world", &self.grants_world)
             .field("profile_world", &self.profile_world)
             .field("movement_world", &self.movement_world)
             .field("program_world", &self.program_world)
             .field("maxes_world", &self.maxes_world)
+            .field("training_days_world", &self.training_days_world)
+            .field("templates_world", &self.templates_world)
             .finish()
     }
 }
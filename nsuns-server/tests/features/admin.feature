@@ -0,0 +1,106 @@
+Feature: Administration
+
+  Scenario: Listing users
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I list all users
+    Then The request succeeds with status 200
+    And "admin" is listed as an admin who was recently active
+    And "lifter" is listed as a user who was recently active
+
+  Scenario: Regular users cannot administer users
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I list all users
+    Then The request fails with status 403
+
+  Scenario: Disabled users cannot log in
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I disable the user "lifter"
+    And I log in with username "lifter" and password "correct horse battery"
+    Then The request fails with status 403
+    When I enable the user "lifter"
+    And I log in with username "lifter" and password "correct horse battery"
+    Then The request succeeds with status 204
+
+  Scenario: Disabled users are rejected by their existing sessions
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I disable the user "lifter"
+    And I go back to the session I set aside
+    And I fetch my user info
+    Then The request fails with status 403
+
+  Scenario: Administrators cannot disable themselves
+    Given I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I disable the user "admin"
+    Then The request fails with status 409
+
+  Scenario: Logging a user out everywhere
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I revoke every session of "lifter"
+    And I go back to the session I set aside
+    And I fetch my user info
+    Then The request fails with status 401
+
+  Scenario: Resetting a password for a user
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I create a password reset token for "lifter"
+    And The password is reset to "tr0ub4dor and 3" with the token from the administrator
+    And I log in with username "lifter" and password "tr0ub4dor and 3"
+    Then The request succeeds with status 204
+
+  Scenario: Resetting a password to the username
+    Given I am registered with username "powerlifter" and password "correct horse battery"
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I create a password reset token for "powerlifter"
+    And The password is reset to "powerlifter" with the token from the administrator
+    Then The request fails with status 422
+    When The password is reset to "tr0ub4dor and 3" with the token from the administrator
+    Then The request succeeds with status 204
+
+  Scenario: Counting owners
+    Given I am an anonymous user
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I count owners
+    Then There is 1 registered and 1 anonymous owner
+
+  Scenario: Checking and repairing program integrity
+    Given I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    And A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I have a "bench press" set for Monday
+    And The sets of my program have gaps in their order
+    And The first set of my program is in a week that does not exist
+    When I check program integrity
+    Then 1 set is without a week and 3 are out of order
+    When I repair program integrity
+    Then 1 set was without a week and 2 were out of order
+    When I check program integrity
+    Then No program has integrity problems
+    When I create a "bench press" set for Monday
+    And I fetch my program summary
+    Then My program has ["squat", "bench press", "bench press"] on Monday
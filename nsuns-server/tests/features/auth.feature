@@ -0,0 +1,266 @@
+Feature: Authentication
+
+  Scenario: Registering a new user
+    When I register with username "lifter" and password "correct horse battery"
+    And I fetch my user info
+    Then I am logged in as "lifter"
+
+  Scenario: Registering with a taken username
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I register with username "lifter" and password "another password"
+    Then The request fails with status 409
+
+  Scenario: Registering with a weak password
+    When I register with username "lifter" and password "short"
+    Then The request fails with status 422
+
+  Scenario: Claiming an anonymous account keeps its data
+    Given I am an anonymous user
+    And A profile with name "test" exists
+    When I claim my account with username "lifter" and password "correct horse battery"
+    And I fetch my user info
+    And I fetch all profiles
+    Then I am logged in as "lifter"
+    And My profile has the name "test"
+
+  Scenario: Claiming an account that is already registered
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I claim my account with username "lifter2" and password "correct horse battery"
+    Then The request fails with status 409
+
+  Scenario: Changing my password
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I change my password from "correct horse battery" to "tr0ub4dor and 3"
+    And I log in with username "lifter" and password "tr0ub4dor and 3"
+    Then The request succeeds with status 204
+
+  Scenario: Changing my password requires the current password
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I change my password from "wrong password" to "tr0ub4dor and 3"
+    Then The request fails with status 403
+
+  Scenario: Changing my password to my username
+    Given I am registered with username "powerlifter" and password "correct horse battery"
+    When I change my password from "correct horse battery" to "PowerLifter"
+    Then The request fails with status 422
+
+  Scenario: Logging in with an old password
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I change my password from "correct horse battery" to "tr0ub4dor and 3"
+    And I log in with username "lifter" and password "correct horse battery"
+    Then The request fails with status 401
+
+  Scenario: Resetting a password with an invalid token
+    When I reset my password to "tr0ub4dor and 3" with token "not-a-token"
+    Then The request fails with status 400
+
+  Scenario: Listing my sessions
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I log in with username "lifter" and password "correct horse battery"
+    And I list my sessions
+    Then I have 2 active sessions
+    And My current session was used by "nsuns-tests"
+
+  Scenario: Revoking another session
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I log in with username "lifter" and password "correct horse battery"
+    And I list my sessions
+    And I revoke my other session
+    And I list my sessions
+    Then I have 1 active session
+
+  Scenario: Logging out everywhere
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I log out everywhere
+    And I fetch my user info
+    Then The request fails with status 401
+
+  Scenario: Anonymous users cannot manage sessions
+    Given I am an anonymous user
+    When I list my sessions
+    Then The request fails with status 403
+
+  Scenario: Using a read-write access token
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have a read-write access token named "importer"
+    When I authenticate with my access token
+    And I create a profile with name "test"
+    And I fetch my user info
+    And I fetch all profiles
+    Then I am logged in as "lifter"
+    And My profile has the name "test"
+
+  Scenario: Read-only access tokens cannot make changes
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have a read-only access token named "dashboard"
+    When I authenticate with my access token
+    And I try to create a profile with name "test"
+    Then The request fails with status 403
+
+  Scenario: Using a revoked access token
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have a read-write access token named "importer"
+    When I revoke my access token
+    And I authenticate with my access token
+    And I fetch my user info
+    Then The request fails with status 401
+
+  Scenario: Changing my password revokes my access tokens
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have a read-write access token named "importer"
+    When I change my password from "correct horse battery" to "tr0ub4dor and 3"
+    And I authenticate with my access token
+    And I fetch my user info
+    Then The request fails with status 401
+
+  Scenario: Access tokens cannot create more access tokens
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have a read-write access token named "importer"
+    When I authenticate with my access token
+    And I create a read-write access token named "escalated"
+    Then The request fails with status 403
+
+  Scenario: Anonymous users cannot create access tokens
+    Given I am an anonymous user
+    When I create a read-only access token named "dashboard"
+    Then The request fails with status 403
+
+  Scenario: Logging in after too many failed attempts
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I fail to log in as "lifter" 3 times
+    And I log in with username "lifter" and password "correct horse battery"
+    Then I am asked to retry later
+
+  Scenario: Logging in with an identity provider
+    When I log in with the identity provider as "alice"
+    And I fetch my user info
+    Then I am logged in as "alice"
+
+  Scenario: Logging in again with the same identity
+    Given I am logged in with the identity provider as "alice"
+    And A profile with name "test" exists
+    When I log in with the identity provider as "alice"
+    And I fetch all profiles
+    Then My profile has the name "test"
+
+  Scenario: Disabled users cannot log in with an identity provider
+    Given I am logged in with the identity provider as "alice"
+    And I set my session aside
+    And I am registered with username "admin" and password "correct horse battery"
+    And "admin" is an administrator
+    When I disable the user "alice"
+    And I log in with the identity provider as "alice"
+    Then The request fails with status 403
+
+  Scenario: Completing a login that was not started by this browser
+    When I complete an identity provider login with state "forged"
+    Then The request fails with status 400
+
+  Scenario: Logging in with two-factor authentication
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have enabled two-factor authentication
+    When I log in with username "lifter" and password "correct horse battery"
+    Then I am asked for a second factor
+    When I enter the code from my authenticator
+    And I list my sessions
+    And I fetch my user info
+    Then I am logged in as "lifter"
+    And I have 1 active session
+
+  Scenario: Recovery codes can only be used once
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have enabled two-factor authentication
+    When I log in with username "lifter" and password "correct horse battery"
+    And I enter recovery code 1
+    Then The request succeeds with status 204
+    When I log in with username "lifter" and password "correct horse battery"
+    And I enter recovery code 1
+    Then The request fails with status 403
+
+  Scenario: Login challenges are discarded after too many wrong codes
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have enabled two-factor authentication
+    When I log in with username "lifter" and password "correct horse battery"
+    And I enter a wrong code 5 times
+    And I enter recovery code 1
+    Then The request fails with status 401
+
+  Scenario: Disabling two-factor authentication
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have enabled two-factor authentication
+    When I disable two-factor authentication with recovery code 1
+    And I log in with username "lifter" and password "correct horse battery"
+    Then The request succeeds with status 204
+
+  Scenario: Too many wrong codes log out every session
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And I have enabled two-factor authentication
+    When I try to disable two-factor authentication with a wrong code 5 times
+    Then The request fails with status 401
+    When I disable two-factor authentication with recovery code 1
+    Then The request fails with status 401
+
+  Scenario: Publishing the session signing keys
+    When I fetch the session signing keys
+    Then The signing keys only include the public key "test-key"
+
+  Scenario: Changes without the CSRF token are rejected
+    Given I am an anonymous user
+    When I forget my CSRF token
+    And I try to create a profile with name "test"
+    Then The request fails with status 403
+
+  Scenario: Restoring an export into a new account
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I added a week to my program
+    And I have a "squat" set for Wednesday
+    And My program has the training days ["Day A", "Day B"]
+    And I have a "bench press" set for training day "Day B"
+    And I completed "Day A"
+    And I have a max of 100 in "bench press"
+    And I have 5 reps in "squat"
+    When I export my data
+    And I delete my account with password "correct horse battery"
+    And I register with username "lifter" and password "correct horse battery"
+    And I restore my exported data
+    Then The request succeeds with status 204
+    And My data matches the export
+
+  Scenario: Restoring an export with invalid data
+    Given I am registered with username "lifter" and password "correct horse battery"
+    And A profile with name "test" exists
+    And A movement with name "bench press" exists
+    And I fetch all movements
+    And I have a max of 100 in "bench press"
+    When I export my data
+    And I change every max in my exported data to be negative
+    And I restore my exported data
+    Then The request fails with status 422
+
+  Scenario: Deleting my account requires the password
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I delete my account with password "wrong password"
+    Then The request fails with status 403
+    When I log in with username "lifter" and password "correct horse battery"
+    Then The request succeeds with status 204
+
+  Scenario: Deleting my account after logging in with an identity provider
+    Given I am logged in with the identity provider as "alice"
+    When I delete my account without a password
+    Then The request succeeds with status 204
+    When I fetch my user info
+    Then The request fails with status 401
+
+  Scenario: Deleted accounts cannot log in
+    Given I am registered with username "lifter" and password "correct horse battery"
+    When I delete my account with password "correct horse battery"
+    Then The request succeeds with status 204
+    When I log in with username "lifter" and password "correct horse battery"
+    Then The request fails with status 401
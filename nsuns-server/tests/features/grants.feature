@@ -0,0 +1,55 @@
+Feature: Sharing profiles
+
+  Background:
+    Given I am registered with username "coach" and password "correct horse battery"
+    And I set my session aside
+    And I am registered with username "athlete" and password "correct horse battery"
+    And A profile with name "Athlete" exists
+    And A movement with name "Squat" exists
+    And I fetch all movements
+    And I have a max of 200 in "Squat"
+    And A program with name "Strength" exists
+    And I have a "Squat" set for Monday
+
+  Scenario: Profiles that are not shared are hidden
+    When I go back to the session I set aside
+    And I fetch all movements for my profile
+    Then I have 0 movements
+    When I fetch my maxes
+    Then I do not see any maxes
+    When I fetch my programs
+    Then My program does not exist
+
+  Scenario: Read-only access
+    When I share the profile with "coach" with read access
+    And I go back to the session I set aside
+    And I fetch all movements for my profile
+    Then I have 1 movements
+    When I fetch the profiles shared with me
+    Then The profile "Athlete" is shared with me with read access
+    When I fetch my maxes
+    Then My "Squat" max is 200
+    When I fetch my program summary
+    Then My program has ["Squat"] on Monday
+    When I try to log a max of 205 in "Squat"
+    Then The request fails with status 403
+
+  Scenario: Read-write access
+    When I share the profile with "coach" with write access
+    And I go back to the session I set aside
+    And I fetch all movements for my profile
+    And I try to log a max of 205 in "Squat"
+    Then The request succeeds with status 201
+    When I create a "Squat" set for Tuesday
+    And I go back to the session I set aside
+    And I fetch my maxes
+    Then My "Squat" max is 205
+    When I fetch my program summary
+    Then My program has ["Squat"] on Tuesday
+
+  Scenario: Revoking access
+    When I share the profile with "coach" with write access
+    And I stop sharing the profile
+    And I go back to the session I set aside
+    And I fetch my maxes
+    Then I do not see any maxes
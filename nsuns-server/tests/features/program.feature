@@ -47,3 +47,245 @@ Feature: Programs
     When I reorder Monday from 0 to 1
     And I fetch my program summary
     Then My program has ["squat", "bench press"] on Monday
+
+  Scenario: Deleting a set keeps the order of the others
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I have a "bench press" set for Monday
+    When I fetch my program summary
+    And I delete set 0 on Monday
+    And I create a "squat" set for Monday
+    And I fetch my program summary
+    Then My program has ["squat", "bench press", "squat"] on Monday
+
+  Scenario: Moving a set to another day
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I have a "bench press" set for Tuesday
+    When I fetch my program summary
+    And I move set 1 on Monday to Tuesday at 0
+    And I fetch my program summary
+    Then My program has ["bench press"] on Monday
+    And My program has ["squat", "bench press"] on Tuesday
+
+  Scenario: Moving a set within a day
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And A movement with name "deadlift" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I have a "deadlift" set for Monday
+    When I fetch my program summary
+    And I move set 0 on Monday to Monday at 2
+    And I fetch my program summary
+    Then My program has ["squat", "deadlift", "bench press"] on Monday
+
+  Scenario: Moving a set to another program
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "first program" exists
+    And A movement with name "bench press" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    When I fetch my program summary
+    And I create a program with name "second program"
+    And I move set 0 on Monday to Friday at 0
+    And I fetch my program summary
+    Then My program has ["bench press"] on Friday
+
+  Scenario: Replacing the sets of a day
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And A movement with name "deadlift" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I have a "deadlift" set for Monday
+    When I fetch my program summary
+    And I replace Monday with ["Monday 2", "new bench press", "Monday 0"]
+    And I fetch my program summary
+    Then My program has ["deadlift", "bench press", "bench press"] on Monday
+
+  Scenario: Replacing a day only keeps sets that are on it
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Tuesday
+    When I fetch my program summary
+    And I try to replace Monday with ["new squat", "Tuesday 0"]
+    Then The request fails with status 409
+    When I fetch my program summary
+    Then My program has ["bench press"] on Monday
+    And My program has ["squat"] on Tuesday
+
+  Scenario: Creating sets in bulk
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And A movement with name "deadlift" exists
+    And I fetch all movements
+    And I have a "deadlift" set for Monday
+    When I create ["squat", "bench press", "squat"] sets for Monday in bulk
+    And I fetch my program summary
+    Then My program has ["deadlift", "squat", "bench press", "squat"] on Monday
+
+  Scenario: Creating sets in bulk creates nothing if one set is invalid
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    When I try to create ["squat", "bench press", "squat"] sets for Monday in bulk with an invalid set 1
+    Then The request fails with status 422
+    And The error is about set 1
+    When I fetch my program summary
+    Then My program has [] on Monday
+
+  Scenario: Adding a week
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    When I add a week to my program
+    And I create a "squat" set for Monday
+    And I fetch my program summary
+    Then My program has ["squat"] on Monday
+    And My program has 2 weeks
+    When I switch to week 0
+    And I fetch my program summary
+    Then My program has ["bench press"] on Monday
+
+  Scenario: Cloning a program into another profile
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I added a week to my program
+    And I have a "squat" set for Friday
+    And A profile with name "other" exists
+    When I clone my program into the current profile with name "next cycle"
+    And I fetch my programs
+    Then My program has the name "next cycle"
+    When I fetch my program summary
+    Then My program has ["bench press", "squat"] on Monday
+    And My program has 2 weeks
+    When I switch to week 1
+    And I fetch my program summary
+    Then My program has ["squat"] on Friday
+
+  Scenario: Exporting a program and importing it for another user
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    And I added a week to my program
+    And I have a "squat" set for Friday
+    When I export my program
+    Then The exported program does not name a profile
+    Given I am an anonymous user
+    And A profile with name "other" exists
+    When I import the exported program
+    And I fetch all movements
+    And I fetch my programs
+    Then My program has the name "test program"
+    When I fetch my program summary
+    Then My program has ["bench press", "squat"] on Monday
+    And My program has 2 weeks
+    When I switch to week 1
+    And I fetch my program summary
+    Then My program has ["squat"] on Friday
+
+  Scenario: Templates from newer versions are rejected
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    When I export my program
+    And I try to import the exported program as version 99
+    Then The request fails with status 422
+
+  Scenario: Copying a week
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I have a "squat" set for Monday
+    When I copy week 0 into a new week
+    And I reorder Monday from 0 to 1
+    And I fetch my program summary
+    Then My program has ["squat", "bench press"] on Monday
+    When I switch to week 0
+    And I fetch my program summary
+    Then My program has ["bench press", "squat"] on Monday
+
+  Scenario: Deleting a week
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And I have a "bench press" set for Monday
+    And I added a week to my program
+    And I have a "squat" set for Monday
+    When I delete week 0 of my program
+    And I switch to week 0
+    And I fetch my program summary
+    Then My program has ["squat"] on Monday
+    And My program has 1 week
+
+  Scenario: A program keeps at least one week
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    When I try to delete week 0 of my program
+    Then The request fails with status 409
+
+  Scenario: Creating a multi-week program from a template
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    When I create a program from a template with 3 weeks of "deadlift" on Friday
+    And I fetch all movements
+    And I switch to week 2
+    And I fetch my program summary
+    Then My program has ["deadlift"] on Friday
+    And My program has 3 weeks
@@ -0,0 +1,26 @@
+Feature: Quotas
+
+  Scenario: Anonymous owners are limited
+    Given I am an anonymous user
+    And A profile with name "one" exists
+    And A profile with name "two" exists
+    And A profile with name "three" exists
+    When I try to create a profile with name "four"
+    Then The request fails with status 403
+
+  Scenario: Registered owners have a higher limit
+    Given I am registered with username "test" and password "password"
+    And A profile with name "one" exists
+    And A profile with name "two" exists
+    And A profile with name "three" exists
+    When I try to create a profile with name "four"
+    Then The request succeeds with status 201
+
+  Scenario: Registering raises the limit
+    Given I am an anonymous user
+    And A profile with name "one" exists
+    And A profile with name "two" exists
+    And A profile with name "three" exists
+    When I claim my account with username "test" and password "password"
+    And I try to create a profile with name "four"
+    Then The request succeeds with status 201
@@ -0,0 +1,37 @@
+Feature: Templates
+
+  Scenario: Listing the built-in templates
+    Given I am an anonymous user
+    When I fetch the templates
+    Then The template "nsuns-4day" has 4 days a week
+    And The template "nsuns-5day" has 5 days a week
+    And The template "nsuns-5day-weekend" has 5 days a week
+    And The template "nsuns-6day" has 6 days a week
+
+  Scenario: Creating a program from a built-in template
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A movement with name "squat" exists
+    When I fetch the template "nsuns-4day"
+    And I import the template
+    And I fetch all movements
+    Then I have 7 movements
+    When I fetch my programs
+    Then My program has the name "nSuns 4-Day"
+    When I fetch my program summary
+    Then My program has 9 "squat" sets on Tuesday
+    And My program has 8 "Sumo Deadlift" sets on Tuesday
+
+  Scenario: Creating a program from a built-in template twice
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    When I fetch the template "nsuns-5day"
+    And I import the template
+    And I import the template
+    And I fetch all movements
+    Then I have 7 movements
+
+  Scenario: Fetching a template that does not exist
+    Given I am an anonymous user
+    When I fetch the template "nsuns-7day"
+    Then The request fails with status 404
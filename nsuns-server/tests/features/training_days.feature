@@ -0,0 +1,101 @@
+Feature: Training days
+
+  Scenario: Adding training days
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    When I add the training days ["Day A", "Day B"] to my program
+    And I fetch my training days
+    Then My training days are ["Day A", "Day B"]
+
+  Scenario: Reordering training days
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And My program has the training days ["Day A", "Day B", "Day C"]
+    When I move training day 2 to 0
+    And I fetch my training days
+    Then My training days are ["Day C", "Day A", "Day B"]
+
+  Scenario: Deleting a training day
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And My program has the training days ["Day A", "Day B", "Day C"]
+    When I delete the training day "Day B"
+    And I fetch my training days
+    Then My training days are ["Day A", "Day C"]
+
+  Scenario: Rotating through training days
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And My program has the training days ["Day A", "Day B", "Day C"]
+    When I fetch the next training day
+    Then The next training day is "Day A"
+    When I complete "Day A"
+    And I complete "Day B"
+    And I fetch the next training day
+    Then The next training day is "Day C"
+    When I complete "Day C"
+    And I fetch the next training day of my profile
+    Then The next training day is "Day A"
+
+  Scenario: The next training day needs a program
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    When I fetch the next training day of my profile
+    Then There is no next training day
+
+  Scenario: Adding sets to a training day
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And My program has the training days ["Day A", "Day B"]
+    When I create a "bench press" set for training day "Day A"
+    And I create a "squat" set for training day "Day A"
+    And I create a "squat" set for training day "Day B"
+    And I fetch my program summary
+    Then My program has ["bench press", "squat"] on training day "Day A"
+    And My program has ["squat"] on training day "Day B"
+    And My program has [] on Monday
+
+  Scenario: Reordering sets of a training day
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And My program has the training days ["Day A"]
+    And I have a "bench press" set for training day "Day A"
+    And I have a "squat" set for training day "Day A"
+    When I reorder training day "Day A" from 0 to 1
+    And I fetch my program summary
+    Then My program has ["squat", "bench press"] on training day "Day A"
+
+  Scenario: Copying a week with training days
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    And A program with name "test program" exists
+    And A movement with name "bench press" exists
+    And A movement with name "squat" exists
+    And I fetch all movements
+    And My program has the training days ["Day A"]
+    And I have a "bench press" set for training day "Day A"
+    And I have a "squat" set for training day "Day A"
+    When I copy week 0 into a new week
+    And I fetch my program summary
+    Then My program has ["bench press", "squat"] on training day "Day A"
+
+  Scenario: Creating a program with training days from a template
+    Given I am an anonymous user
+    Given A profile with name "test" exists
+    When I create a program from a template with training days ["Day A", "Day B"] and "deadlift" on "Day B"
+    And I fetch all movements
+    And I fetch my program summary
+    Then My program has [] on training day "Day A"
+    And My program has ["deadlift"] on training day "Day B"